@@ -0,0 +1,161 @@
+//! Authenticating as a GitHub App installation instead of with a personal
+//! token, see [`crate::Cleaner::new_app`]. Installation tokens expire after
+//! an hour, so [`Auth`] renews them for runs outlasting one.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use log::info;
+use octocrab::models::{AppId, InstallationToken};
+use octocrab::Octocrab;
+use tokio::sync::Mutex;
+
+/// Renew an installation token once it expires within this many minutes,
+/// so no request is sent with one about to expire.
+const RENEW_MARGIN_MINUTES: i64 = 5;
+
+/// What authenticating as an installation of a GitHub App takes.
+#[derive(Clone)]
+pub struct AppCredentials {
+  pub app_id: u64,
+  /// The PEM private key generated in the App's settings
+  pub key: jsonwebtoken::EncodingKey,
+  /// The number at the end of the installation's settings URL
+  pub installation_id: u64,
+}
+
+impl AppCredentials {
+  /// Reads the PEM private `key`, failing with a message ready to be shown.
+  pub fn new(app_id: u64, key: &[u8], installation_id: u64) -> Result<Self, String> {
+    let key = jsonwebtoken::EncodingKey::from_rsa_pem(key)
+      .map_err(|e| format!("not an RSA private key in PEM format: {e}"))?;
+    Ok(Self {
+      app_id,
+      key,
+      installation_id,
+    })
+  }
+}
+
+/// A client and the token it sends.
+#[derive(Clone)]
+pub(crate) struct Client {
+  pub(crate) gh: Arc<Octocrab>,
+  pub(crate) token: String,
+}
+
+/// Where deletions get their [`Client`] from.
+pub(crate) enum Auth {
+  /// A personal token, never renewed
+  Token(Client),
+  App(Box<Installation>),
+}
+
+pub(crate) struct Installation {
+  /// Authenticated as the App itself, only to create installation tokens
+  app: Octocrab,
+  id: u64,
+  /// Sent by the clients of renewed tokens too
+  api_version: String,
+  current: Mutex<(Client, DateTime<Utc>)>,
+}
+
+impl Installation {
+  pub(crate) async fn new(
+    credentials: AppCredentials,
+    api_version: &str,
+  ) -> octocrab::Result<Self> {
+    let app = Octocrab::builder()
+      .app(AppId(credentials.app_id), credentials.key)
+      .add_header(crate::api_version_header(), api_version.to_string())
+      .build()?;
+    let current = create_token(&app, credentials.installation_id, api_version).await?;
+    Ok(Self {
+      app,
+      id: credentials.installation_id,
+      api_version: api_version.to_string(),
+      current: Mutex::new(current),
+    })
+  }
+}
+
+impl Auth {
+  /// The client to send with, renewing an installation token first if it
+  /// is about to expire.
+  pub(crate) async fn client(&self) -> octocrab::Result<Client> {
+    match self {
+      Auth::Token(client) => Ok(client.clone()),
+      Auth::App(installation) => {
+        let mut current = installation.current.lock().await;
+        if current.1 - Utc::now() < Duration::minutes(RENEW_MARGIN_MINUTES) {
+          *current = create_token(
+            &installation.app,
+            installation.id,
+            &installation.api_version,
+          )
+          .await?;
+          info!(
+            "Renewed the installation token, it expires at {}",
+            current.1
+          );
+        }
+        Ok(current.0.clone())
+      }
+    }
+  }
+
+  /// Renews an installation token after `rejected` got a `401`, unless
+  /// another request renewed it already. `false` for a personal token,
+  /// which can't be renewed.
+  pub(crate) async fn renew(&self, rejected: &Client) -> octocrab::Result<bool> {
+    match self {
+      Auth::Token(_) => Ok(false),
+      Auth::App(installation) => {
+        let mut current = installation.current.lock().await;
+        if current.0.token == rejected.token {
+          *current = create_token(
+            &installation.app,
+            installation.id,
+            &installation.api_version,
+          )
+          .await?;
+          info!(
+            "Renewed the rejected installation token, it expires at {}",
+            current.1
+          );
+        }
+        Ok(true)
+      }
+    }
+  }
+
+  pub(crate) fn is_app(&self) -> bool {
+    matches!(self, Auth::App(_))
+  }
+}
+
+/// Creates an installation token and a client sending it, with the time it
+/// expires at.
+async fn create_token(
+  app: &Octocrab,
+  id: u64,
+  api_version: &str,
+) -> octocrab::Result<(Client, DateTime<Utc>)> {
+  let created: InstallationToken = app
+    .post(format!("app/installations/{id}/access_tokens"), None::<&()>)
+    .await?;
+  // GitHub says an hour, assume it if the answer can't be read
+  let expires_at = created
+    .expires_at
+    .and_then(|at| at.parse().ok())
+    .unwrap_or_else(|| Utc::now() + Duration::hours(1));
+  let gh = Octocrab::builder()
+    .personal_token(created.token.clone())
+    .add_header(crate::api_version_header(), api_version.to_string())
+    .build()?;
+  let client = Client {
+    gh: Arc::new(gh),
+    token: created.token,
+  };
+  Ok((client, expires_at))
+}
@@ -0,0 +1,47 @@
+//! A JSON lines file with a line per delete attempt, see
+//! [`crate::Cleaner::with_attempt_log`].
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use log::warn;
+use serde::Serialize;
+
+/// A single delete request.
+#[derive(Serialize, Debug)]
+pub struct Attempt<'a> {
+  /// The deleted thing, as in the progress messages
+  pub name: &'a str,
+  /// Starts at 1
+  pub attempt: u32,
+  /// HTTP status, `None` if there was no response, e.g. on timeout
+  pub status: Option<u16>,
+  /// `X-GitHub-Request-Id` of the response, to quote to GitHub Support
+  pub request_id: Option<&'a str>,
+  pub latency_ms: u128,
+  pub error: Option<String>,
+}
+
+pub struct AttemptLog(Mutex<BufWriter<File>>);
+
+impl AttemptLog {
+  pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+    Ok(Self(Mutex::new(BufWriter::new(File::create(path)?))))
+  }
+
+  pub(crate) fn record(&self, attempt: &Attempt) {
+    let mut writer = self.0.lock().unwrap();
+    let written = serde_json::to_writer(&mut *writer, attempt)
+      .map_err(io::Error::from)
+      .and_then(|_| writer.write_all(b"\n"));
+    if let Err(e) = written {
+      warn!("Failed to write the attempt log: {e}");
+    }
+  }
+
+  pub fn flush(&self) -> io::Result<()> {
+    self.0.lock().unwrap().flush()
+  }
+}
@@ -0,0 +1,314 @@
+//! Per-repo lookups behind `--deep-activity`, each costing extra API calls.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use octocrab::Octocrab;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// Lookup results keyed by repo full name, so each repo is only asked once.
+pub(crate) struct Cache<V>(Mutex<HashMap<String, V>>);
+
+impl<V> Default for Cache<V> {
+  fn default() -> Self {
+    Self(Mutex::new(HashMap::new()))
+  }
+}
+
+impl<V: Clone> Cache<V> {
+  pub(crate) async fn get_or_fetch<F, Fut>(&self, key: &str, fetch: F) -> octocrab::Result<V>
+  where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = octocrab::Result<V>>,
+  {
+    if let Some(value) = self.0.lock().unwrap().get(key) {
+      return Ok(value.clone());
+    }
+    let value = fetch().await?;
+    self
+      .0
+      .lock()
+      .unwrap()
+      .insert(key.to_string(), value.clone());
+    Ok(value)
+  }
+}
+
+/// `GET`s `route`, mapping `404 Not Found` to `None`.
+pub(crate) async fn get_optional<T: DeserializeOwned>(
+  gh: &Octocrab,
+  route: impl AsRef<str>,
+) -> octocrab::Result<Option<T>> {
+  let response = crate::api_trace::get(gh, route, None::<&()>).await?;
+  if response.status() == reqwest::StatusCode::NOT_FOUND {
+    return Ok(None);
+  }
+  let response = octocrab::map_github_error(response).await?;
+  octocrab::FromResponse::from_response(response)
+    .await
+    .map(Some)
+}
+
+#[derive(Deserialize, Debug)]
+struct Release {
+  created_at: Option<DateTime<Utc>>,
+  published_at: Option<DateTime<Utc>>,
+}
+
+/// Date of the latest published release, `None` if there is none.
+pub(crate) async fn latest_release(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+) -> octocrab::Result<Option<DateTime<Utc>>> {
+  let release: Option<Release> =
+    get_optional(gh, format!("repos/{owner}/{repo}/releases/latest")).await?;
+  Ok(release.and_then(|r| r.published_at.or(r.created_at)))
+}
+
+#[derive(Deserialize, Debug)]
+struct Issue {
+  updated_at: DateTime<Utc>,
+}
+
+/// When an issue or pull request of `owner/repo` was last updated, which
+/// includes every new comment, `None` if there are none.
+pub(crate) async fn latest_issue_activity(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+) -> octocrab::Result<Option<DateTime<Utc>>> {
+  let issues: Vec<Issue> = crate::api_trace::get_json(
+    gh,
+    format!("repos/{owner}/{repo}/issues"),
+    Some(&[
+      ("state", "all"),
+      ("sort", "updated"),
+      ("direction", "desc"),
+      ("per_page", "1"),
+    ]),
+  )
+  .await?;
+  Ok(issues.first().map(|i| i.updated_at))
+}
+
+#[derive(Deserialize, Debug)]
+struct TotalCount {
+  total_count: u64,
+}
+
+/// Numbers of Actions secrets and of deployment environments of
+/// `owner/repo`, `None` if the token may not read them. Reading secrets
+/// takes admin access, or the secrets permission of a fine-grained token.
+pub(crate) async fn secrets_and_environments(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+) -> octocrab::Result<Option<(u64, u64)>> {
+  let mut counts = [0; 2];
+  for (count, what) in counts.iter_mut().zip(["actions/secrets", "environments"]) {
+    let route = format!("repos/{owner}/{repo}/{what}");
+    let response = crate::api_trace::get(gh, route, None::<&()>).await?;
+    match response.status() {
+      reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::NOT_FOUND => return Ok(None),
+      _ => {}
+    }
+    let response = octocrab::map_github_error(response).await?;
+    let total: TotalCount = octocrab::FromResponse::from_response(response).await?;
+    *count = total.total_count;
+  }
+  Ok(Some((counts[0], counts[1])))
+}
+
+#[derive(Deserialize, Debug)]
+struct ActionsPermissions {
+  enabled: bool,
+}
+
+/// Whether GitHub Actions is enabled in the settings of `owner/repo`,
+/// which often isn't for abandoned repos. `None` if the token may not read
+/// them, that takes admin access.
+pub(crate) async fn actions_enabled(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+) -> octocrab::Result<Option<bool>> {
+  let route = format!("repos/{owner}/{repo}/actions/permissions");
+  let response = crate::api_trace::get(gh, route, None::<&()>).await?;
+  match response.status() {
+    reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::NOT_FOUND => return Ok(None),
+    _ => {}
+  }
+  let response = octocrab::map_github_error(response).await?;
+  let permissions: ActionsPermissions = octocrab::FromResponse::from_response(response).await?;
+  Ok(Some(permissions.enabled))
+}
+
+#[derive(Deserialize, Debug)]
+struct Account {
+  #[serde(rename = "type")]
+  kind: String,
+}
+
+/// Whether `login` is a `User` or an `Organization`, `None` if it doesn't
+/// exist.
+pub(crate) async fn account_type(gh: &Octocrab, login: &str) -> octocrab::Result<Option<String>> {
+  let account: Option<Account> = get_optional(gh, format!("users/{login}")).await?;
+  Ok(account.map(|a| a.kind))
+}
+
+/// The Action metadata file at the root of `owner/repo`, `None` if there
+/// is none, so it is no Action.
+pub(crate) async fn action_file(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+) -> octocrab::Result<Option<&'static str>> {
+  for file in ["action.yml", "action.yaml"] {
+    let content: Option<serde_json::Value> =
+      get_optional(gh, format!("repos/{owner}/{repo}/contents/{file}")).await?;
+    if content.is_some() {
+      return Ok(Some(file));
+    }
+  }
+  Ok(None)
+}
+
+/// Whether `owner/repo` has a README GitHub recognizes, anywhere it looks
+/// for one. Only `404 Not Found` means there is none, other errors fail.
+pub(crate) async fn has_readme(gh: &Octocrab, owner: &str, repo: &str) -> octocrab::Result<bool> {
+  let readme: Option<serde_json::Value> =
+    get_optional(gh, format!("repos/{owner}/{repo}/readme")).await?;
+  Ok(readme.is_some())
+}
+
+/// The repo a fork was created from.
+#[derive(Debug, Clone)]
+pub(crate) struct Parent {
+  pub(crate) owner: String,
+  pub(crate) name: String,
+  pub(crate) default_branch: Option<String>,
+}
+
+/// The repo `owner/repo` was forked from, `None` if it is no fork. Only the
+/// single repo endpoint returns `parent`, lists omit it.
+pub(crate) async fn parent(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+) -> octocrab::Result<Option<Parent>> {
+  let repo = gh.repos(owner, repo).get().await?;
+  Ok(repo.parent.and_then(|p| {
+    Some(Parent {
+      owner: p.owner?.login,
+      name: p.name,
+      default_branch: p.default_branch,
+    })
+  }))
+}
+
+#[derive(Deserialize, Debug)]
+struct Comparison {
+  ahead_by: u64,
+  behind_by: u64,
+}
+
+/// Numbers of commits `branch` of the fork `owner/...` has that the default
+/// branch of `parent` doesn't, and the other way round.
+pub(crate) async fn compare_with_parent(
+  gh: &Octocrab,
+  parent: &Parent,
+  owner: &str,
+  branch: &str,
+) -> octocrab::Result<(u64, u64)> {
+  let base = parent.default_branch.as_deref().unwrap_or("HEAD");
+  let route = format!(
+    "repos/{}/{}/compare/{base}...{owner}:{branch}",
+    parent.owner, parent.name
+  );
+  let comparison: Comparison = crate::api_trace::get_json(gh, route, None::<&()>).await?;
+  Ok((comparison.ahead_by, comparison.behind_by))
+}
+
+/// Counts the items of a list endpoint with a single request, by asking for
+/// one item per page and reading the number of the `last` page from the
+/// `Link` header, with `query` narrowing the list. A `409 Conflict`,
+/// returned for empty repos, counts as 0.
+pub(crate) async fn count(
+  gh: &Octocrab,
+  route: impl AsRef<str>,
+  query: &[(&str, &str)],
+) -> octocrab::Result<u64> {
+  let mut query = query.to_vec();
+  query.push(("per_page", "1"));
+  let response = crate::api_trace::get(gh, route, Some(&query)).await?;
+  if response.status() == reqwest::StatusCode::CONFLICT {
+    return Ok(0);
+  }
+  let response = octocrab::map_github_error(response).await?;
+  let last = response
+    .headers()
+    .get(reqwest::header::LINK)
+    .and_then(|link| link.to_str().ok())
+    .and_then(last_page);
+  if let Some(last) = last {
+    return Ok(last);
+  }
+  let items: Vec<serde_json::Value> = octocrab::FromResponse::from_response(response).await?;
+  Ok(items.len() as u64)
+}
+
+/// Parses the page number of the `rel="last"` entry of a `Link` header.
+pub(crate) fn last_page(link: &str) -> Option<u64> {
+  link
+    .split(',')
+    .find(|part| part.contains(r#"rel="last""#))?
+    .split(['?', '&', '>'])
+    .find_map(|param| param.strip_prefix("page="))?
+    .parse()
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn cache_fetches_each_key_once() {
+    let cache = Cache::default();
+    let fetched = std::sync::atomic::AtomicUsize::new(0);
+    let fetch = |value: u32| {
+      fetched.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+      async move { Ok(value) }
+    };
+    assert_eq!(cache.get_or_fetch("me/a", || fetch(1)).await.unwrap(), 1);
+    assert_eq!(cache.get_or_fetch("me/a", || fetch(2)).await.unwrap(), 1);
+    assert_eq!(cache.get_or_fetch("me/b", || fetch(3)).await.unwrap(), 3);
+    assert_eq!(fetched.into_inner(), 2);
+  }
+
+  #[test]
+  fn last_page_of_link_header() {
+    let link = "<https://api.github.com/repositories/1/commits?per_page=1&page=2>; rel=\"next\", \
+      <https://api.github.com/repositories/1/commits?per_page=1&page=341>; rel=\"last\"";
+    assert_eq!(last_page(link), Some(341));
+    let first = "<https://api.github.com/repositories/1/branches?page=7&per_page=1>; rel=\"last\"";
+    assert_eq!(last_page(first), Some(7));
+  }
+
+  #[test]
+  fn last_page_missing() {
+    // The last page itself only links back
+    let link = "<https://api.github.com/repositories/1/commits?per_page=1&page=1>; rel=\"first\", \
+      <https://api.github.com/repositories/1/commits?per_page=1&page=340>; rel=\"prev\"";
+    assert_eq!(last_page(link), None);
+    assert_eq!(last_page(""), None);
+    assert_eq!(
+      last_page("<https://api.github.com/x?page=x>; rel=\"last\""),
+      None
+    );
+  }
+}
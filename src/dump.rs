@@ -0,0 +1,25 @@
+//! Saving filtered candidates to a JSON file and reading them back, so
+//! filtering and selection can happen in separate runs.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use octocrab::models::Repository;
+
+use crate::Candidate;
+
+/// Writes the repos of `candidates` with all their metadata as a JSON array.
+pub fn write(path: impl AsRef<Path>, candidates: &[Candidate]) -> io::Result<()> {
+  let repos: Vec<_> = candidates.iter().map(|c| &c.repo).collect();
+  let mut writer = BufWriter::new(File::create(path)?);
+  serde_json::to_writer_pretty(&mut writer, &repos)?;
+  writer.write_all(b"\n")?;
+  writer.flush()
+}
+
+/// Reads repos written by [`write`].
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<Repository>> {
+  let reader = BufReader::new(File::open(path)?);
+  Ok(serde_json::from_reader(reader)?)
+}
@@ -0,0 +1,631 @@
+//! The `--filter-expr` language, boolean logic over repo fields for what the
+//! simple flags can't express.
+//!
+//! ```text
+//! expr    = and ("||" and)*
+//! and     = unary ("&&" unary)*
+//! unary   = "!" unary | compare
+//! compare = operand (("==" | "!=" | "<" | "<=" | ">" | ">=") operand)?
+//! operand = FIELD | NUMBER | "STRING" | true | false | "(" expr ")"
+//! ```
+//!
+//! Fields are
+//! - numbers: `stars`, `forks`, `watchers`, `size` (KB), `issues` (open ones),
+//!   `subscribers` (who watch it, only listed with `--fetch-details`, 0 otherwise)
+//! - conditions: `fork`, `archived`, `private`, `template`
+//! - text: `name`, `owner`, `language`, `visibility`, compared ignoring case
+//! - dates: `pushed`, `created`, `updated`, compared with a number as a
+//!   year, like `pushed < 2022`, or with a `"YYYY-MM-DD"` string. A repo
+//!   without the date matches no comparison.
+//!
+//! Everything is checked when parsing, so a typo fails before listing
+//! anything, with the column of the offending token.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use octocrab::models::Repository;
+
+/// A parsed `--filter-expr`, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct FilterExpr {
+  root: Node,
+}
+
+impl FilterExpr {
+  /// Whether `repo` satisfies the expression.
+  pub fn matches(&self, repo: &Repository) -> bool {
+    matches!(self.root.eval(repo), Value::Bool(true))
+  }
+}
+
+impl FromStr for FilterExpr {
+  type Err = ParseError;
+
+  fn from_str(source: &str) -> Result<Self, Self::Err> {
+    let error = |column, message| ParseError {
+      expr: source.to_string(),
+      column,
+      message,
+    };
+    let tokens = tokenize(source).map_err(|(column, message)| error(column, message))?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let root = parser
+      .expr()
+      .and_then(|root| {
+        let (token, column) = parser.peek();
+        match token {
+          Token::End => Ok(root),
+          token => Err((column, format!("expected `&&` or `||`, found {token}"))),
+        }
+      })
+      .and_then(|root| root.expect(Kind::Bool, "the expression"))
+      .map_err(|(column, message)| error(column, message))?;
+    Ok(FilterExpr { root })
+  }
+}
+
+/// Why a `--filter-expr` is invalid, displayed with the expression and a
+/// caret under the offending token.
+#[derive(Debug, thiserror::Error)]
+pub struct ParseError {
+  expr: String,
+  /// 1-based, in chars
+  pub column: usize,
+  pub message: String,
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let pad = " ".repeat(self.column - 1);
+    write!(
+      f,
+      "column {}: {}\n  {}\n  {pad}^",
+      self.column, self.message, self.expr
+    )
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+  Stars,
+  Forks,
+  Watchers,
+  Subscribers,
+  Size,
+  Issues,
+  Fork,
+  Archived,
+  Private,
+  Template,
+  Name,
+  Owner,
+  Language,
+  Visibility,
+  Pushed,
+  Created,
+  Updated,
+}
+
+const FIELDS: &[(&str, Field)] = &[
+  ("stars", Field::Stars),
+  ("forks", Field::Forks),
+  ("watchers", Field::Watchers),
+  ("subscribers", Field::Subscribers),
+  ("size", Field::Size),
+  ("issues", Field::Issues),
+  ("fork", Field::Fork),
+  ("archived", Field::Archived),
+  ("private", Field::Private),
+  ("template", Field::Template),
+  ("name", Field::Name),
+  ("owner", Field::Owner),
+  ("language", Field::Language),
+  ("visibility", Field::Visibility),
+  ("pushed", Field::Pushed),
+  ("created", Field::Created),
+  ("updated", Field::Updated),
+];
+
+impl Field {
+  fn kind(self) -> Kind {
+    match self {
+      Field::Stars
+      | Field::Forks
+      | Field::Watchers
+      | Field::Subscribers
+      | Field::Size
+      | Field::Issues => Kind::Number,
+      Field::Fork | Field::Archived | Field::Private | Field::Template => Kind::Bool,
+      Field::Name | Field::Owner | Field::Language | Field::Visibility => Kind::Text,
+      Field::Pushed | Field::Created | Field::Updated => Kind::Date,
+    }
+  }
+
+  fn value(self, repo: &Repository) -> Value {
+    let number = |n: Option<u32>| Value::Number(f64::from(n.unwrap_or(0)));
+    let text = |s: Option<&str>| Value::Text(s.unwrap_or_default().to_string());
+    match self {
+      Field::Stars => number(repo.stargazers_count),
+      Field::Forks => number(repo.forks_count),
+      Field::Watchers => number(repo.watchers_count),
+      Field::Subscribers => Value::Number(repo.subscribers_count.unwrap_or(0) as f64),
+      Field::Size => number(repo.size),
+      Field::Issues => number(repo.open_issues_count),
+      Field::Fork => Value::Bool(repo.fork.unwrap_or(false)),
+      Field::Archived => Value::Bool(repo.archived.unwrap_or(false)),
+      Field::Private => Value::Bool(repo.private.unwrap_or(false)),
+      Field::Template => Value::Bool(repo.is_template.unwrap_or(false)),
+      Field::Name => text(Some(&repo.name)),
+      Field::Owner => text(repo.owner.as_ref().map(|o| o.login.as_str())),
+      Field::Language => text(repo.language.as_ref().and_then(|l| l.as_str())),
+      Field::Visibility => text(repo.visibility.as_deref()),
+      Field::Pushed => Value::Date(repo.pushed_at),
+      Field::Created => Value::Date(repo.created_at),
+      Field::Updated => Value::Date(repo.updated_at),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+  Bool,
+  Number,
+  Text,
+  Date,
+}
+
+impl fmt::Display for Kind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Kind::Bool => "a condition",
+      Kind::Number => "a number",
+      Kind::Text => "text",
+      Kind::Date => "a date",
+    })
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+  Eq,
+  Ne,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+}
+
+impl Op {
+  fn holds(self, ordering: Ordering) -> bool {
+    match self {
+      Op::Eq => ordering == Ordering::Equal,
+      Op::Ne => ordering != Ordering::Equal,
+      Op::Lt => ordering == Ordering::Less,
+      Op::Le => ordering != Ordering::Greater,
+      Op::Gt => ordering == Ordering::Greater,
+      Op::Ge => ordering != Ordering::Less,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+  Field(Field),
+  Literal(Value),
+  Not(Box<Node>),
+  And(Box<Node>, Box<Node>),
+  Or(Box<Node>, Box<Node>),
+  Compare(Box<Node>, Op, Box<Node>),
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+  Bool(bool),
+  Number(f64),
+  Text(String),
+  Date(Option<DateTime<Utc>>),
+}
+
+impl Node {
+  fn eval(&self, repo: &Repository) -> Value {
+    let truthy = |node: &Node| matches!(node.eval(repo), Value::Bool(true));
+    match self {
+      Node::Field(field) => field.value(repo),
+      Node::Literal(value) => value.clone(),
+      Node::Not(node) => Value::Bool(!truthy(node)),
+      Node::And(a, b) => Value::Bool(truthy(a) && truthy(b)),
+      Node::Or(a, b) => Value::Bool(truthy(a) || truthy(b)),
+      Node::Compare(a, op, b) => {
+        let ordering = match (a.eval(repo), b.eval(repo)) {
+          (Value::Number(a), Value::Number(b)) => a.partial_cmp(&b),
+          (Value::Text(a), Value::Text(b)) => Some(a.to_lowercase().cmp(&b.to_lowercase())),
+          (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(&b)),
+          (Value::Date(Some(a)), Value::Date(Some(b))) => Some(a.cmp(&b)),
+          (Value::Date(Some(a)), Value::Number(year)) => f64::from(a.year()).partial_cmp(&year),
+          (Value::Number(year), Value::Date(Some(b))) => year.partial_cmp(&f64::from(b.year())),
+          _ => None,
+        };
+        Value::Bool(ordering.is_some_and(|o| op.holds(o)))
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Ident(String),
+  Number(f64),
+  Text(String),
+  Op(Op),
+  And,
+  Or,
+  Not,
+  Open,
+  Close,
+  End,
+}
+
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Token::Ident(name) => write!(f, "`{name}`"),
+      Token::Number(n) => write!(f, "`{n}`"),
+      Token::Text(s) => write!(f, "\"{s}\""),
+      Token::Op(op) => f.write_str(match op {
+        Op::Eq => "`==`",
+        Op::Ne => "`!=`",
+        Op::Lt => "`<`",
+        Op::Le => "`<=`",
+        Op::Gt => "`>`",
+        Op::Ge => "`>=`",
+      }),
+      Token::And => f.write_str("`&&`"),
+      Token::Or => f.write_str("`||`"),
+      Token::Not => f.write_str("`!`"),
+      Token::Open => f.write_str("`(`"),
+      Token::Close => f.write_str("`)`"),
+      Token::End => f.write_str("the end"),
+    }
+  }
+}
+
+/// A column and what's wrong there.
+type Error = (usize, String);
+
+/// Splits `source` into tokens with their columns, ending with
+/// [`Token::End`].
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, Error> {
+  let chars: Vec<char> = source.chars().collect();
+  let mut tokens = vec![];
+  let mut i = 0;
+  while i < chars.len() {
+    let column = i + 1;
+    let c = chars[i];
+    let next = chars.get(i + 1).copied();
+    let (token, len) = match (c, next) {
+      (c, _) if c.is_whitespace() => {
+        i += 1;
+        continue;
+      }
+      ('&', Some('&')) => (Token::And, 2),
+      ('|', Some('|')) => (Token::Or, 2),
+      ('=', Some('=')) => (Token::Op(Op::Eq), 2),
+      ('!', Some('=')) => (Token::Op(Op::Ne), 2),
+      ('<', Some('=')) => (Token::Op(Op::Le), 2),
+      ('>', Some('=')) => (Token::Op(Op::Ge), 2),
+      ('<', _) => (Token::Op(Op::Lt), 1),
+      ('>', _) => (Token::Op(Op::Gt), 1),
+      ('!', _) => (Token::Not, 1),
+      ('(', _) => (Token::Open, 1),
+      (')', _) => (Token::Close, 1),
+      ('"' | '\'', _) => {
+        let len = chars[i + 1..]
+          .iter()
+          .position(|&q| q == c)
+          .ok_or((column, "unterminated string".to_string()))?;
+        let text = chars[i + 1..i + 1 + len].iter().collect();
+        (Token::Text(text), len + 2)
+      }
+      (c, _) if c.is_ascii_digit() => {
+        let len = chars[i..]
+          .iter()
+          .take_while(|c| c.is_ascii_digit() || **c == '.')
+          .count();
+        let text: String = chars[i..i + len].iter().collect();
+        let number = text
+          .parse()
+          .map_err(|_| (column, format!("invalid number `{text}`")))?;
+        (Token::Number(number), len)
+      }
+      (c, _) if c.is_alphabetic() || c == '_' => {
+        let len = chars[i..]
+          .iter()
+          .take_while(|c| c.is_alphanumeric() || **c == '_')
+          .count();
+        (Token::Ident(chars[i..i + len].iter().collect()), len)
+      }
+      ('&' | '|' | '=', _) => {
+        return Err((column, format!("unexpected `{c}`, did you mean `{c}{c}`?")));
+      }
+      (c, _) => return Err((column, format!("unexpected `{c}`"))),
+    };
+    tokens.push((token, column));
+    i += len;
+  }
+  tokens.push((Token::End, chars.len() + 1));
+  Ok(tokens)
+}
+
+/// A node with its type and the column it starts at.
+struct Typed {
+  node: Node,
+  kind: Kind,
+  column: usize,
+}
+
+impl Typed {
+  fn expect(self, kind: Kind, what: &str) -> Result<Node, Error> {
+    if self.kind == kind {
+      Ok(self.node)
+    } else {
+      Err((
+        self.column,
+        format!("expected {kind} for {what}, found {}", self.kind),
+      ))
+    }
+  }
+}
+
+struct Parser {
+  tokens: Vec<(Token, usize)>,
+  pos: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> (Token, usize) {
+    self.tokens[self.pos].clone()
+  }
+
+  fn bump(&mut self) -> (Token, usize) {
+    let token = self.peek();
+    if token.0 != Token::End {
+      self.pos += 1;
+    }
+    token
+  }
+
+  fn expr(&mut self) -> Result<Typed, Error> {
+    let mut left = self.and()?;
+    while self.peek().0 == Token::Or {
+      self.bump();
+      let right = self.and()?;
+      let column = left.column;
+      let left_node = left.expect(Kind::Bool, "`||`")?;
+      let node = Node::Or(
+        Box::new(left_node),
+        Box::new(right.expect(Kind::Bool, "`||`")?),
+      );
+      left = Typed {
+        node,
+        kind: Kind::Bool,
+        column,
+      };
+    }
+    Ok(left)
+  }
+
+  fn and(&mut self) -> Result<Typed, Error> {
+    let mut left = self.unary()?;
+    while self.peek().0 == Token::And {
+      self.bump();
+      let right = self.unary()?;
+      let column = left.column;
+      let left_node = left.expect(Kind::Bool, "`&&`")?;
+      let node = Node::And(
+        Box::new(left_node),
+        Box::new(right.expect(Kind::Bool, "`&&`")?),
+      );
+      left = Typed {
+        node,
+        kind: Kind::Bool,
+        column,
+      };
+    }
+    Ok(left)
+  }
+
+  fn unary(&mut self) -> Result<Typed, Error> {
+    if self.peek().0 != Token::Not {
+      return self.compare();
+    }
+    let (_, column) = self.bump();
+    let node = self.unary()?.expect(Kind::Bool, "`!`")?;
+    Ok(Typed {
+      node: Node::Not(Box::new(node)),
+      kind: Kind::Bool,
+      column,
+    })
+  }
+
+  fn compare(&mut self) -> Result<Typed, Error> {
+    let left = self.operand()?;
+    let (op, op_column) = match self.peek() {
+      (Token::Op(op), column) => (op, column),
+      _ => return Ok(left),
+    };
+    self.bump();
+    let right = self.operand()?;
+    let (left, right) = match (left.kind, right.kind) {
+      (Kind::Date, Kind::Text) => (left, date_literal(right)?),
+      (Kind::Text, Kind::Date) => (date_literal(left)?, right),
+      (Kind::Date, Kind::Number) | (Kind::Number, Kind::Date) => (left, right),
+      (Kind::Bool, Kind::Bool) if matches!(op, Op::Eq | Op::Ne) => (left, right),
+      (Kind::Bool, Kind::Bool) => {
+        return Err((
+          op_column,
+          "conditions can only be compared with `==` or `!=`".into(),
+        ));
+      }
+      (a, b) if a == b => (left, right),
+      (a, b) => return Err((op_column, format!("can't compare {a} with {b}"))),
+    };
+    Ok(Typed {
+      column: left.column,
+      node: Node::Compare(Box::new(left.node), op, Box::new(right.node)),
+      kind: Kind::Bool,
+    })
+  }
+
+  fn operand(&mut self) -> Result<Typed, Error> {
+    let (token, column) = self.bump();
+    let (node, kind) = match token {
+      Token::Number(n) => (Node::Literal(Value::Number(n)), Kind::Number),
+      Token::Text(s) => (Node::Literal(Value::Text(s)), Kind::Text),
+      Token::Ident(name) if name == "true" || name == "false" => {
+        (Node::Literal(Value::Bool(name == "true")), Kind::Bool)
+      }
+      Token::Ident(name) => match FIELDS.iter().find(|(n, _)| *n == name) {
+        Some((_, field)) => (Node::Field(*field), field.kind()),
+        None => {
+          let names: Vec<_> = FIELDS.iter().map(|(n, _)| *n).collect();
+          let message = format!(
+            "unknown field `{name}`, expected one of {}",
+            names.join(", ")
+          );
+          return Err((column, message));
+        }
+      },
+      Token::Open => {
+        let inner = self.expr()?;
+        match self.bump() {
+          (Token::Close, _) => {}
+          (token, column) => return Err((column, format!("expected `)`, found {token}"))),
+        }
+        (inner.node, inner.kind)
+      }
+      token => {
+        return Err((
+          column,
+          format!("expected a field or a value, found {token}"),
+        ))
+      }
+    };
+    Ok(Typed { node, kind, column })
+  }
+}
+
+/// Turns a text literal compared with a date field into a date.
+fn date_literal(typed: Typed) -> Result<Typed, Error> {
+  let text = match &typed.node {
+    Node::Literal(Value::Text(text)) => text,
+    _ => return Err((typed.column, "can't compare text with a date".into())),
+  };
+  let date = NaiveDate::parse_from_str(text, "%Y-%m-%d").map_err(|_| {
+    (
+      typed.column,
+      format!("invalid date \"{text}\", expected YYYY-MM-DD"),
+    )
+  })?;
+  let date = DateTime::<Utc>::from_utc(date.and_hms(0, 0, 0), Utc);
+  Ok(Typed {
+    node: Node::Literal(Value::Date(Some(date))),
+    kind: Kind::Date,
+    column: typed.column,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// The column and message `expr` fails to parse with.
+  fn error(expr: &str) -> (usize, String) {
+    let error = expr.parse::<FilterExpr>().unwrap_err();
+    (error.column, error.message)
+  }
+
+  #[test]
+  fn parses_valid_expressions() {
+    for expr in [
+      "stars == 0",
+      "fork && !archived",
+      "(stars < 5 || forks > 2) && pushed < 2022",
+      "language == \"Rust\" && created >= \"2020-01-01\"",
+      "true",
+    ] {
+      assert!(expr.parse::<FilterExpr>().is_ok(), "{expr}");
+    }
+  }
+
+  #[test]
+  fn rejects_unknown_tokens() {
+    assert_eq!(
+      error("stars = 0"),
+      (7, "unexpected `=`, did you mean `==`?".into())
+    );
+    assert_eq!(
+      error("fork & archived"),
+      (6, "unexpected `&`, did you mean `&&`?".into())
+    );
+    assert_eq!(error("stars == 0 $"), (12, "unexpected `$`".into()));
+    assert_eq!(error("name == \"x"), (9, "unterminated string".into()));
+    assert_eq!(
+      error("stars == 1.2.3"),
+      (10, "invalid number `1.2.3`".into())
+    );
+  }
+
+  #[test]
+  fn rejects_unknown_fields() {
+    let (column, message) = error("fork && starz > 1");
+    assert_eq!(column, 9);
+    assert!(
+      message.starts_with("unknown field `starz`, expected one of stars,"),
+      "{message}"
+    );
+  }
+
+  #[test]
+  fn rejects_type_errors() {
+    assert_eq!(
+      error("stars"),
+      (
+        1,
+        "expected a condition for the expression, found a number".into()
+      )
+    );
+    assert_eq!(
+      error("stars == \"a\""),
+      (7, "can't compare a number with text".into())
+    );
+    assert_eq!(
+      error("pushed < \"2022\""),
+      (10, "invalid date \"2022\", expected YYYY-MM-DD".into())
+    );
+  }
+
+  #[test]
+  fn rejects_unbalanced_expressions() {
+    assert_eq!(error("(fork"), (6, "expected `)`, found the end".into()));
+    assert_eq!(
+      error("fork archived"),
+      (6, "expected `&&` or `||`, found `archived`".into())
+    );
+    assert_eq!(
+      error("fork &&"),
+      (8, "expected a field or a value, found the end".into())
+    );
+  }
+
+  #[test]
+  fn displays_a_caret_under_the_column() {
+    let error = "stars = 0".parse::<FilterExpr>().unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "column 7: unexpected `=`, did you mean `==`?\n  stars = 0\n        ^"
+    );
+  }
+}
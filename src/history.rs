@@ -0,0 +1,139 @@
+//! A local history of runs, a JSON lines file nothing but the `history`
+//! subcommand reads. Nothing is sent anywhere.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Options whose value is a secret, recorded as `***`. Proxy URLs can carry
+/// credentials.
+const SECRET_OPTIONS: &[&str] = &["--token", "-t", "--proxy"];
+
+/// A single run, as a line of the history.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+  pub at: DateTime<Utc>,
+  /// The command line without the program and with secrets masked
+  pub args: Vec<String>,
+  pub target: String,
+  pub report_only: bool,
+  pub matched: usize,
+  pub deleted: usize,
+  pub failed: usize,
+  pub interrupted: bool,
+}
+
+/// `$XDG_DATA_HOME/delete-unused-repo/history.jsonl`, falling back to
+/// `~/.local/share`, `None` without a home directory.
+pub fn default_path() -> Option<PathBuf> {
+  let data = match std::env::var_os("XDG_DATA_HOME") {
+    Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+    _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+  };
+  Some(data.join("delete-unused-repo").join("history.jsonl"))
+}
+
+/// `args` with the values of [`SECRET_OPTIONS`] masked, whether given as
+/// the next argument, after `=` or right after a short option.
+pub fn mask_secrets(args: impl IntoIterator<Item = String>) -> Vec<String> {
+  let mut masked = vec![];
+  let mut secret_next = false;
+  for arg in args {
+    if secret_next {
+      masked.push("***".to_string());
+      secret_next = false;
+      continue;
+    }
+    match arg.split_once('=') {
+      Some((name, _)) if SECRET_OPTIONS.contains(&name) => masked.push(format!("{name}=***")),
+      _ if SECRET_OPTIONS
+        .iter()
+        .any(|o| o.len() == 2 && arg.len() > 2 && arg.starts_with(o)) =>
+      {
+        masked.push(format!("{}***", &arg[..2]))
+      }
+      _ => {
+        secret_next = SECRET_OPTIONS.contains(&arg.as_str());
+        masked.push(arg);
+      }
+    }
+  }
+  masked
+}
+
+/// Appends `entry` to the history at `path`, creating it and its directory
+/// if needed.
+pub fn append(path: &Path, entry: &Entry) -> io::Result<()> {
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir)?;
+  }
+  let mut line = serde_json::to_vec(entry)?;
+  line.push(b'\n');
+  OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(path)?
+    .write_all(&line)
+}
+
+/// Reads back the history at `path`, oldest first, empty if there is none
+/// yet. Lines that can't be read, e.g. cut off by a crash, are skipped.
+pub fn read(path: &Path) -> io::Result<Vec<Entry>> {
+  let content = match fs::read_to_string(path) {
+    Ok(content) => content,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+    Err(e) => return Err(e),
+  };
+  Ok(
+    content
+      .lines()
+      .filter_map(|line| serde_json::from_str(line).ok())
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(at: &str, matched: usize) -> Entry {
+    Entry {
+      at: at.parse().unwrap(),
+      args: vec!["--token".to_string(), "***".to_string()],
+      target: "repos".to_string(),
+      report_only: false,
+      matched,
+      deleted: matched,
+      failed: 0,
+      interrupted: false,
+    }
+  }
+
+  #[test]
+  fn appends_and_reads_back_in_order() {
+    let dir = std::env::temp_dir().join(format!("history-{}", std::process::id()));
+    let path = dir.join("nested").join("history.jsonl");
+    assert!(read(&path).unwrap().is_empty());
+
+    append(&path, &entry("2022-01-01T00:00:00Z", 3)).unwrap();
+    append(&path, &entry("2022-02-01T00:00:00Z", 5)).unwrap();
+    let entries = read(&path).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let runs: Vec<_> = entries
+      .iter()
+      .map(|e| (e.at.to_rfc3339(), e.matched))
+      .collect();
+    assert_eq!(
+      runs,
+      [
+        ("2022-01-01T00:00:00+00:00".to_string(), 3),
+        ("2022-02-01T00:00:00+00:00".to_string(), 5),
+      ]
+    );
+    assert_eq!(entries[0].args, ["--token", "***"]);
+  }
+}
@@ -0,0 +1,35 @@
+//! The `--on-delete-cmd` extension point, a shell command run after every
+//! successful deletion, see [`crate::Config::on_delete_cmd`].
+
+use std::process::Command;
+
+/// Runs `cmd` with the shell, `REPO_FULL_NAME` set to `full_name` and
+/// `DELETED_ITEM` to `what` was deleted. An error is a message ready to be
+/// shown, with what the command wrote to stderr.
+pub(crate) async fn run(cmd: &str, full_name: &str, what: &str) -> Result<(), String> {
+  let mut command = if cfg!(windows) {
+    let mut command = Command::new("cmd");
+    command.arg("/C");
+    command
+  } else {
+    let mut command = Command::new("sh");
+    command.arg("-c");
+    command
+  };
+  command
+    .arg(cmd)
+    .env("REPO_FULL_NAME", full_name)
+    .env("DELETED_ITEM", what);
+  let output = tokio::task::spawn_blocking(move || command.output())
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("failed to run it: {e}"))?;
+  if output.status.success() {
+    return Ok(());
+  }
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  match stderr.trim() {
+    "" => Err(output.status.to_string()),
+    stderr => Err(format!("{}: {stderr}", output.status)),
+  }
+}
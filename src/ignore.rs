@@ -0,0 +1,143 @@
+//! The `.delete-unused-ignore` file, gitignore-style patterns of repos to
+//! never delete, see [`crate::Config::ignore`].
+
+use std::path::{Path, PathBuf};
+
+use crate::glob_match;
+
+/// Name of the ignore file looked up in the working directory.
+pub const FILE_NAME: &str = ".delete-unused-ignore";
+
+/// A pattern of an [`IgnoreFile`].
+#[derive(Debug, Clone)]
+struct Rule {
+  /// Lowercase, as GitHub ignores case in names
+  pattern: String,
+  /// `!pattern`, taking a repo ignored by an earlier rule back
+  negated: bool,
+}
+
+/// Patterns, one per line like in a `.gitignore`. A pattern with a `/` is
+/// matched against `owner/name`, one without against the name alone, `*`
+/// never matches a `/`. The last matching pattern wins, so `!pattern`
+/// takes back what an earlier one ignored. Blank lines and lines starting
+/// with `#` are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreFile {
+  rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+  pub fn parse(content: &str) -> Self {
+    let rules = content
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty() && !line.starts_with('#'))
+      .map(|line| match line.strip_prefix('!') {
+        Some(pattern) => (pattern, true),
+        None => (line, false),
+      })
+      .map(|(pattern, negated)| Rule {
+        pattern: pattern.trim_start_matches('/').to_lowercase(),
+        negated,
+      })
+      .collect();
+    Self { rules }
+  }
+
+  /// Appends the patterns of `other`, which win over these.
+  pub fn extend(&mut self, other: IgnoreFile) {
+    self.rules.extend(other.rules);
+  }
+
+  pub fn len(&self) -> usize {
+    self.rules.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.rules.is_empty()
+  }
+
+  /// Whether the repo `full_name`, `owner/name`, is ignored.
+  pub fn is_ignored(&self, full_name: &str) -> bool {
+    let full_name = full_name.to_lowercase();
+    let name = full_name.rsplit('/').next().unwrap();
+    let mut ignored = false;
+    for rule in &self.rules {
+      let matched = match rule.pattern.contains('/') {
+        true => matches_segments(&rule.pattern, &full_name),
+        false => glob_match(&rule.pattern, name),
+      };
+      if matched {
+        ignored = !rule.negated;
+      }
+    }
+    ignored
+  }
+}
+
+/// Matches `pattern` against `path` one `/` separated segment at a time,
+/// so `*` stays within a segment.
+fn matches_segments(pattern: &str, path: &str) -> bool {
+  let (pattern, path): (Vec<_>, Vec<_>) = (pattern.split('/').collect(), path.split('/').collect());
+  pattern.len() == path.len() && pattern.iter().zip(&path).all(|(p, s)| glob_match(p, s))
+}
+
+/// Where the ignore files are looked up, in the order they are read: the
+/// user's, `$XDG_CONFIG_HOME/delete-unused-repo/ignore` falling back to
+/// `~/.config`, then [`FILE_NAME`] in the working directory, whose
+/// patterns win.
+pub fn default_paths() -> Vec<PathBuf> {
+  let config = match std::env::var_os("XDG_CONFIG_HOME") {
+    Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+    _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")),
+  };
+  config
+    .map(|dir| dir.join("delete-unused-repo").join("ignore"))
+    .into_iter()
+    .chain([Path::new(FILE_NAME).to_path_buf()])
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn names_and_full_names() {
+    let ignore = IgnoreFile::parse("# keep these\n\nkeep-*\n/me/dotfiles\norg/*\n");
+    assert_eq!(ignore.len(), 3);
+    assert!(ignore.is_ignored("me/keep-this"));
+    assert!(ignore.is_ignored("other/keep-that"));
+    assert!(ignore.is_ignored("Me/DotFiles"));
+    assert!(ignore.is_ignored("org/anything"));
+    assert!(!ignore.is_ignored("other/dotfiles"));
+    assert!(!ignore.is_ignored("me/scratch"));
+  }
+
+  #[test]
+  fn star_stays_within_a_segment() {
+    let ignore = IgnoreFile::parse("*/keep\n");
+    assert!(ignore.is_ignored("me/keep"));
+    assert!(!IgnoreFile::parse("*keep\n").is_ignored("me/discard"));
+    assert!(!IgnoreFile::parse("m*p\n").is_ignored("me/temp"));
+  }
+
+  #[test]
+  fn last_match_wins() {
+    let ignore = IgnoreFile::parse("org/*\n!org/old-*\norg/old-but-gold\n");
+    assert!(ignore.is_ignored("org/new"));
+    assert!(!ignore.is_ignored("org/old-stuff"));
+    assert!(ignore.is_ignored("org/old-but-gold"));
+    // A negation alone ignores nothing
+    assert!(!IgnoreFile::parse("!keep\n").is_ignored("me/keep"));
+  }
+
+  #[test]
+  fn extended_patterns_win() {
+    let mut ignore = IgnoreFile::parse("scratch-*\n");
+    ignore.extend(IgnoreFile::parse("!scratch-old\n"));
+    assert!(ignore.is_ignored("me/scratch-new"));
+    assert!(!ignore.is_ignored("me/scratch-old"));
+  }
+}
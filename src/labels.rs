@@ -0,0 +1,112 @@
+use log::info;
+use octocrab::Octocrab;
+use serde::Deserialize;
+
+use crate::deep;
+
+#[derive(Deserialize, Debug)]
+struct Label {
+  name: String,
+}
+
+/// Lists names of the labels of `owner/repo` no issue or pull request has,
+/// open or closed. Costs a request per [`countable`] label on top of
+/// listing them.
+pub(crate) async fn list_unused(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+) -> octocrab::Result<Vec<String>> {
+  let mut unused = vec![];
+  for page in 1u32.. {
+    let labels: Vec<Label> = crate::api_trace::get_json(
+      gh,
+      format!("repos/{owner}/{repo}/labels"),
+      Some(&[("per_page", "100"), ("page", &page.to_string())]),
+    )
+    .await?;
+    let len = labels.len();
+    for label in labels {
+      if !countable(&label.name) {
+        info!(
+          "Keep label {} of {owner}/{repo}, its uses can't be counted",
+          label.name
+        );
+        continue;
+      }
+      let query = [("labels", label.name.as_str()), ("state", "all")];
+      let uses = deep::count(gh, format!("repos/{owner}/{repo}/issues"), &query).await?;
+      if is_unused(&label.name, uses) {
+        unused.push(label.name);
+      }
+    }
+    if len < 100 {
+      break;
+    }
+  }
+  Ok(unused)
+}
+
+/// Whether the issues with the label `name` can be counted. Their `labels`
+/// filter is a comma separated list, so `bug,ui` would count those with
+/// both `bug` and `ui`, likely none.
+fn countable(name: &str) -> bool {
+  !name.contains(',')
+}
+
+/// Whether the label `name` is unused, found on `uses` issues and pull
+/// requests. Labels that aren't [`countable`] never are.
+fn is_unused(name: &str, uses: u64) -> bool {
+  countable(name) && uses == 0
+}
+
+pub(crate) async fn delete(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+  name: &str,
+) -> (crate::Reply, octocrab::Result<()>) {
+  let name = encode(name);
+  crate::send_delete(gh, format!("repos/{owner}/{repo}/labels/{name}")).await
+}
+
+/// Percent-encodes `name` for a path segment, labels may contain spaces,
+/// slashes and emoji.
+fn encode(name: &str) -> String {
+  name
+    .bytes()
+    .map(|b| match b {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+        (b as char).to_string()
+      }
+      _ => format!("%{b:02X}"),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn unused_with_no_uses() {
+    assert!(is_unused("wontfix", 0));
+    assert!(is_unused("good first issue", 0));
+    assert!(!is_unused("bug", 1));
+    assert!(!is_unused("bug", 42));
+  }
+
+  #[test]
+  fn labels_with_commas_are_kept() {
+    assert!(!countable("bug,ui"));
+    assert!(!is_unused("bug,ui", 0));
+    assert!(countable("bug/ui"));
+  }
+
+  #[test]
+  fn encode_path_segment() {
+    assert_eq!(encode("good first issue"), "good%20first%20issue");
+    assert_eq!(encode("area/ui"), "area%2Fui");
+    assert_eq!(encode("v1.0_rc-2~"), "v1.0_rc-2~");
+  }
+}
@@ -0,0 +1,3210 @@
+//! Find and delete unused GitHub repositories.
+//!
+//! The `delete-unused-repo` binary is a thin interactive wrapper around
+//! [`Cleaner`] and the steps of [`pipeline`], which can be embedded in
+//! other tools as well:
+//!
+//! ```no_run
+//! # async fn run(config: delete_unused_repo::Config) -> octocrab::Result<()> {
+//! let cleaner = delete_unused_repo::Cleaner::new(config)?;
+//! let repos = cleaner.list_candidates().await?;
+//! let candidates = cleaner.filter(repos).await;
+//! let outcomes = cleaner.delete(candidates, |_| {}).await;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use clap::ValueEnum;
+use futures_util::future;
+use log::{error, info, warn};
+use octocrab::models::orgs::Organization;
+use octocrab::models::Repository;
+use octocrab::params::repos::Type as RepoType;
+use octocrab::{Octocrab, Page};
+use serde::Serialize;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use app_auth::{AppCredentials, Auth, Client};
+use attempt_log::{Attempt, AttemptLog};
+use backup::BackupFormat;
+use filter_expr::FilterExpr;
+use ignore::IgnoreFile;
+use owner_cache::OwnerCache;
+use packages::{Package, PackageType};
+use rename::{RenameManifest, Renamed};
+
+pub mod api_trace;
+pub mod app_auth;
+pub mod attempt_log;
+pub mod backup;
+mod deep;
+pub mod dump;
+pub mod filter_expr;
+pub mod history;
+mod hook;
+pub mod ignore;
+mod labels;
+pub mod owner_cache;
+pub mod packages;
+mod pages;
+mod pinned;
+pub mod pipeline;
+pub mod rate_limit;
+mod releases;
+pub mod rename;
+pub mod report;
+mod stars;
+mod subscriptions;
+mod workflow_runs;
+
+/// What to clean up in the matched repos.
+#[derive(ValueEnum, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Target {
+  /// Delete the repos themselves
+  Repos,
+  /// Delete old GitHub Actions workflow runs, keep the repos
+  WorkflowRuns,
+  /// Delete old releases, keep the repos
+  Releases,
+  /// Delete GitHub Packages not updated recently, regardless of repos
+  Packages,
+  /// Delete the GitHub Pages sites, keep the repos
+  Pages,
+  /// Delete labels no issue or pull request has, keep the repos
+  Labels,
+  /// Stop watching repos, of any owner, keep the repos and their stars
+  Subscriptions,
+  /// Unstar repos, of any owner, keep the repos and watching them
+  Stars,
+}
+
+impl Target {
+  /// What a single deleted thing is called in messages.
+  pub fn item(self) -> &'static str {
+    match self {
+      Target::Repos => "repo",
+      Target::WorkflowRuns => "workflow run",
+      Target::Releases => "release",
+      Target::Packages => "package",
+      Target::Pages => "Pages site",
+      Target::Labels => "label",
+      Target::Subscriptions => "subscription",
+      Target::Stars => "star",
+    }
+  }
+}
+
+/// Which releases [`Target::Releases`] deletes.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseKind {
+  All,
+  Draft,
+  Prerelease,
+}
+
+/// Options of a [`Cleaner`].
+#[derive(Debug, Clone)]
+pub struct Config {
+  /// GitHub personal token
+  pub token: String,
+  /// REST API version sent with every request, see [`parse_api_version`]
+  pub api_version: String,
+  /// Keep owner lookups between runs in this directory, see
+  /// [`owner_cache`], `None` to look owners up every run
+  pub owner_cache_dir: Option<PathBuf>,
+  /// Look every owner up again, replacing what [`Config::owner_cache_dir`]
+  /// kept
+  pub refresh_owner_cache: bool,
+  /// Only keep repos whose `fork` flag equals this
+  pub fork: bool,
+  /// Visibility values to keep, `all` keeps every visibility
+  pub visibility: Vec<String>,
+  /// Relations to the authenticated user its repos are listed with:
+  /// `owner`, `collaborator` or `organization_member`
+  pub affiliation: Vec<String>,
+  /// Order GitHub lists the authenticated user's repos in: `created`,
+  /// `updated`, `pushed` or `full_name`, GitHub's default if `None`
+  pub list_sort: Option<String>,
+  /// `asc` or `desc` for `list_sort`
+  pub list_direction: Option<String>,
+  /// Type of repos GitHub lists: `all`, `public`, `private`, `forks`,
+  /// `sources` or `member`. Only `public` and `private` narrow the
+  /// authenticated user's repos, which can't be listed by the others.
+  pub list_type: Option<String>,
+  /// Owners to keep, every owner if `None`. [`ME`] stands for the
+  /// authenticated user, see [`Cleaner::resolve_me`].
+  pub owner: Option<Vec<String>>,
+  /// Owners whose repos are dropped, even with `owner` or `all_orgs`
+  pub exclude_owner: Vec<String>,
+  /// Match `owner` and `exclude_owner` exactly, instead of ignoring case
+  /// like GitHub does
+  pub owner_case_sensitive: bool,
+  /// Also list the repos of every org the authenticated user belongs to
+  pub all_orgs: bool,
+  /// Only list the org repos the user is admin of. When off,
+  /// [`Cleaner::deletable`] keeps every repo they aren't admin of too, so
+  /// it can be shown marked.
+  pub org_admin_only: bool,
+  /// Keep repos with at most this many stars
+  pub star: u32,
+  /// Drop repos pinned to the authenticated user's profile
+  pub exclude_pinned: bool,
+  pub target: Target,
+  /// Keep repos not pushed since then, or workflow runs and releases older
+  /// than it. Without it no workflow run or release is found.
+  pub inactive: Option<When>,
+  /// With [`Target::Stars`], keep repos starred before then
+  pub starred_before: Option<When>,
+  /// Drop repos pushed since then, see [`Cleaner::filter`]
+  pub protect_pushed_within: Option<When>,
+  /// Drop repos this ignores, like [`Config::protect_pushed_within`]
+  pub ignore: Option<IgnoreFile>,
+  /// Don't drop profile and `.github` repos, see [`special_repo`]
+  pub include_special: bool,
+  /// Kind of releases to delete for [`Target::Releases`]
+  pub releases_only: ReleaseKind,
+  /// Ecosystem of the packages to delete for [`Target::Packages`]
+  pub package_type: PackageType,
+  /// Repos per page when listing, 1 to 100
+  pub per_page: u8,
+  /// Max number of concurrent API requests while listing and filtering
+  pub list_concurrency: usize,
+  /// Max number of concurrent deletions
+  pub delete_concurrency: usize,
+  /// Give up on a single delete request after this long
+  pub request_timeout: Duration,
+  /// Space out delete requests so at most this many start per minute
+  pub max_deletions_per_minute: Option<u32>,
+  /// Only keep repos never pushed to after creation, see [`never_pushed`]
+  pub pushed_never: bool,
+  /// Keep repos gaining fewer stars per year than this, see [`star_rate`]
+  pub max_star_rate: Option<f64>,
+  /// Keep repos with this default branch, never empty repos, which have
+  /// none
+  pub default_branch: Option<String>,
+  /// Allow filters costing extra API calls per repo
+  pub deep_activity: bool,
+  /// Replace each listed repo in scope by the full object of the single
+  /// repo endpoint, which has fields lists omit, like `subscribers_count`
+  pub fetch_details: bool,
+  /// With `deep_activity`, keep repos whose latest release is older than
+  /// `inactive`, or which have no release at all
+  pub since_last_release: bool,
+  /// With `deep_activity`, count issues and pull requests updated after
+  /// `inactive` as activity, skipped for repos with issues disabled
+  pub consider_issue_activity: bool,
+  /// With `deep_activity`, keep repos with at least this many branches
+  pub min_branches: Option<u64>,
+  /// With `deep_activity`, keep repos with at most this many branches
+  pub max_branches: Option<u64>,
+  /// With `deep_activity`, keep repos with at least this many commits on
+  /// the default branch
+  pub min_commits: Option<u64>,
+  /// With `deep_activity`, keep repos with at most this many commits on
+  /// the default branch, empty repos have none
+  pub max_commits: Option<u64>,
+  /// With `deep_activity`, keep forks of repos owned by one of these
+  pub parent_owner: Option<Vec<String>>,
+  /// With `deep_activity`, keep forks without commits their parent lacks
+  pub fork_not_ahead: bool,
+  /// With `deep_activity`, keep user owned repos with no collaborator but
+  /// the owner. Org repos count every org member, so they are never kept.
+  pub solo_only: bool,
+  /// With `deep_activity`, drop repos with an `action.yml` at the root, as
+  /// other repos may use them in their workflows
+  pub skip_actions: bool,
+  /// With `deep_activity`, keep repos without a README
+  pub no_readme: bool,
+  /// With `deep_activity`, keep repos with GitHub Actions disabled in
+  /// their settings, dropping those the token can't read the settings of
+  pub actions_disabled: bool,
+  /// Keep repos matching this too, see [`filter_expr`]
+  pub filter_expr: Option<FilterExpr>,
+  /// Back up each repo into this directory before deleting it, keeping the
+  /// repo if that fails
+  pub backup_dir: Option<PathBuf>,
+  pub backup_format: BackupFormat,
+  /// Max number of backups taken at once, apart from the deletions
+  pub clone_concurrency: usize,
+  /// Stop the deletion at the first failure like [`Stopper::stop`], the
+  /// requests already sent still finish
+  pub fail_fast: bool,
+  /// Stop the deletion like [`Config::fail_fast`] once this many have
+  /// failed, something is likely wrong with every one of them then
+  pub max_failures: Option<usize>,
+  /// Drop repos linked to a GitHub Package, whose consumers would break
+  pub skip_packaged: bool,
+  /// Drop repos with Actions secrets or deployment environments, which CI
+  /// is likely using
+  pub protect_with_secrets: bool,
+  /// Drop this many of the most recently pushed repos of each owner, after
+  /// all other filters
+  pub retain_newest: Option<usize>,
+  /// With a rename manifest, try the next new name when GitHub answers
+  /// `422` that one is taken, see [`Cleaner::with_rename_manifest`]
+  pub retry_on_422: bool,
+  /// Of the matched forks of the same upstream, keep only the most recently
+  /// pushed one, dropping forks of upstreams forked only once, see
+  /// [`Cleaner::squash_forks`]
+  pub squash_forks: bool,
+  /// Run this with the shell after every successful deletion, see
+  /// [`Cleaner::hook_failures`]
+  pub on_delete_cmd: Option<String>,
+}
+
+/// The [`Config::owner`] standing for the authenticated user.
+pub const ME: &str = "@me";
+
+/// `owners` with [`ME`] replaced by `login`, keeping the first of the
+/// owners that turn out the same.
+fn replace_me(owners: Vec<String>, login: &str) -> Vec<String> {
+  let mut resolved: Vec<String> = vec![];
+  for owner in owners {
+    let owner = if owner == ME {
+      login.to_string()
+    } else {
+      owner
+    };
+    if !resolved.contains(&owner) {
+      resolved.push(owner);
+    }
+  }
+  resolved
+}
+
+/// The `--owner` standing for every owner, the authenticated user and all
+/// their orgs, which the CLI turns into [`Config::all_orgs`].
+pub const ANY_OWNER: &str = "*";
+
+/// Repos younger than this are treated as this old by [`star_rate`].
+pub const STAR_RATE_MIN_AGE_DAYS: i64 = 30;
+
+/// Stars per year since `created_at`: `stars / (age in days / 365.25)`,
+/// where age is at least [`STAR_RATE_MIN_AGE_DAYS`] so brand new repos get
+/// a high rate instead of dividing by almost zero.
+pub fn star_rate(stars: u32, created_at: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+  let days = (now - created_at).num_days().max(STAR_RATE_MIN_AGE_DAYS);
+  f64::from(stars) / (days as f64 / 365.25)
+}
+
+/// Whether `text` matches the glob `pattern`, where `*` matches any run of
+/// characters, `/` included, and `?` any single one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+  let (pattern, text): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+  let (mut p, mut t) = (0, 0);
+  // Where the last `*` was and the text position it was tried at
+  let mut star: Option<(usize, usize)> = None;
+  while t < text.len() {
+    match pattern.get(p) {
+      Some('*') => {
+        star = Some((p, t));
+        p += 1;
+      }
+      Some(&c) if c == '?' || c == text[t] => {
+        p += 1;
+        t += 1;
+      }
+      _ => match star {
+        Some((star_p, star_t)) => {
+          // Let the `*` take one more character
+          p = star_p + 1;
+          t = star_t + 1;
+          star = Some((star_p, star_t + 1));
+        }
+        None => return false,
+      },
+    }
+  }
+  pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Why `repo` is special to GitHub, `None` if it isn't: the profile README
+/// repo named like its owner, or the `.github` repo with the defaults of
+/// the owner's repos, like issue templates.
+pub fn special_repo(repo: &Repository) -> Option<&'static str> {
+  let owner = repo.owner.as_ref()?;
+  if repo.name.eq_ignore_ascii_case(&owner.login) {
+    Some("the profile repo of its owner")
+  } else if repo.name.eq_ignore_ascii_case(".github") {
+    Some("it holds the defaults of its owner's repos")
+  } else {
+    None
+  }
+}
+
+/// Pushes this close to creation count as part of creating the repo.
+pub const PUSHED_NEVER_TOLERANCE_SECS: i64 = 60;
+
+/// Whether `pushed_at` is within [`PUSHED_NEVER_TOLERANCE_SECS`] of
+/// `created_at`, like a repo created from a template and never touched.
+pub fn never_pushed(created_at: DateTime<Utc>, pushed_at: DateTime<Utc>) -> bool {
+  (pushed_at - created_at).num_seconds().abs() <= PUSHED_NEVER_TOLERANCE_SECS
+}
+
+/// Repos pushed within this many days are never [`is_safe`].
+pub const SAFE_INACTIVE_DAYS: i64 = 90;
+
+/// Whether a repo is an unambiguously worthless fork: no stars, no
+/// watchers and not pushed within [`SAFE_INACTIVE_DAYS`].
+pub fn is_safe(repo: &Repository, now: DateTime<Utc>) -> bool {
+  repo.fork == Some(true)
+    && repo.stargazers_count.unwrap_or(0) == 0
+    && repo.watchers_count.unwrap_or(0) == 0
+    && repo
+      .pushed_at
+      .is_none_or(|p| (now - p).num_days() >= SAFE_INACTIVE_DAYS)
+}
+
+/// Repos with at least this many stars are [`Risk::High`] to delete.
+pub const RISKY_STARS: u32 = 10;
+
+/// How much deleting a repo could lose, for reviewing the matches and
+/// ordering the deletion, see [`order_for_deletion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Risk {
+  Low,
+  Medium,
+  High,
+}
+
+impl Risk {
+  pub fn label(self) -> &'static str {
+    match self {
+      Risk::Low => "low",
+      Risk::Medium => "medium",
+      Risk::High => "high",
+    }
+  }
+}
+
+/// [`Risk::Low`] for [`is_safe`] repos, [`Risk::High`] for those with at
+/// least [`RISKY_STARS`] stars or watched by anyone but their owner, and
+/// [`Risk::Medium`] for the rest. Watchers are only known with
+/// [`Config::fetch_details`], the lists omit `subscribers_count`.
+pub fn risk(repo: &Repository, now: DateTime<Utc>) -> Risk {
+  if is_safe(repo, now) {
+    return Risk::Low;
+  }
+  if repo.stargazers_count.unwrap_or(0) >= RISKY_STARS || repo.subscribers_count.unwrap_or(0) > 1 {
+    return Risk::High;
+  }
+  Risk::Medium
+}
+
+/// Whether `min <= value <= max`, a missing bound is unbounded.
+pub fn in_range(value: u64, min: Option<u64>, max: Option<u64>) -> bool {
+  min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+}
+
+/// Total stars of the repos of `candidates`.
+pub fn total_stars(candidates: &[Candidate]) -> u64 {
+  candidates
+    .iter()
+    .filter_map(|c| c.repo.stargazers_count)
+    .map(u64::from)
+    .sum()
+}
+
+/// Total size of the repos of `candidates` in bytes, GitHub reports `size`
+/// in kilobytes.
+pub fn total_size(candidates: &[Candidate]) -> u64 {
+  candidates
+    .iter()
+    .filter_map(|c| c.repo.size)
+    .map(|s| u64::from(s) * 1024)
+    .sum()
+}
+
+/// Distinct owner logins of `candidates`, sorted.
+pub fn owners(candidates: &[Candidate]) -> Vec<String> {
+  candidates
+    .iter()
+    .filter_map(|c| c.repo.owner.as_ref())
+    .map(|o| o.login.clone())
+    .collect::<BTreeSet<_>>()
+    .into_iter()
+    .collect()
+}
+
+/// Parses a size like `1gb`, `500MB` or `1.5g` into bytes, units are
+/// powers of 1024 like [`indicatif::HumanBytes`] shows them, and a bare
+/// number is bytes.
+pub fn parse_size(text: &str) -> Result<u64, String> {
+  let text = text.trim().to_ascii_lowercase();
+  let split = text
+    .find(|c: char| !c.is_ascii_digit() && c != '.')
+    .unwrap_or(text.len());
+  let (number, unit) = text.split_at(split);
+  let number: f64 = number
+    .parse()
+    .map_err(|_| format!("expected a size like 1gb, found '{text}'"))?;
+  let power = match unit.trim().trim_end_matches('b').trim_end_matches('i') {
+    "" => 0,
+    "k" => 1,
+    "m" => 2,
+    "g" => 3,
+    "t" => 4,
+    _ => {
+      return Err(format!(
+        "unknown unit '{unit}', expected b, kb, mb, gb or tb"
+      ))
+    }
+  };
+  Ok((number * 1024f64.powi(power)) as u64)
+}
+
+/// The visibility values GitHub is known to return.
+pub const VISIBILITIES: &[&str] = &["public", "internal", "private"];
+
+/// Fails naming the first repo whose visibility isn't one of
+/// [`VISIBILITIES`], for surfacing API changes `--visibility` would
+/// silently mishandle. A missing visibility passes, older GitHub
+/// Enterprise Server versions don't return it.
+pub fn check_visibility(repos: &[Repository]) -> Result<(), String> {
+  for repo in repos {
+    if let Some(vis) = &repo.visibility {
+      if !VISIBILITIES.contains(&vis.as_str()) {
+        let name = repo.full_name.as_deref().unwrap_or(&repo.name);
+        return Err(format!(
+          "{name} has the unknown visibility '{vis}', expected one of {}",
+          VISIBILITIES.join(", ")
+        ));
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Checks a fully-qualified repo name like `owner/name`, as `--repo`
+/// takes it. Only names GitHub allows for owners and repos pass, so typos
+/// fail here rather than as a `404` later. Owners may have a `_`, like the
+/// `user_shortcode` logins of Enterprise Managed Users.
+pub fn parse_full_name(text: &str) -> Result<String, String> {
+  let text = text.trim();
+  let (owner, name) = match text.split('/').collect::<Vec<_>>()[..] {
+    [owner, name] => (owner, name),
+    [_] => {
+      return Err(format!(
+        "expected a repo like owner/name, found '{text}' without owner"
+      ))
+    }
+    _ => {
+      return Err(format!(
+        "expected a repo like owner/name, found '{text}' with extra '/'"
+      ))
+    }
+  };
+  if owner.is_empty()
+    || owner.len() > 39
+    || owner.starts_with('-')
+    || owner.ends_with('-')
+    || !owner
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+  {
+    return Err(format!(
+      "'{owner}' in '{text}' is no valid owner, they are up to 39 letters, digits, '_' \
+       and inner '-'"
+    ));
+  }
+  if name.is_empty()
+    || name.len() > 100
+    || name == "."
+    || name == ".."
+    || !name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+  {
+    return Err(format!(
+      "'{name}' in '{text}' is no valid repo name, they are up to 100 letters, digits, '-', '_' \
+       and '.'"
+    ));
+  }
+  Ok(format!("{owner}/{name}"))
+}
+
+/// A point in time for the date filters, see [`parse_when`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum When {
+  /// This long before the run
+  Ago(Duration),
+  At(DateTime<Utc>),
+}
+
+impl When {
+  /// The point in time as of `now`. A duration reaching back before the
+  /// earliest date [`chrono`] has resolves to that date instead.
+  pub fn resolve(self, now: DateTime<Utc>) -> DateTime<Utc> {
+    match self {
+      When::Ago(ago) => chrono::Duration::from_std(ago)
+        .ok()
+        .and_then(|ago| now.checked_sub_signed(ago))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC),
+      When::At(at) => at,
+    }
+  }
+}
+
+/// The REST API version sent by default, the first GitHub dated.
+pub const DEFAULT_API_VERSION: &str = "2022-11-28";
+
+/// Header pinning the REST API version, so changes GitHub makes to the
+/// API in newer versions don't reach runs asking for an older one.
+pub(crate) fn api_version_header() -> reqwest::header::HeaderName {
+  reqwest::header::HeaderName::from_static("x-github-api-version")
+}
+
+/// Checks a REST API version, a date like `2022-11-28`. GitHub rejects
+/// versions it doesn't know with `400 Bad Request`.
+pub fn parse_api_version(text: &str) -> Result<String, String> {
+  let text = text.trim();
+  match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+    Ok(date) if date.format("%Y-%m-%d").to_string() == text => Ok(text.to_string()),
+    _ => Err(format!(
+      "expected a date like {DEFAULT_API_VERSION}, found '{text}'"
+    )),
+  }
+}
+
+/// Parses the value of every date filter, so they all accept the same:
+/// a duration ago like `90d`, `6mo` or `2y` (anything [`humantime`] reads,
+/// plus `mo` for months), a date like `2022-01-01`, or a datetime like
+/// `2022-01-01T12:00:00Z`, UTC unless it has an offset.
+pub fn parse_when(text: &str) -> Result<When, String> {
+  let text = text.trim();
+  if let Ok(at) = DateTime::parse_from_rfc3339(text) {
+    return Ok(When::At(at.with_timezone(&Utc)));
+  }
+  if let Ok(at) = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S") {
+    return Ok(When::At(DateTime::from_utc(at, Utc)));
+  }
+  if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+    return Ok(When::At(DateTime::from_utc(date.and_hms(0, 0, 0), Utc)));
+  }
+  let ago = humantime::parse_duration(&expand_months(text)).map_err(|_| {
+    format!(
+      "expected a duration like 90d, 6mo or 2y, a date like 2022-01-01 \
+      or a datetime like 2022-01-01T12:00:00Z, found '{text}'"
+    )
+  })?;
+  let reachable = chrono::Duration::from_std(ago)
+    .ok()
+    .and_then(|ago| Utc::now().checked_sub_signed(ago))
+    .is_some();
+  if !reachable {
+    return Err(format!(
+      "'{text}' reaches back before the earliest date there is"
+    ));
+  }
+  Ok(When::Ago(ago))
+}
+
+/// Spells out the unit `mo` as `months`, which [`humantime`] only knows as
+/// `M` or `month(s)`.
+fn expand_months(text: &str) -> String {
+  let chars: Vec<char> = text.chars().collect();
+  let mut out = String::new();
+  let mut i = 0;
+  while i < chars.len() {
+    let unit_start = i == 0 || !chars[i - 1].is_alphabetic();
+    let unit_end = chars.get(i + 2).is_none_or(|c| !c.is_alphabetic());
+    if unit_start && unit_end && chars[i] == 'm' && chars.get(i + 1) == Some(&'o') {
+      out += "months";
+      i += 2;
+    } else {
+      out.push(chars[i]);
+      i += 1;
+    }
+  }
+  out
+}
+
+/// Order of the candidates shown for selection, see [`sort`].
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+  /// By full name
+  Name,
+  /// Fewest stars first
+  Stars,
+  /// Smallest first
+  Size,
+  /// Least recently pushed first
+  Pushed,
+}
+
+/// Sorts `candidates` by `key`, breaking ties by full name so the order is
+/// the same on every run. Missing values sort first.
+pub fn sort(candidates: &mut [Candidate], key: SortKey) {
+  candidates.sort_by_cached_key(|c| {
+    let primary = match key {
+      SortKey::Name => 0,
+      SortKey::Stars => c.repo.stargazers_count.map_or(0, i64::from),
+      SortKey::Size => c.repo.size.map_or(0, i64::from),
+      SortKey::Pushed => c.repo.pushed_at.map_or(i64::MIN, |p| p.timestamp()),
+    };
+    (primary, c.full_name())
+  });
+}
+
+/// Order [`Cleaner::delete`] goes through the selected candidates in, see
+/// [`order_for_deletion`].
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteOrder {
+  /// Lowest [`risk`] first, so an interrupted run leaves the valuable repos
+  SafestFirst,
+  /// Highest [`risk`] first
+  RiskiestFirst,
+  /// In the order they are listed for selection
+  AsListed,
+}
+
+/// Puts `candidates` in `order`, keeping the listed order among those of
+/// the same [`risk`]. Deletions run concurrently, so they only start in
+/// this order, and with backups their clones do.
+pub fn order_for_deletion(candidates: &mut [Candidate], order: DeleteOrder, now: DateTime<Utc>) {
+  match order {
+    DeleteOrder::SafestFirst => candidates.sort_by_cached_key(|c| risk(&c.repo, now)),
+    DeleteOrder::RiskiestFirst => {
+      candidates.sort_by_cached_key(|c| std::cmp::Reverse(risk(&c.repo, now)))
+    }
+    DeleteOrder::AsListed => {}
+  }
+}
+
+/// Drops the `n` most recently pushed `candidates` of each owner, never
+/// pushed ones counting as the oldest.
+fn drop_newest(candidates: Vec<Candidate>, n: usize) -> Vec<Candidate> {
+  let mut by_owner: HashMap<Option<String>, Vec<&Candidate>> = HashMap::new();
+  for c in &candidates {
+    let owner = c.repo.owner.as_ref().map(|o| o.login.to_lowercase());
+    by_owner.entry(owner).or_default().push(c);
+  }
+  let retained: HashSet<String> = by_owner
+    .into_values()
+    .flat_map(|mut repos| {
+      repos.sort_by_key(|c| std::cmp::Reverse(c.repo.pushed_at));
+      repos.into_iter().take(n).map(Candidate::full_name)
+    })
+    .collect();
+  candidates
+    .into_iter()
+    .filter(|c| {
+      let name = c.full_name();
+      if retained.contains(&name) {
+        info!("Keep {name}, one of the {n} most recently pushed of its owner");
+        return false;
+      }
+      true
+    })
+    .collect()
+}
+
+/// Who the token belongs to, see [`Cleaner::check_token`].
+#[derive(Debug, Clone)]
+pub struct TokenInfo {
+  pub login: String,
+  /// OAuth scopes of a classic token, `None` for fine-grained tokens,
+  /// which GitHub doesn't report scopes for
+  pub scopes: Option<Vec<String>>,
+}
+
+/// A repo matched by [`Cleaner::filter`].
+#[derive(Debug, Clone)]
+pub struct Candidate {
+  pub repo: Repository,
+  /// Ids of the workflow runs or releases to delete, empty for [`Target::Repos`]
+  pub items: Vec<u64>,
+  /// Names of the labels to delete for [`Target::Labels`], which GitHub
+  /// deletes by name rather than by id
+  pub labels: Vec<String>,
+}
+
+impl From<Repository> for Candidate {
+  fn from(repo: Repository) -> Self {
+    Candidate {
+      repo,
+      items: vec![],
+      labels: vec![],
+    }
+  }
+}
+
+impl Candidate {
+  pub fn full_name(&self) -> String {
+    match (&self.repo.full_name, &self.repo.owner) {
+      (Some(full_name), _) => full_name.clone(),
+      (None, Some(owner)) => format!("{}/{}", owner.login, self.repo.name),
+      (None, None) => self.repo.name.clone(),
+    }
+  }
+
+  /// Whether the user may delete the repo, assumed so if GitHub didn't
+  /// report their permissions.
+  pub fn is_admin(&self) -> bool {
+    self.repo.permissions.as_ref().is_none_or(|p| p.admin)
+  }
+
+  /// Why `target` can't be deleted for this candidate with the token,
+  /// `None` if nothing tells so up front. `delete_repo` is whether the
+  /// token may delete repos at all.
+  pub fn undeletable(&self, target: Target, delete_repo: bool) -> Option<&'static str> {
+    let permissions = self.repo.permissions.as_ref();
+    match target {
+      Target::Repos if !delete_repo => Some("the token lacks the delete_repo scope"),
+      Target::Repos if !self.is_admin() => Some("you aren't admin of it"),
+      Target::WorkflowRuns | Target::Releases | Target::Labels
+        if self.repo.archived == Some(true) =>
+      {
+        Some("it is archived, so read-only")
+      }
+      Target::WorkflowRuns | Target::Releases | Target::Labels
+        if permissions.is_some_and(|p| !p.push) =>
+      {
+        Some("you can't push to it")
+      }
+      Target::Pages if self.repo.archived == Some(true) => Some("it is archived, so read-only"),
+      Target::Pages if permissions.is_some_and(|p| !p.admin && !p.maintain) => {
+        Some("you can't manage its Pages")
+      }
+      _ => None,
+    }
+  }
+
+  /// Number of things [`Cleaner::delete`] will try to delete for this candidate.
+  pub fn count(&self, target: Target) -> usize {
+    match target {
+      Target::Repos | Target::Packages | Target::Pages | Target::Subscriptions | Target::Stars => 1,
+      Target::WorkflowRuns | Target::Releases => self.items.len(),
+      Target::Labels => self.labels.len(),
+    }
+  }
+}
+
+/// A single thing inside a [`Candidate`] to delete.
+#[derive(Debug, Clone)]
+enum Item {
+  Id(u64),
+  Label(String),
+}
+
+impl std::fmt::Display for Item {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Item::Id(id) => write!(f, "{id}"),
+      Item::Label(name) => write!(f, "{name:?}"),
+    }
+  }
+}
+
+/// What happened to a [`Candidate`] passed to [`Cleaner::delete`].
+#[derive(Serialize, Debug)]
+pub struct Outcome {
+  pub full_name: String,
+  /// Number of deleted things, the repo itself counts as one
+  pub deleted: usize,
+  /// Number of things not attempted because the deletion was stopped
+  pub skipped: usize,
+  #[serde(serialize_with = "report::serialize_errors")]
+  pub errors: Vec<DeleteError>,
+}
+
+/// Why deleting a single thing failed.
+#[derive(Debug, thiserror::Error)]
+pub enum DeleteError {
+  #[error(transparent)]
+  Api(#[from] octocrab::Error),
+  #[error("timed out after {}", humantime::format_duration(*.0))]
+  Timeout(Duration),
+  #[error("backup failed, not deleted: {0}")]
+  Backup(String),
+  /// Packages aren't in repos, [`Cleaner::delete_packages`] deletes them
+  #[error("packages are deleted by delete_packages, not as repos")]
+  NotRepos,
+}
+
+/// What [`Cleaner::delete`] tells its `progress` callback after deleting
+/// `what`, which may have failed.
+fn progress_message(what: &str, result: &Result<(), DeleteError>) -> String {
+  match result {
+    Ok(()) => format!("Deleted {what}"),
+    Err(e) => format!("Failed to delete {what}: {e}"),
+  }
+}
+
+/// Whether `failed` deletions stop the rest, see [`Config::fail_fast`] and
+/// [`Config::max_failures`].
+fn stops_deletion(failed: usize, fail_fast: bool, max_failures: Option<usize>) -> bool {
+  failed > 0 && (fail_fast || max_failures.is_some_and(|max| failed >= max))
+}
+
+/// The [`Cleaner::squash_forks`] of `candidates`, each forked from its
+/// `parents` entry or no fork if `None`.
+fn squash_by_parent(candidates: Vec<Candidate>, parents: Vec<Option<String>>) -> Vec<Candidate> {
+  let mut by_parent: HashMap<String, Vec<&Candidate>> = HashMap::new();
+  for (c, parent) in candidates.iter().zip(&parents) {
+    if let Some(parent) = parent {
+      by_parent.entry(parent.to_lowercase()).or_default().push(c);
+    }
+  }
+  let squashed: HashSet<String> = by_parent
+    .into_values()
+    .filter(|forks| forks.len() > 1)
+    .flat_map(|mut forks| {
+      forks.sort_by_key(|c| std::cmp::Reverse(c.repo.pushed_at));
+      forks.into_iter().skip(1).map(Candidate::full_name)
+    })
+    .collect();
+  candidates
+    .into_iter()
+    .zip(parents)
+    .filter(|(c, parent)| {
+      let name = c.full_name();
+      match parent {
+        Some(parent) if !squashed.contains(&name) => {
+          info!("Keep {name}, the most recently pushed fork of {parent}");
+          false
+        }
+        Some(_) => true,
+        None => false,
+      }
+    })
+    .map(|(c, _)| c)
+    .collect()
+}
+
+/// A single spawned deletion, `None` if skipped after [`Stopper::stop`].
+type DeleteTask = JoinHandle<(String, Option<Result<(), DeleteError>>)>;
+
+/// Stops a running [`Cleaner::delete`], see [`Cleaner::stopper`].
+#[derive(Debug, Clone, Default)]
+pub struct Stopper(Arc<AtomicBool>);
+
+impl Stopper {
+  pub fn stop(&self) {
+    self.0.store(true, Ordering::SeqCst);
+  }
+
+  pub fn is_stopped(&self) -> bool {
+    self.0.load(Ordering::SeqCst)
+  }
+}
+
+/// What came back for a delete request, all `None` without a response.
+#[derive(Debug, Default)]
+pub(crate) struct Reply {
+  pub(crate) status: Option<u16>,
+  /// `X-GitHub-Request-Id`, which GitHub Support asks for to look into a
+  /// failed request
+  pub(crate) request_id: Option<String>,
+}
+
+/// `DELETE`s `route`, also returning the [`Reply`] if there was a response
+/// at all.
+pub(crate) async fn send_delete(
+  gh: &Octocrab,
+  route: impl AsRef<str>,
+) -> (Reply, octocrab::Result<()>) {
+  let response = match api_trace::delete(gh, route).await {
+    Ok(response) => response,
+    Err(e) => return (Reply::default(), Err(e)),
+  };
+  let reply = Reply {
+    status: Some(response.status().as_u16()),
+    request_id: response
+      .headers()
+      .get("x-github-request-id")
+      .and_then(|id| id.to_str().ok())
+      .map(str::to_string),
+  };
+  let result = octocrab::map_github_error(response).await.map(drop);
+  (reply, result)
+}
+
+/// Lets one caller through per `period`, on top of the delete semaphore.
+#[derive(Debug)]
+struct Pace {
+  period: Duration,
+  next: tokio::sync::Mutex<Instant>,
+}
+
+impl Pace {
+  fn per_minute(n: u32) -> Self {
+    Self {
+      period: Duration::from_secs(60) / n.max(1),
+      next: tokio::sync::Mutex::new(Instant::now()),
+    }
+  }
+
+  /// Waits for the next free slot, returning early once `stopper` is
+  /// stopped so skipped deletions don't wait for slots they won't use.
+  async fn wait(&self, stopper: &Stopper) {
+    let mut next = self.next.lock().await;
+    if stopper.is_stopped() {
+      return;
+    }
+    tokio::time::sleep_until(*next).await;
+    *next = Instant::now() + self.period;
+  }
+}
+
+pub struct Cleaner {
+  gh: Arc<Octocrab>,
+  config: Config,
+  /// Where deletions get their client from, see [`Cleaner::new_app`]
+  auth: Arc<Auth>,
+  /// Limits requests to [`Config::list_concurrency`]
+  semaphore: Arc<Semaphore>,
+  /// Limits deletions to [`Config::delete_concurrency`]
+  delete_semaphore: Arc<Semaphore>,
+  /// Limits backups to [`Config::clone_concurrency`]
+  clone_semaphore: Arc<Semaphore>,
+  pace: Option<Arc<Pace>>,
+  attempt_log: Option<Arc<AttemptLog>>,
+  rename_manifest: Option<Arc<RenameManifest>>,
+  stopper: Stopper,
+  hook_failures: Arc<AtomicUsize>,
+  failures: Arc<AtomicUsize>,
+  latest_releases: deep::Cache<Option<DateTime<Utc>>>,
+  issue_activity: deep::Cache<Option<DateTime<Utc>>>,
+  branch_counts: deep::Cache<u64>,
+  commit_counts: deep::Cache<u64>,
+  parents: deep::Cache<Option<deep::Parent>>,
+  collaborator_counts: deep::Cache<u64>,
+  account_types: deep::Cache<Option<String>>,
+  action_files: deep::Cache<Option<&'static str>>,
+  comparisons: deep::Cache<(u64, u64)>,
+  readmes: deep::Cache<bool>,
+  actions_enabled: deep::Cache<Option<bool>>,
+  details: deep::Cache<Repository>,
+  /// See [`Config::owner_cache_dir`]
+  owner_cache: Option<(PathBuf, std::sync::Mutex<OwnerCache>)>,
+}
+
+impl Cleaner {
+  /// Builds the GitHub client from [`Config::token`].
+  pub fn new(config: Config) -> octocrab::Result<Self> {
+    let gh = Octocrab::builder()
+      .personal_token(config.token.clone())
+      .add_header(api_version_header(), config.api_version.clone())
+      .build()?;
+    let gh = Arc::new(gh);
+    let auth = Auth::Token(Client {
+      gh: Arc::clone(&gh),
+      token: config.token.clone(),
+    });
+    Ok(Self {
+      gh,
+      auth: Arc::new(auth),
+      semaphore: Arc::new(Semaphore::new(config.list_concurrency.max(1))),
+      delete_semaphore: Arc::new(Semaphore::new(config.delete_concurrency.max(1))),
+      clone_semaphore: Arc::new(Semaphore::new(config.clone_concurrency.max(1))),
+      pace: config
+        .max_deletions_per_minute
+        .map(|n| Arc::new(Pace::per_minute(n))),
+      attempt_log: None,
+      rename_manifest: None,
+      stopper: Default::default(),
+      hook_failures: Default::default(),
+      failures: Default::default(),
+      latest_releases: Default::default(),
+      issue_activity: Default::default(),
+      branch_counts: Default::default(),
+      commit_counts: Default::default(),
+      parents: Default::default(),
+      collaborator_counts: Default::default(),
+      account_types: Default::default(),
+      action_files: Default::default(),
+      comparisons: Default::default(),
+      readmes: Default::default(),
+      actions_enabled: Default::default(),
+      details: Default::default(),
+      owner_cache: Self::load_owner_cache(&config, &config.token),
+      config,
+    })
+  }
+
+  /// Like [`Cleaner::new`], but authenticated as an installation of a
+  /// GitHub App instead of with [`Config::token`]. Its token is renewed
+  /// during the deletions when it is about to expire, or once after a
+  /// `401 Unauthorized`. Listing and filtering use the first token, which
+  /// lasts an hour. An installation is no user, so only repos of
+  /// [`Config::owner`] and [`Config::all_orgs`] are listed.
+  pub async fn new_app(mut config: Config, credentials: AppCredentials) -> octocrab::Result<Self> {
+    let installation_id = credentials.installation_id;
+    let installation = app_auth::Installation::new(credentials, &config.api_version).await?;
+    let auth = Auth::App(Box::new(installation));
+    config.token = auth.client().await?.token;
+    let mut cleaner = Self::new(config)?;
+    cleaner.auth = Arc::new(auth);
+    // Installation tokens change every hour, the installation doesn't
+    let key = format!("installation-{installation_id}");
+    cleaner.owner_cache = Self::load_owner_cache(&cleaner.config, &key);
+    Ok(cleaner)
+  }
+
+  /// The [`Config::owner_cache_dir`] file of the account behind `key`,
+  /// started empty with [`Config::refresh_owner_cache`].
+  fn load_owner_cache(
+    config: &Config,
+    key: &str,
+  ) -> Option<(PathBuf, std::sync::Mutex<OwnerCache>)> {
+    let path = owner_cache::path(config.owner_cache_dir.as_ref()?, key);
+    let cache = match config.refresh_owner_cache {
+      true => OwnerCache::default(),
+      false => owner_cache::load(&path),
+    };
+    Some((path, std::sync::Mutex::new(cache)))
+  }
+
+  /// Writes the [`Config::owner_cache_dir`] file back after `update`.
+  fn update_owner_cache(&self, update: impl FnOnce(&mut OwnerCache)) {
+    let Some((path, cache)) = &self.owner_cache else {
+      return;
+    };
+    let mut cache = cache.lock().unwrap();
+    update(&mut cache);
+    if let Err(e) = owner_cache::save(path, &cache) {
+      warn!("Failed to write the owner cache {}: {e}", path.display());
+    }
+  }
+
+  fn cached_owners<T>(&self, read: impl FnOnce(&OwnerCache) -> Option<T>) -> Option<T> {
+    let (_, cache) = self.owner_cache.as_ref()?;
+    read(&cache.lock().unwrap())
+  }
+
+  /// Records every delete request to `log`, see [`AttemptLog`].
+  pub fn with_attempt_log(mut self, log: AttemptLog) -> Self {
+    self.attempt_log = Some(Arc::new(log));
+    self
+  }
+
+  /// Renames each repo with [`rename::new_name`] right before deleting it,
+  /// recording it to `manifest`. A repo that can't be renamed is kept.
+  pub fn with_rename_manifest(mut self, manifest: RenameManifest) -> Self {
+    self.rename_manifest = Some(Arc::new(manifest));
+    self
+  }
+
+  /// Flushes the log set by [`Cleaner::with_attempt_log`], if any.
+  pub fn flush_attempt_log(&self) -> std::io::Result<()> {
+    match &self.attempt_log {
+      Some(log) => log.flush(),
+      None => Ok(()),
+    }
+  }
+
+  /// Replaces [`ME`] in [`Config::owner`] with the login of the
+  /// authenticated user, asking GitHub only if it is there.
+  pub async fn resolve_me(&mut self) -> octocrab::Result<()> {
+    let owners = match &mut self.config.owner {
+      Some(owners) if owners.iter().any(|o| o == ME) => owners,
+      _ => return Ok(()),
+    };
+    let login = self.gh.current().user().await?.login;
+    *owners = replace_me(std::mem::take(owners), &login);
+    Ok(())
+  }
+
+  pub fn config(&self) -> &Config {
+    &self.config
+  }
+
+  /// Asks GitHub who the token belongs to and what it may do.
+  pub async fn check_token(&self) -> octocrab::Result<TokenInfo> {
+    let response = api_trace::get(&self.gh, "user", None::<&()>).await?;
+    let response = octocrab::map_github_error(response).await?;
+    let scopes = response
+      .headers()
+      .get("x-oauth-scopes")
+      .and_then(|s| s.to_str().ok())
+      .map(|s| {
+        s.split(',')
+          .map(|s| s.trim().to_string())
+          .filter(|s| !s.is_empty())
+          .collect()
+      });
+    let user: octocrab::models::User = octocrab::FromResponse::from_response(response).await?;
+    Ok(TokenInfo {
+      login: user.login,
+      scopes,
+    })
+  }
+
+  /// The API budgets left, without spending any.
+  pub async fn rate_limits(&self) -> octocrab::Result<rate_limit::RateLimits> {
+    rate_limit::get(&self.gh).await
+  }
+
+  /// Lists a single repo, to check repos can be listed at all.
+  pub async fn check_listing(&self) -> octocrab::Result<()> {
+    self
+      .gh
+      .current()
+      .list_repos_for_authenticated_user()
+      .per_page(1)
+      .send()
+      .await
+      .map(drop)
+  }
+
+  /// Lists the repos of the authenticated user with a
+  /// [`Config::affiliation`], and the repos of each org
+  /// in [`Config::owner`], or of all their orgs with [`Config::all_orgs`].
+  /// Only org repos they administer are listed unless
+  /// [`Config::org_admin_only`] is off. With [`Config::fetch_details`] the
+  /// repos in scope are fetched one by one after listing.
+  pub async fn list_candidates(&self) -> octocrab::Result<Vec<Repository>> {
+    // Watched and starred repos are mostly someone else's, neither the
+    // user's nor their orgs' listings have them. They go through the same
+    // filters, `--owner` included, so only the listing differs
+    let listed = match self.config.target {
+      Target::Subscriptions => {
+        let repos = subscriptions::list(&self.gh, self.config.per_page).await?;
+        info!("Found {} repos watched by you", repos.len());
+        Some(repos)
+      }
+      Target::Stars => {
+        let before = self.config.starred_before.map(|w| w.resolve(Utc::now()));
+        let repos = stars::list(&self.gh, self.config.per_page, before).await?;
+        info!("Found {} repos starred by you", repos.len());
+        Some(repos)
+      }
+      _ => None,
+    };
+    if let Some(repos) = listed {
+      return match self.config.fetch_details {
+        true => Ok(self.fetch_details(repos).await),
+        false => Ok(repos),
+      };
+    }
+    let mut repos = match self.auth.is_app() {
+      true => vec![],
+      false => self.list_user_repos().await?,
+    };
+    let mut orgs = vec![];
+    if self.config.all_orgs {
+      orgs = self.list_orgs().await?;
+    }
+    for org in self.owner_orgs().await {
+      if !orgs.contains(&org) {
+        orgs.push(org);
+      }
+    }
+    orgs.retain(|org| !self.is_excluded(org));
+    if !orgs.is_empty() {
+      let mut seen: HashSet<_> = repos.iter().map(|r| r.id).collect();
+      for org in orgs {
+        let org_repos = self.list_org_repos(&org).await?;
+        let total = org_repos.len();
+        let (admin, others): (Vec<_>, Vec<_>) = org_repos
+          .into_iter()
+          .partition(|r| r.permissions.as_ref().is_some_and(|p| p.admin));
+        info!(
+          "Found {total} repos in org {org}, {} of them administered by you",
+          admin.len()
+        );
+        repos.extend(admin.into_iter().filter(|r| seen.insert(r.id)));
+        if !self.config.org_admin_only {
+          repos.extend(others.into_iter().filter(|r| seen.insert(r.id)));
+        }
+      }
+    }
+    if self.config.fetch_details {
+      repos = self.fetch_details(repos).await;
+    }
+    Ok(repos)
+  }
+
+  /// Replaces the `repos` [`Cleaner::in_scope`] by their full objects,
+  /// dropping those that fail to be fetched. The others can't match
+  /// anyway, so they are kept as listed.
+  async fn fetch_details(&self, repos: Vec<Repository>) -> Vec<Repository> {
+    let in_scope = repos.iter().filter(|r| self.in_scope(r)).count();
+    warn!("--fetch-details: fetching {in_scope} repos one by one, an API call each");
+    let fetched = future::join_all(repos.into_iter().map(|repo| async move {
+      if !self.in_scope(&repo) {
+        return Some(repo);
+      }
+      let full_name = Candidate::from(repo).full_name();
+      let _permit = self.semaphore.acquire().await.unwrap();
+      let detailed = self
+        .details
+        .get_or_fetch(&full_name, || async {
+          api_trace::get_json(&self.gh, format!("repos/{full_name}"), None::<&()>).await
+        })
+        .await;
+      match detailed {
+        Ok(repo) => Some(repo),
+        Err(e) => {
+          warn!("Skip {full_name}, failed to fetch its details: {e}");
+          None
+        }
+      }
+    }))
+    .await;
+    fetched.into_iter().flatten().collect()
+  }
+
+  /// The orgs among [`Config::owner`], whose repos `user/repos` may miss.
+  /// Users are skipped, as only the authenticated user's own repos can be
+  /// deleted, and so are owners which don't exist or can't be looked up.
+  async fn owner_orgs(&self) -> Vec<String> {
+    let mut orgs = vec![];
+    let mut resolved = vec![];
+    for owner in self.config.owner.iter().flatten() {
+      let kind = match self.cached_owners(|c| c.account_type(owner)) {
+        Some(kind) => Ok(kind),
+        None => {
+          let kind = self
+            .account_types
+            .get_or_fetch(owner, || deep::account_type(&self.gh, owner))
+            .await;
+          if let Ok(kind) = &kind {
+            resolved.push((owner, kind.clone()));
+          }
+          kind
+        }
+      };
+      match kind.as_ref().map(|k| k.as_deref()) {
+        Ok(Some("Organization")) => orgs.push(owner.clone()),
+        Ok(Some(_)) => {}
+        Ok(None) => warn!("Skip owner {owner}, no such user or org"),
+        Err(e) => warn!("Skip owner {owner}, failed to look it up: {e}"),
+      }
+    }
+    if !resolved.is_empty() {
+      self.update_owner_cache(|cache| {
+        for (owner, kind) in resolved {
+          cache.set_account_type(owner, kind);
+        }
+      });
+    }
+    orgs
+  }
+
+  /// Whether `login` is in [`Config::exclude_owner`].
+  fn is_excluded(&self, login: &str) -> bool {
+    self
+      .config
+      .exclude_owner
+      .iter()
+      .any(|o| match self.config.owner_case_sensitive {
+        true => o == login,
+        false => o.eq_ignore_ascii_case(login),
+      })
+  }
+
+  /// Logins of the orgs the authenticated user is a member of.
+  async fn list_orgs(&self) -> octocrab::Result<Vec<String>> {
+    if let Some(orgs) = self.cached_owners(OwnerCache::orgs) {
+      return Ok(orgs);
+    }
+    let page: Page<Organization> =
+      api_trace::get_json(&self.gh, "user/orgs", Some(&[("per_page", "100")])).await?;
+    let orgs = self.gh.all_pages(page).await?;
+    let orgs: Vec<_> = orgs.into_iter().map(|o| o.login).collect();
+    self.update_owner_cache(|cache| cache.set_orgs(orgs.clone()));
+    Ok(orgs)
+  }
+
+  /// Lists the repos of `org` page by page, logging the progress every ten
+  /// pages for orgs with many of them.
+  async fn list_org_repos(&self, org: &str) -> octocrab::Result<Vec<Repository>> {
+    let per_page = self.config.per_page;
+    let repo_type = self.config.list_type.as_deref().and_then(|t| match t {
+      "public" => Some(RepoType::Public),
+      "private" => Some(RepoType::Private),
+      "forks" => Some(RepoType::Forks),
+      "sources" => Some(RepoType::Sources),
+      "member" => Some(RepoType::Member),
+      _ => None,
+    });
+    let mut page = self
+      .gh
+      .orgs(org)
+      .list_repos()
+      .repo_type(repo_type)
+      .per_page(per_page)
+      .send()
+      .await?;
+    let pages = page.number_of_pages();
+    let mut repos = page.take_items();
+    let mut listed = 1;
+    while let Some(mut next) = self.gh.get_page::<Repository>(&page.next).await? {
+      repos.extend(next.take_items());
+      listed += 1;
+      if let (Some(pages), 0) = (pages, listed % 10) {
+        info!(
+          "Listed page {listed} of {pages} of org {org}, {} repos so far",
+          repos.len()
+        );
+      }
+      page = next;
+    }
+    Ok(repos)
+  }
+
+  async fn list_user_repos(&self) -> octocrab::Result<Vec<Repository>> {
+    let affiliation = self.config.affiliation.join(",");
+    let per_page = self.config.per_page;
+    let get_repos = |page: u8| {
+      let gh = Arc::clone(&self.gh);
+      let affiliation = affiliation.clone();
+      let sort = self.config.list_sort.clone();
+      let direction = self.config.list_direction.clone();
+      // `type` can't be combined with `affiliation`, `visibility` can
+      let visibility = match self.config.list_type.as_deref() {
+        Some(vis @ ("public" | "private")) => Some(vis.to_string()),
+        _ => None,
+      };
+      async move {
+        let mut request = gh
+          .current()
+          .list_repos_for_authenticated_user()
+          .affiliation(affiliation);
+        if let Some(sort) = sort {
+          request = request.sort(sort);
+        }
+        if let Some(direction) = direction {
+          request = request.direction(direction);
+        }
+        if let Some(visibility) = visibility {
+          request = request.visibility(visibility);
+        }
+        request.per_page(per_page).page(page).send().await
+      }
+    };
+
+    let mut repos = vec![];
+    let first = get_repos(1).await?;
+    let page_num = first.number_of_pages();
+    // The page parameter is a u8 in octocrab, follow the links beyond that
+    if page_num > Some(u32::from(u8::MAX)) {
+      return self.gh.all_pages(first).await;
+    }
+    repos.extend(first);
+    if page_num >= Some(2) {
+      let handles: Vec<_> = (2..=page_num.unwrap())
+        .map(|i| tokio::spawn(get_repos(i as u8)))
+        .collect();
+      for get_repo in handles {
+        repos.extend(get_repo.await.unwrap()?.items);
+      }
+    }
+    Ok(repos)
+  }
+
+  /// Lists the [`Config::package_type`] packages of the authenticated user,
+  /// and with [`Config::all_orgs`] of each of their orgs, keeping those not
+  /// updated within [`Config::inactive`].
+  pub async fn list_packages(&self) -> octocrab::Result<Vec<Package>> {
+    let package_type = self.config.package_type;
+    let mut packages = packages::list(&self.gh, None, package_type).await?;
+    if self.config.all_orgs {
+      for org in self.list_orgs().await? {
+        packages.extend(packages::list(&self.gh, Some(&org), package_type).await?);
+      }
+    }
+    if let Some(cutoff) = self.cutoff() {
+      packages.retain(|p| p.updated_at < cutoff);
+    }
+    Ok(packages)
+  }
+
+  fn cutoff(&self) -> Option<DateTime<Utc>> {
+    self.config.inactive.map(|when| when.resolve(Utc::now()))
+  }
+
+  /// Per owner counts of the `repos` [`Cleaner::in_scope`], sorted by
+  /// owner, ignoring the other filters. Stale ones weren't pushed since
+  /// [`Config::inactive`], none without it.
+  pub fn owner_summaries(&self, repos: &[Repository]) -> Vec<report::OwnerSummary> {
+    let cutoff = self.cutoff();
+    let mut owners: BTreeMap<String, report::OwnerSummary> = BTreeMap::new();
+    for repo in repos.iter().filter(|r| self.in_scope(r)) {
+      let owner = repo.owner.as_ref().map_or("", |o| o.login.as_str());
+      let summary = owners
+        .entry(owner.to_lowercase())
+        .or_insert_with(|| report::OwnerSummary {
+          owner: owner.to_string(),
+          ..Default::default()
+        });
+      summary.repos += 1;
+      summary.forks += usize::from(repo.fork == Some(true));
+      summary.archived += usize::from(repo.archived == Some(true));
+      let stale = match (cutoff, repo.pushed_at) {
+        (Some(cutoff), Some(pushed_at)) => pushed_at < cutoff,
+        (Some(_), None) => true,
+        (None, _) => false,
+      };
+      summary.stale += usize::from(stale);
+      summary.size += u64::from(repo.size.unwrap_or(0));
+      summary.stars += u64::from(repo.stargazers_count.unwrap_or(0));
+    }
+    owners.into_values().collect()
+  }
+
+  /// Whether `repo` is of an owner in [`Config::owner`] and not in
+  /// [`Config::exclude_owner`], and has a [`Config::visibility`].
+  fn in_scope(&self, repo: &Repository) -> bool {
+    let args = &self.config;
+    let owner_matches = match (repo.owner.as_ref().map(|u| &u.login), &args.owner) {
+      (Some(user), Some(owner)) if args.owner_case_sensitive => owner.contains(user),
+      (Some(user), Some(owner)) => owner.iter().any(|o| o.eq_ignore_ascii_case(user)),
+      _ => true,
+    };
+    let excluded = repo
+      .owner
+      .as_ref()
+      .is_some_and(|o| self.is_excluded(&o.login));
+    let visible = match &repo.visibility {
+      _ if args.visibility.iter().any(|v| v == "all") => true,
+      Some(vis) => args.visibility.contains(vis),
+      None => true,
+    };
+    owner_matches && !excluded && visible
+  }
+
+  /// Keeps the repos matching [`Config`], and for targets inside repos
+  /// looks up the old things to delete, dropping repos without any. The
+  /// same as [`Cleaner::shallow_filter`] then [`Cleaner::finish_filter`].
+  pub async fn filter(&self, repos: Vec<Repository>) -> Vec<Candidate> {
+    let candidates = self.shallow_filter(repos);
+    self.finish_filter(candidates).await
+  }
+
+  /// The filters [`Cleaner::filter`] applies on the listing alone, without
+  /// any API call, so their matches can be counted before the others run.
+  pub fn shallow_filter(&self, repos: Vec<Repository>) -> Vec<Candidate> {
+    let args = &self.config;
+    let cutoff = self.cutoff();
+    let listed_as = match args.target {
+      Target::Subscriptions => "you watch",
+      Target::Stars => "you starred",
+      _ => "you can access",
+    };
+    for owner in args.owner.iter().flatten() {
+      let listed = repos.iter().any(|r| {
+        r.owner
+          .as_ref()
+          .is_some_and(|u| match args.owner_case_sensitive {
+            true => u.login == *owner,
+            false => u.login.eq_ignore_ascii_case(owner),
+          })
+      });
+      if !listed {
+        warn!("--owner {owner} has no repos {listed_as}, is it a typo?");
+      }
+    }
+    let repos: Vec<_> = repos
+      .into_iter()
+      .filter(|r| self.in_scope(r))
+      .filter(|r| r.fork == Some(args.fork))
+      .filter(|r| r.stargazers_count <= Some(args.star))
+      .filter(|r| match (args.pushed_never, r.created_at, r.pushed_at) {
+        (false, _, _) => true,
+        (true, Some(created_at), Some(pushed_at)) => never_pushed(created_at, pushed_at),
+        (true, _, _) => false,
+      })
+      .filter(|r| match (args.max_star_rate, r.created_at) {
+        (Some(max), Some(created_at)) => {
+          star_rate(r.stargazers_count.unwrap_or(0), created_at, Utc::now()) < max
+        }
+        _ => true,
+      })
+      .filter(|r| match (&args.default_branch, &r.default_branch) {
+        (None, _) => true,
+        (Some(name), Some(branch)) => branch == name,
+        (Some(_), None) => false,
+      })
+      .filter(|r| args.filter_expr.as_ref().is_none_or(|e| e.matches(r)))
+      .filter(|r| match (args.target, cutoff, r.pushed_at) {
+        (
+          Target::Repos | Target::Pages | Target::Labels | Target::Subscriptions | Target::Stars,
+          Some(cutoff),
+          Some(pushed_at),
+        ) => pushed_at < cutoff,
+        _ => true,
+      })
+      .map(Candidate::from)
+      .collect();
+    self.protect(repos)
+  }
+
+  /// The rest of [`Cleaner::filter`] after [`Cleaner::shallow_filter`],
+  /// the filters and lookups costing API calls.
+  pub async fn finish_filter(&self, repos: Vec<Candidate>) -> Vec<Candidate> {
+    let cutoff = self.cutoff();
+    let repos = self.exclude_pinned(repos).await;
+    let repos = self.protect_packaged(repos).await;
+    let repos = self.protect_with_secrets(repos).await;
+    let repos = self.deep_filter(repos, cutoff).await;
+    let repos = self.squash_forks(repos).await;
+    let repos = self.retain_newest(repos);
+    self.prepare(repos).await
+  }
+
+  /// For `--reconcile`, the repos [`Cleaner::in_scope`] whose full name is
+  /// not in `keep`, ignoring case. The other filters don't apply, so
+  /// everything else in scope goes, but the protections still do.
+  pub async fn reconcile(&self, repos: Vec<Repository>, keep: &[String]) -> Vec<Candidate> {
+    let keep: HashSet<String> = keep.iter().map(|k| k.to_lowercase()).collect();
+    let candidates: Vec<_> = repos
+      .into_iter()
+      .filter(|r| self.in_scope(r))
+      .map(Candidate::from)
+      .collect();
+    let names: HashSet<String> = candidates
+      .iter()
+      .map(|c| c.full_name().to_lowercase())
+      .collect();
+    for missing in keep.iter().filter(|k| !names.contains(*k)) {
+      warn!("{missing} from the keep file isn't in scope, is it a typo?");
+    }
+    let candidates = candidates
+      .into_iter()
+      .filter(|c| {
+        let kept = keep.contains(&c.full_name().to_lowercase());
+        if kept {
+          info!("Keep {}, it is in the keep file", c.full_name());
+        }
+        !kept
+      })
+      .collect();
+    let candidates = self.protect(candidates);
+    let candidates = self.exclude_pinned(candidates).await;
+    self.prepare(candidates).await
+  }
+
+  /// Turns already filtered repos, e.g. read back by [`dump::read`], into
+  /// candidates, looking up the things to delete for targets inside repos.
+  pub async fn load(&self, repos: Vec<Repository>) -> Vec<Candidate> {
+    let candidates = self.protect(repos.into_iter().map(Candidate::from).collect());
+    self.prepare(candidates).await
+  }
+
+  /// Gets the repos named like `owner/name`, one request per permit of the
+  /// semaphore, for passing them to [`Cleaner::load`]. Fails naming the
+  /// first repo that doesn't exist or can't be got.
+  pub async fn get_repos(&self, full_names: &[String]) -> Result<Vec<Repository>, String> {
+    let repos = future::join_all(full_names.iter().map(|full_name| async move {
+      let _permit = self.semaphore.acquire().await.unwrap();
+      deep::get_optional::<Repository>(&self.gh, format!("repos/{full_name}")).await
+    }))
+    .await;
+    full_names
+      .iter()
+      .zip(repos)
+      .map(|(full_name, repo)| match repo {
+        Ok(Some(repo)) => Ok(repo),
+        Ok(None) => Err(format!("{full_name} doesn't exist or isn't accessible")),
+        Err(e) => Err(format!("Failed to get {full_name}: {e}")),
+      })
+      .collect()
+  }
+
+  /// Drops the candidates [`Candidate::undeletable`] with the token, so
+  /// nothing is selected that would fail with `403` or `422`. Costs an API
+  /// call for the scopes of the token, which are assumed fine if they
+  /// can't be told, like for fine-grained tokens. Repos the user isn't
+  /// admin of stay unless [`Config::org_admin_only`] is on.
+  pub async fn deletable(&self, candidates: Vec<Candidate>) -> Vec<Candidate> {
+    let delete_repo = match self.check_token().await {
+      Ok(TokenInfo {
+        scopes: Some(scopes),
+        ..
+      }) => scopes.iter().any(|s| s == "delete_repo"),
+      Ok(_) => true,
+      Err(e) => {
+        warn!("Failed to get the scopes of the token, assuming it may delete repos: {e}");
+        true
+      }
+    };
+    let target = self.config.target;
+    candidates
+      .into_iter()
+      .filter(|c| match c.undeletable(target, delete_repo) {
+        // Only not being admin stops it, and the user asked to see those
+        Some(_) if target == Target::Repos && delete_repo && !self.config.org_admin_only => true,
+        Some(reason) => {
+          info!("Skip {}, {reason}", c.full_name());
+          false
+        }
+        None => true,
+      })
+      .collect()
+  }
+
+  /// Drops repos pushed within [`Config::protect_pushed_within`], ignored
+  /// by [`Config::ignore`] or [`special_repo`]s. Unlike the other filters
+  /// this also applies to [`Cleaner::load`], so no combination of options
+  /// can catch them.
+  fn protect(&self, candidates: Vec<Candidate>) -> Vec<Candidate> {
+    let cutoff = self
+      .config
+      .protect_pushed_within
+      .map(|since| since.resolve(Utc::now()));
+    candidates
+      .into_iter()
+      .filter(|c| match (cutoff, c.repo.pushed_at) {
+        (Some(cutoff), Some(pushed_at)) if pushed_at >= cutoff => {
+          info!("Protected {}: pushed at {pushed_at}", c.full_name());
+          false
+        }
+        _ => true,
+      })
+      .filter(|c| match &self.config.ignore {
+        Some(ignore) if ignore.is_ignored(&c.full_name()) => {
+          info!("Protected {}: in an ignore file", c.full_name());
+          false
+        }
+        _ => true,
+      })
+      .filter(|c| match special_repo(&c.repo) {
+        Some(why) if !self.config.include_special => {
+          info!("Protected {}: {why}", c.full_name());
+          false
+        }
+        _ => true,
+      })
+      .collect()
+  }
+
+  /// Drops the [`Config::retain_newest`] most recently pushed candidates
+  /// of each owner, see [`drop_newest`].
+  fn retain_newest(&self, candidates: Vec<Candidate>) -> Vec<Candidate> {
+    match self.config.retain_newest {
+      Some(n) => drop_newest(candidates, n),
+      None => candidates,
+    }
+  }
+
+  /// With [`Config::squash_forks`], groups the forks among `candidates` by
+  /// the repo they were forked from and keeps the most recently pushed of
+  /// each group, the rest of the group staying candidates. Forks of an
+  /// upstream forked only once and repos that are no forks are dropped,
+  /// they have nothing to be squashed into.
+  async fn squash_forks(&self, candidates: Vec<Candidate>) -> Vec<Candidate> {
+    if !self.config.squash_forks {
+      return candidates;
+    }
+    let parents = future::join_all(candidates.iter().map(|c| async {
+      let full_name = c.full_name();
+      let owner = match (&c.repo.owner, c.repo.fork) {
+        (Some(owner), Some(true)) => owner.login.as_str(),
+        _ => return None,
+      };
+      let _permit = self.semaphore.acquire().await.unwrap();
+      let parent = self
+        .parents
+        .get_or_fetch(&full_name, || deep::parent(&self.gh, owner, &c.repo.name))
+        .await;
+      match parent {
+        Ok(Some(parent)) => Some(format!("{}/{}", parent.owner, parent.name)),
+        Ok(None) => {
+          info!("Skip {full_name}, its parent is gone");
+          None
+        }
+        Err(e) => {
+          warn!("Skip {full_name}, failed to get its parent: {e}");
+          None
+        }
+      }
+    }))
+    .await;
+
+    squash_by_parent(candidates, parents)
+  }
+
+  async fn prepare(&self, candidates: Vec<Candidate>) -> Vec<Candidate> {
+    match self.config.target {
+      Target::Repos | Target::Packages | Target::Subscriptions | Target::Stars => candidates,
+      Target::Pages => candidates
+        .into_iter()
+        .filter(|c| c.repo.has_pages == Some(true))
+        .collect(),
+      Target::WorkflowRuns | Target::Releases | Target::Labels => self.find_items(candidates).await,
+    }
+  }
+
+  /// Drops pinned repos with [`Config::exclude_pinned`]. Pins are only
+  /// readable through GraphQL, so if the query fails, e.g. for a token
+  /// without the `read:user` scope, every repo is kept.
+  async fn exclude_pinned(&self, candidates: Vec<Candidate>) -> Vec<Candidate> {
+    if !self.config.exclude_pinned {
+      return candidates;
+    }
+    let pinned = match pinned::pinned_repos(&self.gh).await {
+      Ok(pinned) => pinned,
+      Err(e) => {
+        warn!("Failed to fetch pinned repos, not excluding any: {e}");
+        return candidates;
+      }
+    };
+    candidates
+      .into_iter()
+      .filter(|c| {
+        let is_pinned = c
+          .repo
+          .node_id
+          .as_ref()
+          .is_some_and(|id| pinned.contains(id));
+        if is_pinned {
+          info!("Protected {}: pinned to your profile", c.full_name());
+        }
+        !is_pinned
+      })
+      .collect()
+  }
+
+  /// Drops repos linked to a package with [`Config::skip_packaged`]. The
+  /// packages API has no per repo listing, so every package of every type
+  /// is listed for each owner, one request per permit of the semaphore. The
+  /// repos of an owner whose packages can't be listed are dropped too.
+  async fn protect_packaged(&self, candidates: Vec<Candidate>) -> Vec<Candidate> {
+    if !self.config.skip_packaged {
+      return candidates;
+    }
+    let mut owners: Vec<(String, bool)> = vec![];
+    for c in &candidates {
+      if let Some(owner) = &c.repo.owner {
+        if !owners.iter().any(|(login, _)| *login == owner.login) {
+          owners.push((owner.login.clone(), owner.r#type == "Organization"));
+        }
+      }
+    }
+    let lookups = owners.iter().flat_map(|(owner, is_org)| {
+      let (owner, is_org) = (owner.as_str(), *is_org);
+      PackageType::value_variants()
+        .iter()
+        .map(move |&package_type| async move {
+          let _permit = self.semaphore.acquire().await.unwrap();
+          let linked = packages::linked_repos(&self.gh, owner, is_org, package_type).await;
+          (owner, linked)
+        })
+    });
+    let mut linked: HashSet<String> = HashSet::new();
+    let mut failed: HashSet<&str> = HashSet::new();
+    for (owner, result) in future::join_all(lookups).await {
+      match result {
+        Ok(repos) => linked.extend(repos),
+        Err(e) => {
+          if failed.insert(owner) {
+            warn!("Skip the repos of {owner}, failed to list their packages: {e}");
+          }
+        }
+      }
+    }
+    candidates
+      .into_iter()
+      .filter(|c| {
+        let owner = c.repo.owner.as_ref().map(|o| o.login.as_str());
+        if owner.is_some_and(|o| failed.contains(o)) {
+          return false;
+        }
+        let full_name = c.full_name();
+        let packaged = linked.contains(&full_name);
+        if packaged {
+          info!("Protected {full_name}: it hosts packages others may use");
+        }
+        !packaged
+      })
+      .collect()
+  }
+
+  /// Drops repos with Actions secrets or environments with
+  /// [`Config::protect_with_secrets`], one repo per permit of the
+  /// semaphore. Repos the token may not look into are only warned about,
+  /// repos whose lookup fails are dropped.
+  async fn protect_with_secrets(&self, candidates: Vec<Candidate>) -> Vec<Candidate> {
+    if !self.config.protect_with_secrets {
+      return candidates;
+    }
+    let keep = future::join_all(candidates.iter().map(|c| async {
+      let _permit = self.semaphore.acquire().await.unwrap();
+      let full_name = c.full_name();
+      let owner = match &c.repo.owner {
+        Some(owner) => owner.login.as_str(),
+        None => return false,
+      };
+      match deep::secrets_and_environments(&self.gh, owner, &c.repo.name).await {
+        Ok(Some((0, 0))) => true,
+        Ok(Some((secrets, environments))) => {
+          info!("Protected {full_name}: {secrets} Actions secrets, {environments} environments");
+          false
+        }
+        Ok(None) => {
+          warn!("Can't tell whether {full_name} has Actions secrets, the token may not read them");
+          true
+        }
+        Err(e) => {
+          warn!("Skip {full_name}, failed to get its Actions secrets: {e}");
+          false
+        }
+      }
+    }))
+    .await;
+    candidates
+      .into_iter()
+      .zip(keep)
+      .filter_map(|(c, keep)| keep.then_some(c))
+      .collect()
+  }
+
+  /// Runs the `deep_activity` filters, one repo per permit of the semaphore.
+  async fn deep_filter(
+    &self,
+    candidates: Vec<Candidate>,
+    cutoff: Option<DateTime<Utc>>,
+  ) -> Vec<Candidate> {
+    if !self.config.deep_activity {
+      return candidates;
+    }
+    let keep = future::join_all(candidates.iter().map(|c| async {
+      let _permit = self.semaphore.acquire().await.unwrap();
+      self.keep_deep(c, cutoff).await
+    }))
+    .await;
+    candidates
+      .into_iter()
+      .zip(keep)
+      .filter_map(|(c, keep)| keep.then_some(c))
+      .collect()
+  }
+
+  async fn keep_deep(&self, candidate: &Candidate, cutoff: Option<DateTime<Utc>>) -> bool {
+    let full_name = candidate.full_name();
+    let owner = match &candidate.repo.owner {
+      Some(owner) => owner.login.as_str(),
+      None => return false,
+    };
+    let repo = candidate.repo.name.as_str();
+
+    if let (true, Some(cutoff)) = (self.config.since_last_release, cutoff) {
+      let released = self
+        .latest_releases
+        .get_or_fetch(&full_name, || deep::latest_release(&self.gh, owner, repo))
+        .await;
+      match released {
+        Ok(Some(released)) if released >= cutoff => return false,
+        Ok(_) => {}
+        Err(e) => {
+          warn!("Skip {full_name}, failed to get its latest release: {e}");
+          return false;
+        }
+      }
+    }
+
+    let issues_enabled = candidate.repo.has_issues != Some(false);
+    if let (true, true, Some(cutoff)) =
+      (self.config.consider_issue_activity, issues_enabled, cutoff)
+    {
+      let active = self
+        .issue_activity
+        .get_or_fetch(&full_name, || {
+          deep::latest_issue_activity(&self.gh, owner, repo)
+        })
+        .await;
+      match active {
+        Ok(Some(active)) if active >= cutoff => {
+          info!("Protected {full_name}: issues active at {active}");
+          return false;
+        }
+        Ok(_) => {}
+        Err(e) => {
+          warn!("Skip {full_name}, failed to get its issue activity: {e}");
+          return false;
+        }
+      }
+    }
+
+    let (min, max) = (self.config.min_branches, self.config.max_branches);
+    if min.is_some() || max.is_some() {
+      let branches = self
+        .branch_counts
+        .get_or_fetch(&full_name, || {
+          deep::count(&self.gh, format!("repos/{owner}/{repo}/branches"), &[])
+        })
+        .await;
+      match branches {
+        Ok(branches) if !in_range(branches, min, max) => return false,
+        Ok(_) => {}
+        Err(e) => {
+          warn!("Skip {full_name}, failed to count its branches: {e}");
+          return false;
+        }
+      }
+    }
+
+    let (min, max) = (self.config.min_commits, self.config.max_commits);
+    if min.is_some() || max.is_some() {
+      let commits = self
+        .commit_counts
+        .get_or_fetch(&full_name, || {
+          deep::count(&self.gh, format!("repos/{owner}/{repo}/commits"), &[])
+        })
+        .await;
+      match commits {
+        Ok(commits) if !in_range(commits, min, max) => return false,
+        Ok(_) => {}
+        Err(e) => {
+          warn!("Skip {full_name}, failed to count its commits: {e}");
+          return false;
+        }
+      }
+    }
+
+    if self.config.no_readme {
+      let readme = self
+        .readmes
+        .get_or_fetch(&full_name, || deep::has_readme(&self.gh, owner, repo))
+        .await;
+      match readme {
+        Ok(true) => return false,
+        Ok(false) => {}
+        Err(e) => {
+          warn!("Skip {full_name}, failed to look for its README: {e}");
+          return false;
+        }
+      }
+    }
+
+    if self.config.actions_disabled {
+      let enabled = self
+        .actions_enabled
+        .get_or_fetch(&full_name, || deep::actions_enabled(&self.gh, owner, repo))
+        .await;
+      match enabled {
+        Ok(Some(false)) => {}
+        Ok(Some(true)) => return false,
+        Ok(None) => {
+          info!("Skip {full_name}, the token may not read its Actions settings");
+          return false;
+        }
+        Err(e) => {
+          warn!("Skip {full_name}, failed to get its Actions settings: {e}");
+          return false;
+        }
+      }
+    }
+
+    if self.config.solo_only {
+      if candidate
+        .repo
+        .owner
+        .as_ref()
+        .is_none_or(|o| o.r#type != "User")
+      {
+        return false;
+      }
+      let collaborators = self
+        .collaborator_counts
+        .get_or_fetch(&full_name, || {
+          deep::count(&self.gh, format!("repos/{owner}/{repo}/collaborators"), &[])
+        })
+        .await;
+      match collaborators {
+        Ok(collaborators) if collaborators > 1 => return false,
+        Ok(_) => {}
+        Err(e) => {
+          warn!("Skip {full_name}, failed to count its collaborators: {e}");
+          return false;
+        }
+      }
+    }
+
+    if self.config.parent_owner.is_some() || self.config.fork_not_ahead {
+      if candidate.repo.fork != Some(true) {
+        return false;
+      }
+      let parent = self
+        .parents
+        .get_or_fetch(&full_name, || deep::parent(&self.gh, owner, repo))
+        .await;
+      let parent = match parent {
+        Ok(Some(parent)) => parent,
+        Ok(None) => {
+          info!("Skip {full_name}, its parent is gone");
+          return false;
+        }
+        Err(e) => {
+          warn!("Skip {full_name}, failed to get its parent: {e}");
+          return false;
+        }
+      };
+      if let Some(parent_owners) = &self.config.parent_owner {
+        if !parent_owners.contains(&parent.owner) {
+          return false;
+        }
+      }
+      if self.config.fork_not_ahead {
+        let branch = candidate.repo.default_branch.as_deref().unwrap_or("HEAD");
+        let compared = self
+          .comparisons
+          .get_or_fetch(&full_name, || {
+            deep::compare_with_parent(&self.gh, &parent, owner, branch)
+          })
+          .await;
+        match compared {
+          Ok((0, _)) => {}
+          Ok(_) => return false,
+          Err(e) => {
+            warn!("Skip {full_name}, failed to compare it with its parent: {e}");
+            return false;
+          }
+        }
+      }
+    }
+
+    if self.config.skip_actions {
+      let action = self
+        .action_files
+        .get_or_fetch(&full_name, || deep::action_file(&self.gh, owner, repo))
+        .await;
+      match action {
+        Ok(Some(file)) => {
+          warn!("Protect {full_name}, it is an Action others may use, see its {file}");
+          return false;
+        }
+        Ok(None) => {}
+        Err(e) => {
+          warn!("Skip {full_name}, failed to look for an action.yml: {e}");
+          return false;
+        }
+      }
+    }
+    true
+  }
+
+  /// Commits each fork in `candidates` is ahead and behind its parent, for
+  /// showing them. `None` for repos that aren't forks, and for forks whose
+  /// parent is gone or can't be compared with. API calls per fork, limited
+  /// by [`Config::list_concurrency`] and cached like the deep filters.
+  pub async fn upstream_commits(&self, candidates: &[Candidate]) -> Vec<Option<(u64, u64)>> {
+    future::join_all(candidates.iter().map(|c| async {
+      if c.repo.fork != Some(true) {
+        return None;
+      }
+      let _permit = self.semaphore.acquire().await.unwrap();
+      match self.compare_fork(c).await {
+        Ok(compared) => compared,
+        Err(e) => {
+          warn!("Failed to compare {} with its parent: {e}", c.full_name());
+          None
+        }
+      }
+    }))
+    .await
+  }
+
+  /// Like [`deep::compare_with_parent`] for the default branch of the fork
+  /// `candidate`, `None` if its parent is gone.
+  async fn compare_fork(&self, candidate: &Candidate) -> octocrab::Result<Option<(u64, u64)>> {
+    let full_name = candidate.full_name();
+    let owner = match &candidate.repo.owner {
+      Some(owner) => owner.login.as_str(),
+      None => return Ok(None),
+    };
+    let repo = candidate.repo.name.as_str();
+    let parent = self
+      .parents
+      .get_or_fetch(&full_name, || deep::parent(&self.gh, owner, repo))
+      .await?;
+    let parent = match parent {
+      Some(parent) => parent,
+      None => return Ok(None),
+    };
+    let branch = candidate.repo.default_branch.as_deref().unwrap_or("HEAD");
+    self
+      .comparisons
+      .get_or_fetch(&full_name, || {
+        deep::compare_with_parent(&self.gh, &parent, owner, branch)
+      })
+      .await
+      .map(Some)
+  }
+
+  async fn find_items(&self, candidates: Vec<Candidate>) -> Vec<Candidate> {
+    let (target, kind) = (self.config.target, self.config.releases_only);
+    let before = self.cutoff();
+    // Only the CLI requires --inactive for them, a library Config may not
+    // set it
+    if let (Target::WorkflowRuns | Target::Releases, None) = (target, before) {
+      error!(
+        "No {} is old without Config::inactive, finding none",
+        target.item()
+      );
+      return vec![];
+    }
+    let mut handles = vec![];
+    for mut candidate in candidates {
+      let owner = match &candidate.repo.owner {
+        Some(owner) => owner.login.clone(),
+        None => continue,
+      };
+      let gh = Arc::clone(&self.gh);
+      let semaphore = Arc::clone(&self.semaphore);
+      let handle = tokio::spawn(async move {
+        let _permit = semaphore.acquire().await.unwrap();
+        let name = &candidate.repo.name;
+        let found = match (target, before) {
+          (Target::WorkflowRuns, Some(before)) => {
+            workflow_runs::list_old(&gh, &owner, name, before)
+              .await
+              .map(|ids| candidate.items = ids)
+          }
+          (Target::Releases, Some(before)) => releases::list_old(&gh, &owner, name, before, kind)
+            .await
+            .map(|ids| candidate.items = ids),
+          (Target::Labels, _) => labels::list_unused(&gh, &owner, name)
+            .await
+            .map(|names| candidate.labels = names),
+          (Target::WorkflowRuns | Target::Releases, None)
+          | (Target::Repos, _)
+          | (Target::Packages, _)
+          | (Target::Pages, _)
+          | (Target::Subscriptions, _)
+          | (Target::Stars, _) => unreachable!(),
+        };
+        if let Err(e) = found {
+          error!("Failed to list {}s of {owner}/{name}: {e}", target.item());
+        }
+        candidate
+      });
+      handles.push(handle);
+    }
+
+    let mut candidates = vec![];
+    for handle in handles {
+      let candidate = handle.await.unwrap();
+      if candidate.count(target) > 0 {
+        candidates.push(candidate);
+      }
+    }
+    candidates
+  }
+
+  /// Returns a handle to stop a running [`Cleaner::delete`].
+  pub fn stopper(&self) -> Stopper {
+    self.stopper.clone()
+  }
+
+  /// Number of [`Config::on_delete_cmd`] runs that failed so far. They are
+  /// logged but don't count as failed deletions, the deletion happened.
+  pub fn hook_failures(&self) -> usize {
+    self.hook_failures.load(Ordering::SeqCst)
+  }
+
+  /// Number of deletions that failed so far, for reporting progress while
+  /// [`Cleaner::delete`] runs.
+  pub fn failures(&self) -> usize {
+    self.failures.load(Ordering::SeqCst)
+  }
+
+  /// Deletes the candidates, or the things inside them for targets other
+  /// than [`Target::Repos`]. `progress` is called with a message after
+  /// every attempted deletion, telling whether it failed. Once
+  /// [`Stopper::stop`] is called, deletions already running finish but
+  /// pending ones are skipped.
+  /// With [`Target::Packages`] nothing is deleted, each candidate fails
+  /// with [`DeleteError::NotRepos`], see [`Cleaner::delete_packages`].
+  pub async fn delete<F>(&self, candidates: Vec<Candidate>, progress: F) -> Vec<Outcome>
+  where
+    F: Fn(String) + Send + Sync + 'static,
+  {
+    if self.config.target == Target::Packages {
+      error!("Packages aren't deleted as repos, use Cleaner::delete_packages");
+      return candidates
+        .into_iter()
+        .map(|c| Outcome {
+          full_name: c.full_name(),
+          deleted: 0,
+          skipped: 0,
+          errors: vec![DeleteError::NotRepos],
+        })
+        .collect();
+    }
+    let progress = Arc::new(progress);
+    let mut handles = vec![];
+    for candidate in candidates {
+      let full_name = candidate.full_name();
+      let owner = match candidate.repo.owner {
+        Some(owner) => owner.login,
+        None => continue,
+      };
+      let repo = candidate.repo.name;
+      let target = self.config.target;
+      let items = match target {
+        Target::Repos | Target::Pages | Target::Subscriptions | Target::Stars => vec![None],
+        Target::Packages => unreachable!(),
+        Target::WorkflowRuns | Target::Releases => candidate
+          .items
+          .into_iter()
+          .map(|id| Some(Item::Id(id)))
+          .collect(),
+        Target::Labels => candidate
+          .labels
+          .into_iter()
+          .map(|name| Some(Item::Label(name)))
+          .collect(),
+      };
+      for item in items {
+        let (owner, repo) = (owner.clone(), repo.clone());
+        let what = match item {
+          None => format!("{}/{}", owner, repo),
+          Some(ref item) => format!("{} {item} of {owner}/{repo}", target.item()),
+        };
+        let backup = match (target, &self.config.backup_dir) {
+          (Target::Repos, Some(_)) => Some((owner.clone(), repo.clone())),
+          _ => None,
+        };
+        let manifest = self.rename_manifest.clone();
+        let retry_taken = self.config.retry_on_422;
+        // Set by the first attempt, so a retry only sends the delete again
+        let renamed = Arc::new(tokio::sync::OnceCell::new());
+        let request = move |gh: Arc<Octocrab>| {
+          let (owner, repo, manifest) = (owner.clone(), repo.clone(), manifest.clone());
+          let (item, renamed) = (item.clone(), Arc::clone(&renamed));
+          async move {
+            match (target, item, manifest) {
+              (Target::WorkflowRuns, Some(Item::Id(id)), _) => {
+                workflow_runs::delete(&gh, &owner, &repo, id).await
+              }
+              (Target::Releases, Some(Item::Id(id)), _) => {
+                releases::delete(&gh, &owner, &repo, id).await
+              }
+              (Target::Labels, Some(Item::Label(name)), _) => {
+                labels::delete(&gh, &owner, &repo, &name).await
+              }
+              (Target::Pages, _, _) => pages::delete(&gh, &owner, &repo).await,
+              (Target::Subscriptions, _, _) => subscriptions::delete(&gh, &owner, &repo).await,
+              (Target::Stars, _, _) => stars::delete(&gh, &owner, &repo).await,
+              (Target::Repos, None, Some(manifest)) => {
+                let to = renamed.get_or_try_init(|| async {
+                  let to = rename::rename(&gh, &owner, &repo, retry_taken).await?;
+                  manifest.record(&Renamed {
+                    owner: &owner,
+                    from: &repo,
+                    to: &to,
+                  });
+                  Ok::<_, octocrab::Error>(to)
+                });
+                match to.await {
+                  Ok(to) => send_delete(&gh, format!("repos/{owner}/{to}")).await,
+                  Err(e) => (Reply::default(), Err(e)),
+                }
+              }
+              (Target::Repos, None, None) => {
+                send_delete(&gh, format!("repos/{owner}/{repo}")).await
+              }
+              (target, item, _) => unreachable!("no {item:?} to delete for {target:?}"),
+            }
+          }
+        };
+        let task = self.spawn_delete(full_name.clone(), what, backup, request, &progress);
+        handles.push(task.await);
+      }
+    }
+    Self::collect_outcomes(handles).await
+  }
+
+  /// Deletes the packages like [`Cleaner::delete`], with an [`Outcome`]
+  /// per package named by [`Package::full_name`].
+  pub async fn delete_packages<F>(&self, packages: Vec<Package>, progress: F) -> Vec<Outcome>
+  where
+    F: Fn(String) + Send + Sync + 'static,
+  {
+    let progress = Arc::new(progress);
+    let mut handles = vec![];
+    for package in packages {
+      let full_name = package.full_name();
+      let what = format!("package {full_name}");
+      let request = move |gh: Arc<Octocrab>| {
+        let package = package.clone();
+        async move { packages::delete(&gh, &package).await }
+      };
+      let task = self.spawn_delete(full_name, what, None, request, &progress);
+      handles.push(task.await);
+    }
+    Self::collect_outcomes(handles).await
+  }
+
+  /// Runs a single delete `request` once the delete semaphore and the pace
+  /// allow, unless stopped by then. `what` names the deleted thing in
+  /// messages.
+  /// The permit, or with a `backup` the clone permit, is taken before
+  /// spawning, so deletions start in the order this is called in.
+  /// The request gets the client from [`Auth::client`], and is sent again
+  /// with a renewed installation token if the first was rejected.
+  async fn spawn_delete<R, Fut, F>(
+    &self,
+    full_name: String,
+    what: String,
+    backup: Option<(String, String)>,
+    request: R,
+    progress: &Arc<F>,
+  ) -> DeleteTask
+  where
+    R: Fn(Arc<Octocrab>) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = (Reply, octocrab::Result<()>)> + Send + 'static,
+    F: Fn(String) + Send + Sync + 'static,
+  {
+    let semaphore = Arc::clone(&self.delete_semaphore);
+    let backup_dir = self.config.backup_dir.clone();
+    let (clone_permit, permit) = if backup.is_some() && backup_dir.is_some() {
+      let clone_semaphore = Arc::clone(&self.clone_semaphore);
+      (Some(clone_semaphore.acquire_owned().await.unwrap()), None)
+    } else {
+      (
+        None,
+        Some(Arc::clone(&semaphore).acquire_owned().await.unwrap()),
+      )
+    };
+    let pace = self.pace.clone();
+    let attempt_log = self.attempt_log.clone();
+    let stopper = self.stopper.clone();
+    let timeout = self.config.request_timeout;
+    let progress = Arc::clone(progress);
+    let auth = Arc::clone(&self.auth);
+    let backup_format = self.config.backup_format;
+    let fail_fast = self.config.fail_fast;
+    let max_failures = self.config.max_failures;
+    let hook = self.config.on_delete_cmd.clone();
+    let hook_failures = Arc::clone(&self.hook_failures);
+    let failures = Arc::clone(&self.failures);
+    tokio::spawn(async move {
+      let fail = || {
+        let failed = failures.fetch_add(1, Ordering::SeqCst) + 1;
+        if stops_deletion(failed, fail_fast, max_failures) {
+          stopper.stop();
+        }
+      };
+      // Before taking a deletion permit, so slow clones don't hold up
+      // deletions of repos already backed up
+      if let (Some((owner, repo)), Some(dir)) = (backup, backup_dir) {
+        let _permit = clone_permit;
+        if stopper.is_stopped() {
+          return (full_name, None);
+        }
+        let client = match auth.client().await {
+          Ok(client) => client,
+          Err(e) => {
+            error!("Failed to back up {what}, can't renew the installation token: {e}");
+            fail();
+            let result = Err(DeleteError::from(e));
+            progress(progress_message(&what, &result));
+            return (full_name, Some(result));
+          }
+        };
+        let (gh, token) = (&client.gh, &client.token);
+        match backup::save(gh, token, &dir, backup_format, &owner, &repo).await {
+          Ok((path, true)) => info!("Backed up {what} to {}", path.display()),
+          Ok((path, false)) => info!("Kept the complete backup of {what} at {}", path.display()),
+          Err(e) => {
+            error!("Failed to back up {what}, keeping it: {e}");
+            fail();
+            let result = Err(DeleteError::Backup(e));
+            progress(progress_message(&what, &result));
+            return (full_name, Some(result));
+          }
+        }
+      }
+      let _permit = match permit {
+        Some(permit) => permit,
+        None => semaphore.acquire_owned().await.unwrap(),
+      };
+      if let Some(pace) = pace {
+        pace.wait(&stopper).await;
+      }
+      if stopper.is_stopped() {
+        return (full_name, None);
+      }
+      let mut client = match auth.client().await {
+        Ok(client) => client,
+        Err(e) => {
+          error!("Failed to delete {what}, can't renew the installation token: {e}");
+          fail();
+          let result = Err(DeleteError::from(e));
+          progress(progress_message(&what, &result));
+          return (full_name, Some(result));
+        }
+      };
+      let mut attempt = 1;
+      let (result, request_id) = loop {
+        let started = Instant::now();
+        let sent = request(Arc::clone(&client.gh));
+        let (reply, result) = match tokio::time::timeout(timeout, sent).await {
+          Ok((reply, result)) => (reply, result.map_err(DeleteError::from)),
+          Err(_) => (Reply::default(), Err(DeleteError::Timeout(timeout))),
+        };
+        if let Some(log) = &attempt_log {
+          log.record(&Attempt {
+            name: &what,
+            attempt,
+            status: reply.status,
+            request_id: reply.request_id.as_deref(),
+            latency_ms: started.elapsed().as_millis(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+          });
+        }
+        if reply.status != Some(401) || attempt > 1 {
+          break (result, reply.request_id);
+        }
+        match auth.renew(&client).await {
+          Ok(true) => warn!("Token rejected while deleting {what}, retrying with a renewed one"),
+          Ok(false) => break (result, reply.request_id),
+          Err(e) => {
+            warn!("Failed to renew the rejected installation token: {e}");
+            break (result, reply.request_id);
+          }
+        }
+        client = match auth.client().await {
+          Ok(client) => client,
+          Err(_) => break (result, reply.request_id),
+        };
+        attempt += 1;
+      };
+      let request_id = match request_id {
+        Some(id) => format!(" (request id {id})"),
+        None => String::new(),
+      };
+      match &result {
+        Err(DeleteError::Timeout(_)) => {
+          error!("Failed to delete {what}: request timed out")
+        }
+        Err(err) => error!("Failed to delete {what}: {err:?}{request_id}"),
+        Ok(()) => {}
+      }
+      if result.is_err() {
+        fail();
+      }
+      // Still holding the permit, so hooks run at most --delete-concurrency
+      // at once
+      if let (Some(cmd), Ok(())) = (&hook, &result) {
+        if let Err(e) = hook::run(cmd, &full_name, &what).await {
+          warn!("--on-delete-cmd failed for {what}: {e}");
+          hook_failures.fetch_add(1, Ordering::SeqCst);
+        }
+      }
+      progress(progress_message(&what, &result));
+      (full_name, Some(result))
+    })
+  }
+
+  /// Sums up the results of [`Cleaner::spawn_delete`] per name.
+  async fn collect_outcomes(handles: Vec<DeleteTask>) -> Vec<Outcome> {
+    let mut outcomes: HashMap<String, Outcome> = HashMap::new();
+    for handle in handles {
+      let (full_name, result) = handle.await.unwrap();
+      let outcome = outcomes
+        .entry(full_name.clone())
+        .or_insert_with(|| Outcome {
+          full_name,
+          deleted: 0,
+          skipped: 0,
+          errors: vec![],
+        });
+      match result {
+        Some(Ok(())) => outcome.deleted += 1,
+        Some(Err(e)) => outcome.errors.push(e),
+        None => outcome.skipped += 1,
+      }
+    }
+    let mut outcomes: Vec<_> = outcomes.into_values().collect();
+    outcomes.sort_by(|a, b| a.full_name.cmp(&b.full_name));
+    outcomes
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn at(text: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(text)
+      .unwrap()
+      .with_timezone(&Utc)
+  }
+
+  /// A candidate for the repo `full_name` with the other repo `fields`.
+  pub(crate) fn candidate(full_name: &str, fields: serde_json::Value) -> Candidate {
+    let (owner, name) = full_name.split_once('/').unwrap();
+    let url = format!("https://api.github.com/users/{owner}");
+    let mut repo = serde_json::json!({
+      "id": 1,
+      "name": name,
+      "full_name": full_name,
+      "url": format!("https://api.github.com/repos/{full_name}"),
+      "owner": {
+        "login": owner,
+        "id": 1,
+        "node_id": "",
+        "avatar_url": url,
+        "gravatar_id": "",
+        "url": url,
+        "html_url": url,
+        "followers_url": url,
+        "following_url": url,
+        "gists_url": url,
+        "starred_url": url,
+        "subscriptions_url": url,
+        "organizations_url": url,
+        "repos_url": url,
+        "events_url": url,
+        "received_events_url": url,
+        "type": "User",
+        "site_admin": false,
+      },
+    });
+    let repo_fields = repo.as_object_mut().unwrap();
+    repo_fields.extend(fields.as_object().unwrap().clone());
+    Candidate::from(serde_json::from_value::<Repository>(repo).unwrap())
+  }
+
+  fn names(candidates: &[Candidate]) -> Vec<String> {
+    candidates.iter().map(Candidate::full_name).collect()
+  }
+
+  /// The options of the CLI without any given, for the `target`.
+  pub(crate) fn config(target: Target) -> Config {
+    Config {
+      token: "ghp_test".to_string(),
+      api_version: DEFAULT_API_VERSION.to_string(),
+      owner_cache_dir: None,
+      refresh_owner_cache: false,
+      fork: false,
+      visibility: vec!["public".to_string()],
+      affiliation: vec!["owner".to_string()],
+      list_sort: None,
+      list_direction: None,
+      list_type: None,
+      owner: None,
+      exclude_owner: vec![],
+      owner_case_sensitive: false,
+      all_orgs: false,
+      org_admin_only: false,
+      star: 0,
+      exclude_pinned: false,
+      target,
+      inactive: None,
+      starred_before: None,
+      protect_pushed_within: None,
+      ignore: None,
+      include_special: false,
+      releases_only: ReleaseKind::All,
+      package_type: PackageType::Npm,
+      per_page: 100,
+      list_concurrency: 1,
+      delete_concurrency: 1,
+      request_timeout: Duration::from_secs(60),
+      max_deletions_per_minute: None,
+      pushed_never: false,
+      max_star_rate: None,
+      default_branch: None,
+      deep_activity: false,
+      fetch_details: false,
+      since_last_release: false,
+      consider_issue_activity: false,
+      min_branches: None,
+      max_branches: None,
+      min_commits: None,
+      max_commits: None,
+      parent_owner: None,
+      fork_not_ahead: false,
+      solo_only: false,
+      skip_actions: false,
+      no_readme: false,
+      actions_disabled: false,
+      filter_expr: None,
+      backup_dir: None,
+      backup_format: BackupFormat::Git,
+      clone_concurrency: 1,
+      fail_fast: false,
+      max_failures: None,
+      skip_packaged: false,
+      protect_with_secrets: false,
+      retain_newest: None,
+      retry_on_422: false,
+      squash_forks: false,
+      on_delete_cmd: None,
+    }
+  }
+
+  #[test]
+  fn progress_message_tells_failures() {
+    assert_eq!(progress_message("me/a", &Ok(())), "Deleted me/a");
+    let timeout = Err(DeleteError::Timeout(Duration::from_secs(60)));
+    assert_eq!(
+      progress_message("me/a", &timeout),
+      "Failed to delete me/a: timed out after 1m"
+    );
+    let backup = Err(DeleteError::Backup("no space left".to_string()));
+    assert_eq!(
+      progress_message("release 7 of me/a", &backup),
+      "Failed to delete release 7 of me/a: backup failed, not deleted: no space left"
+    );
+  }
+
+  /// The request line the [`Cleaner`] of `config` sends for `list`,
+  /// answered with no repos.
+  async fn listing_request<F, Fut>(config: Config, list: F) -> String
+  where
+    F: FnOnce(Cleaner) -> Fut,
+    Fut: std::future::Future<Output = octocrab::Result<Vec<Repository>>>,
+  {
+    let mut cleaner = Cleaner::new(config).unwrap();
+    let (base, server) = crate::api_trace::tests::serve_once("[]");
+    cleaner.gh = Arc::new(crate::api_trace::tests::local_gh(&base));
+    assert!(list(cleaner).await.unwrap().is_empty());
+    let request = server.join().unwrap();
+    request.lines().next().unwrap().to_string()
+  }
+
+  #[tokio::test]
+  async fn listings_use_per_page() {
+    let mut config = config(Target::Repos);
+    config.per_page = 7;
+    let org = listing_request(config.clone(), |c| async move {
+      c.list_org_repos("some-org").await
+    })
+    .await;
+    assert!(org.starts_with("GET /orgs/some-org/repos?"), "{org}");
+    assert!(org.contains("per_page=7"), "{org}");
+    let user = listing_request(config, |c| async move { c.list_user_repos().await }).await;
+    assert!(user.starts_with("GET /user/repos?"), "{user}");
+    assert!(user.contains("per_page=7"), "{user}");
+  }
+
+  #[test]
+  fn max_failures_boundary() {
+    assert!(!stops_deletion(0, false, None));
+    assert!(!stops_deletion(5, false, None));
+    assert!(stops_deletion(1, true, None));
+    assert!(!stops_deletion(2, false, Some(3)));
+    assert!(stops_deletion(3, false, Some(3)));
+    assert!(stops_deletion(4, false, Some(3)));
+    assert!(!stops_deletion(0, false, Some(0)));
+  }
+
+  #[test]
+  fn squash_keeps_the_newest_fork_of_each_parent() {
+    let fork = |full_name, pushed_at| {
+      candidate(
+        full_name,
+        serde_json::json!({ "fork": true, "pushed_at": pushed_at }),
+      )
+    };
+    let candidates = vec![
+      fork("me/a", "2022-01-01T00:00:00Z"),
+      fork("me/b", "2022-03-01T00:00:00Z"),
+      fork("me/c", "2022-02-01T00:00:00Z"),
+      fork("me/d", "2022-01-01T00:00:00Z"),
+      candidate("me/e", serde_json::json!({})),
+      fork("me/f", "2022-01-01T00:00:00Z"),
+    ];
+    let parent = |full_name: &str| Some(full_name.to_string());
+    let parents = vec![
+      parent("up/x"),
+      parent("Up/X"),
+      parent("up/x"),
+      parent("up/y"),
+      None,
+      None,
+    ];
+    let squashed = squash_by_parent(candidates, parents);
+    assert_eq!(names(&squashed), ["me/a", "me/c"]);
+  }
+
+  #[tokio::test]
+  async fn no_old_items_without_inactive() {
+    for target in [Target::WorkflowRuns, Target::Releases] {
+      let cleaner = Cleaner::new(config(target)).unwrap();
+      let candidates = vec![candidate("me/app", serde_json::json!({}))];
+      assert!(
+        cleaner.find_items(candidates).await.is_empty(),
+        "{target:?}"
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn delete_refuses_packages() {
+    let cleaner = Cleaner::new(config(Target::Packages)).unwrap();
+    let outcomes = cleaner
+      .delete(vec![candidate("me/app", serde_json::json!({}))], |_| {})
+      .await;
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].full_name, "me/app");
+    assert_eq!(outcomes[0].deleted, 0);
+    assert!(matches!(outcomes[0].errors[..], [DeleteError::NotRepos]));
+  }
+
+  #[test]
+  fn star_rate_per_year() {
+    let now = at("2022-01-01T00:00:00Z");
+    let rate = star_rate(10, now - chrono::Duration::days(730), now);
+    assert!((rate - 5.0).abs() < 0.01, "{rate}");
+  }
+
+  #[test]
+  fn star_rate_treats_new_repos_as_min_age() {
+    let now = at("2022-01-01T00:00:00Z");
+    let floor = star_rate(3, now - chrono::Duration::days(STAR_RATE_MIN_AGE_DAYS), now);
+    assert_eq!(star_rate(3, now - chrono::Duration::days(1), now), floor);
+    assert_eq!(star_rate(3, now, now), floor);
+    assert!(star_rate(3, now + chrono::Duration::days(1), now).is_finite());
+  }
+
+  #[test]
+  fn never_pushed_within_tolerance() {
+    let created = at("2022-01-01T00:00:00Z");
+    let secs = chrono::Duration::seconds;
+    assert!(never_pushed(created, created));
+    assert!(never_pushed(
+      created,
+      created + secs(PUSHED_NEVER_TOLERANCE_SECS)
+    ));
+    assert!(never_pushed(created, created - secs(5)));
+    assert!(!never_pushed(
+      created,
+      created + secs(PUSHED_NEVER_TOLERANCE_SECS + 1)
+    ));
+    assert!(!never_pushed(
+      created,
+      created - secs(PUSHED_NEVER_TOLERANCE_SECS + 1)
+    ));
+  }
+
+  #[test]
+  fn sort_breaks_ties_by_full_name() {
+    let mut candidates = vec![
+      candidate("b/x", serde_json::json!({ "stargazers_count": 1 })),
+      candidate("a/z", serde_json::json!({ "stargazers_count": 1 })),
+      candidate("a/y", serde_json::json!({ "stargazers_count": 0 })),
+      candidate("a/x", serde_json::json!({})),
+    ];
+    sort(&mut candidates, SortKey::Stars);
+    assert_eq!(names(&candidates), ["a/x", "a/y", "a/z", "b/x"]);
+    sort(&mut candidates, SortKey::Name);
+    assert_eq!(names(&candidates), ["a/x", "a/y", "a/z", "b/x"]);
+  }
+
+  #[test]
+  fn sort_puts_never_pushed_first() {
+    let mut candidates = vec![
+      candidate(
+        "a/new",
+        serde_json::json!({ "pushed_at": "2022-06-01T00:00:00Z" }),
+      ),
+      candidate(
+        "a/old",
+        serde_json::json!({ "pushed_at": "2020-06-01T00:00:00Z" }),
+      ),
+      candidate("a/never", serde_json::json!({})),
+    ];
+    sort(&mut candidates, SortKey::Pushed);
+    assert_eq!(names(&candidates), ["a/never", "a/old", "a/new"]);
+  }
+
+  #[test]
+  fn parse_size_units() {
+    assert_eq!(parse_size("512"), Ok(512));
+    assert_eq!(parse_size("2kb"), Ok(2048));
+    assert_eq!(parse_size("500MB"), Ok(500 * 1024 * 1024));
+    assert_eq!(parse_size("1.5g"), Ok(3 * 512 * 1024 * 1024));
+    assert_eq!(parse_size(" 1 GiB "), Ok(1024 * 1024 * 1024));
+    assert_eq!(parse_size("1tb"), Ok(1 << 40));
+  }
+
+  #[test]
+  fn parse_size_rejects_garbage() {
+    assert_eq!(
+      parse_size("gb"),
+      Err("expected a size like 1gb, found 'gb'".to_string())
+    );
+    assert_eq!(
+      parse_size("5pb"),
+      Err("unknown unit 'pb', expected b, kb, mb, gb or tb".to_string())
+    );
+  }
+
+  #[test]
+  fn in_range_bounds() {
+    assert!(in_range(3, Some(3), Some(5)));
+    assert!(in_range(4, Some(3), Some(5)));
+    assert!(in_range(5, Some(3), Some(5)));
+    assert!(!in_range(2, Some(3), Some(5)));
+    assert!(!in_range(6, Some(3), Some(5)));
+    assert!(in_range(4, Some(4), Some(4)));
+    assert!(!in_range(4, Some(5), Some(3)));
+    assert!(in_range(u64::MAX, Some(3), None));
+    assert!(!in_range(2, Some(3), None));
+    assert!(in_range(0, None, Some(5)));
+    assert!(!in_range(6, None, Some(5)));
+    assert!(in_range(0, None, None));
+    assert!(in_range(u64::MAX, None, None));
+  }
+
+  #[test]
+  fn parse_when_durations() {
+    let days = |n: u64| When::Ago(Duration::from_secs(n * 24 * 60 * 60));
+    assert_eq!(parse_when("90d"), Ok(days(90)));
+    assert_eq!(parse_when(" 1w "), Ok(days(7)));
+    assert_eq!(parse_when("6mo"), parse_when("6months"));
+    assert_eq!(parse_when("1y 2mo"), parse_when("1y 2months"));
+    assert!(matches!(parse_when("2y"), Ok(When::Ago(_))));
+  }
+
+  #[test]
+  fn parse_when_dates() {
+    let midnight = When::At(at("2022-01-01T00:00:00Z"));
+    assert_eq!(parse_when("2022-01-01"), Ok(midnight));
+    assert_eq!(parse_when("2022-01-01T00:00:00"), Ok(midnight));
+    assert_eq!(parse_when("2022-01-01T00:00:00Z"), Ok(midnight));
+    assert_eq!(parse_when("2022-01-01T02:00:00+02:00"), Ok(midnight));
+  }
+
+  #[test]
+  fn parse_when_rejects_garbage_and_overflow() {
+    assert!(parse_when("soon").unwrap_err().ends_with("found 'soon'"));
+    assert!(parse_when("2022-13-01").is_err());
+    assert_eq!(
+      parse_when("500000y"),
+      Err("'500000y' reaches back before the earliest date there is".to_string())
+    );
+  }
+
+  #[test]
+  fn when_resolves_against_now() {
+    let now = at("2022-01-01T00:00:00Z");
+    let day = When::Ago(Duration::from_secs(24 * 60 * 60));
+    assert_eq!(day.resolve(now), at("2021-12-31T00:00:00Z"));
+    assert_eq!(When::At(now).resolve(at("2030-01-01T00:00:00Z")), now);
+    let forever = When::Ago(Duration::from_secs(u64::MAX));
+    assert_eq!(forever.resolve(now), DateTime::<Utc>::MIN_UTC);
+  }
+
+  #[test]
+  fn glob_match_stars_and_question_marks() {
+    assert!(glob_match("*", ""));
+    assert!(glob_match("*", "owner/name"));
+    assert!(glob_match("test-*", "test-"));
+    assert!(glob_match("test-*", "test-repo"));
+    assert!(glob_match("*-old", "a-b-old"));
+    assert!(glob_match("a*b*c", "aXbYbZc"));
+    assert!(glob_match("repo?", "repo1"));
+    assert!(glob_match("owner/*", "owner/name"));
+    assert!(!glob_match("repo?", "repo"));
+    assert!(!glob_match("test-*", "a-test-repo"));
+    assert!(!glob_match("a*b*c", "aXbYbZ"));
+    assert!(!glob_match("", "a"));
+  }
+
+  #[tokio::test]
+  async fn in_scope_by_owner_and_visibility() {
+    let repo = |full_name, visibility| {
+      let fields = serde_json::json!({ "visibility": visibility });
+      candidate(full_name, fields).repo
+    };
+    let mut args = config(Target::Repos);
+    args.owner = Some(vec!["Me".to_string()]);
+    args.visibility = vec!["all".to_string()];
+    let cleaner = Cleaner::new(args.clone()).unwrap();
+    assert!(cleaner.in_scope(&repo("me/a", "public")));
+    assert!(cleaner.in_scope(&repo("ME/b", "private")));
+    assert!(!cleaner.in_scope(&repo("other/c", "public")));
+
+    args.owner_case_sensitive = true;
+    args.visibility = vec!["public".to_string()];
+    let cleaner = Cleaner::new(args).unwrap();
+    assert!(cleaner.in_scope(&repo("Me/a", "public")));
+    assert!(!cleaner.in_scope(&repo("me/a", "public")));
+    assert!(!cleaner.in_scope(&repo("Me/b", "private")));
+  }
+
+  #[tokio::test]
+  async fn owner_summaries_by_owner() {
+    let repo = |full_name, fields: serde_json::Value| candidate(full_name, fields).repo;
+    let repos = [
+      repo(
+        "org/a",
+        serde_json::json!({ "fork": true, "size": 10, "stargazers_count": 1,
+          "pushed_at": "2021-06-01T00:00:00Z" }),
+      ),
+      repo(
+        "me/b",
+        serde_json::json!({ "archived": true, "size": 5, "stargazers_count": 2,
+          "pushed_at": "2022-06-01T00:00:00Z" }),
+      ),
+      repo(
+        "Org/c",
+        serde_json::json!({ "size": 7, "stargazers_count": 4 }),
+      ),
+      repo(
+        "me/d",
+        serde_json::json!({ "visibility": "private", "size": 100 }),
+      ),
+    ];
+    let mut args = config(Target::Repos);
+    args.inactive = Some(When::At(at("2022-01-01T00:00:00Z")));
+    let summaries = Cleaner::new(args).unwrap().owner_summaries(&repos);
+    let counts: Vec<_> = summaries
+      .iter()
+      .map(|s| {
+        (
+          s.owner.as_str(),
+          s.repos,
+          s.forks,
+          s.archived,
+          s.stale,
+          s.size,
+          s.stars,
+        )
+      })
+      .collect();
+    assert_eq!(
+      counts,
+      [("me", 1, 0, 1, 0, 5, 2), ("org", 2, 1, 0, 2, 17, 5)]
+    );
+    let markdown = report::owner_summary_markdown(&summaries);
+    assert!(
+      markdown.ends_with("| **Total** | **3** | **1** | **1** | **2** | **22** | **7** |\n"),
+      "{markdown}"
+    );
+  }
+
+  #[test]
+  fn replace_me_with_the_login() {
+    let owners = |list: &[&str]| list.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+    assert_eq!(replace_me(owners(&["@me"]), "me"), ["me"]);
+    assert_eq!(
+      replace_me(owners(&["org", "@me", "other"]), "me"),
+      ["org", "me", "other"]
+    );
+    assert_eq!(
+      replace_me(owners(&["me", "@me", "org"]), "me"),
+      ["me", "org"]
+    );
+    assert_eq!(
+      replace_me(owners(&["@me", "org", "@me"]), "me"),
+      ["me", "org"]
+    );
+    assert_eq!(replace_me(owners(&["org"]), "me"), ["org"]);
+  }
+
+  #[test]
+  fn owners_distinct_and_sorted() {
+    let candidates = [
+      candidate("org/a", serde_json::json!({})),
+      candidate("me/b", serde_json::json!({})),
+      candidate("org/c", serde_json::json!({})),
+      candidate("admin/d", serde_json::json!({})),
+    ];
+    assert_eq!(owners(&candidates), ["admin", "me", "org"]);
+    assert!(owners(&[]).is_empty());
+  }
+
+  #[test]
+  fn special_profile_and_github_repos() {
+    let special = |full_name| special_repo(&candidate(full_name, serde_json::json!({})).repo);
+    assert_eq!(special("Me/me"), Some("the profile repo of its owner"));
+    assert_eq!(special("me/me"), Some("the profile repo of its owner"));
+    assert_eq!(
+      special("org/.github"),
+      Some("it holds the defaults of its owner's repos")
+    );
+    assert!(special("org/.GitHub").is_some());
+    assert_eq!(special("me/other"), None);
+    assert_eq!(special("me/me-too"), None);
+    assert_eq!(special("me/github"), None);
+    assert_eq!(special("me/.github-old"), None);
+  }
+
+  #[tokio::test]
+  async fn visibility_all_keeps_every_repo() {
+    let repos = || {
+      ["public", "private", "internal"]
+        .map(|vis| {
+          let fields = serde_json::json!({ "visibility": vis, "fork": false });
+          candidate(&format!("me/{vis}"), fields).repo
+        })
+        .to_vec()
+    };
+    let mut args = config(Target::Repos);
+    args.visibility = vec!["all".to_string()];
+    let cleaner = Cleaner::new(args.clone()).unwrap();
+    assert_eq!(
+      names(&cleaner.shallow_filter(repos())),
+      ["me/public", "me/private", "me/internal"]
+    );
+    args.visibility = vec!["public".to_string(), "all".to_string()];
+    let cleaner = Cleaner::new(args).unwrap();
+    assert_eq!(cleaner.shallow_filter(repos()).len(), 3);
+  }
+
+  #[tokio::test]
+  async fn default_branch_filter_skips_empty_repos() {
+    let repos = || {
+      let repo = |full_name, branch: Option<&str>| {
+        let fields = serde_json::json!({ "default_branch": branch, "fork": false });
+        candidate(full_name, fields).repo
+      };
+      vec![
+        repo("me/old", Some("master")),
+        repo("me/new", Some("main")),
+        repo("me/empty", None),
+      ]
+    };
+    assert_eq!(repos()[2].default_branch, None);
+    let mut args = config(Target::Repos);
+    let cleaner = Cleaner::new(args.clone()).unwrap();
+    assert_eq!(
+      names(&cleaner.shallow_filter(repos())),
+      ["me/old", "me/new", "me/empty"]
+    );
+    args.default_branch = Some("master".to_string());
+    let cleaner = Cleaner::new(args).unwrap();
+    assert_eq!(names(&cleaner.shallow_filter(repos())), ["me/old"]);
+  }
+
+  #[test]
+  fn undeletable_by_permissions_and_scope() {
+    let permissions = |admin, push| serde_json::json!({ "admin": admin, "push": push, "pull": true, "maintain": false });
+    let admin = candidate(
+      "me/a",
+      serde_json::json!({ "permissions": permissions(true, true) }),
+    );
+    let pusher = candidate(
+      "org/b",
+      serde_json::json!({ "permissions": permissions(false, true) }),
+    );
+    let reader = candidate(
+      "org/c",
+      serde_json::json!({ "permissions": permissions(false, false) }),
+    );
+    let archived = candidate("me/d", serde_json::json!({ "archived": true }));
+    let unknown = candidate("me/e", serde_json::json!({}));
+
+    assert_eq!(admin.undeletable(Target::Repos, true), None);
+    assert_eq!(unknown.undeletable(Target::Repos, true), None);
+    assert_eq!(
+      pusher.undeletable(Target::Repos, true),
+      Some("you aren't admin of it")
+    );
+    assert_eq!(
+      admin.undeletable(Target::Repos, false),
+      Some("the token lacks the delete_repo scope")
+    );
+    assert_eq!(pusher.undeletable(Target::Releases, true), None);
+    assert_eq!(
+      reader.undeletable(Target::Releases, true),
+      Some("you can't push to it")
+    );
+    assert_eq!(
+      archived.undeletable(Target::WorkflowRuns, true),
+      Some("it is archived, so read-only")
+    );
+  }
+
+  #[test]
+  fn drop_newest_per_owner() {
+    let pushed = |at: &str| serde_json::json!({ "pushed_at": at });
+    let candidates = vec![
+      candidate("me/old", pushed("2020-01-01T00:00:00Z")),
+      candidate("me/new", pushed("2022-01-01T00:00:00Z")),
+      candidate("me/never", serde_json::json!({})),
+      candidate("Me/newer", pushed("2022-06-01T00:00:00Z")),
+      candidate("org/only", pushed("2019-01-01T00:00:00Z")),
+    ];
+    let left = drop_newest(candidates.clone(), 1);
+    assert_eq!(names(&left), ["me/old", "me/new", "me/never"]);
+    let left = drop_newest(candidates.clone(), 2);
+    assert_eq!(names(&left), ["me/old", "me/never"]);
+    assert!(drop_newest(candidates.clone(), 5).is_empty());
+    assert_eq!(drop_newest(candidates, 0).len(), 5);
+  }
+
+  #[test]
+  fn parse_full_name_accepts_github_names() {
+    assert_eq!(parse_full_name(" me/repo "), Ok("me/repo".to_string()));
+    assert_eq!(
+      parse_full_name("my-org/a_b.c-d"),
+      Ok("my-org/a_b.c-d".to_string())
+    );
+    assert_eq!(
+      parse_full_name("user_shortcode/repo"),
+      Ok("user_shortcode/repo".to_string())
+    );
+    assert_eq!(parse_full_name("me/.github"), Ok("me/.github".to_string()));
+  }
+
+  #[test]
+  fn parse_full_name_rejects_invalid_names() {
+    let rejected = |text: &str| parse_full_name(text).unwrap_err();
+    assert!(rejected("repo").ends_with("without owner"));
+    assert!(rejected("me/repo/extra").ends_with("with extra '/'"));
+    assert!(rejected("/repo").contains("no valid owner"));
+    assert!(rejected("-me/repo").contains("no valid owner"));
+    assert!(rejected("me-/repo").contains("no valid owner"));
+    assert!(rejected("m.e/repo").contains("no valid owner"));
+    assert!(rejected(&format!("{}/repo", "a".repeat(40))).contains("no valid owner"));
+    assert!(rejected("me/").contains("no valid repo name"));
+    assert!(rejected("me/..").contains("no valid repo name"));
+    assert!(rejected("me/re po").contains("no valid repo name"));
+    assert!(rejected(&format!("me/{}", "a".repeat(101))).contains("no valid repo name"));
+  }
+
+  #[test]
+  fn risk_levels_and_labels() {
+    let now = at("2022-01-01T00:00:00Z");
+    let risk_of = |fields| risk(&candidate("me/repo", fields).repo, now);
+    let old = "2021-01-01T00:00:00Z";
+    let recent = "2021-12-01T00:00:00Z";
+    assert_eq!(
+      risk_of(serde_json::json!({ "fork": true, "pushed_at": old })),
+      Risk::Low
+    );
+    assert_eq!(risk_of(serde_json::json!({ "fork": true })), Risk::Low);
+    assert_eq!(
+      risk_of(serde_json::json!({ "fork": true, "pushed_at": recent })),
+      Risk::Medium
+    );
+    assert_eq!(
+      risk_of(serde_json::json!({ "fork": false, "pushed_at": old })),
+      Risk::Medium
+    );
+    assert_eq!(
+      risk_of(serde_json::json!({ "fork": true, "pushed_at": old, "stargazers_count": 1 })),
+      Risk::Medium
+    );
+    assert_eq!(
+      risk_of(serde_json::json!({ "stargazers_count": RISKY_STARS })),
+      Risk::High
+    );
+    assert_eq!(
+      risk_of(serde_json::json!({ "subscribers_count": 2 })),
+      Risk::High
+    );
+    assert_eq!(
+      risk_of(serde_json::json!({ "subscribers_count": 1 })),
+      Risk::Medium
+    );
+
+    assert!(Risk::Low < Risk::Medium && Risk::Medium < Risk::High);
+    let labels: Vec<_> = [Risk::Low, Risk::Medium, Risk::High]
+      .map(Risk::label)
+      .into();
+    assert_eq!(labels, ["low", "medium", "high"]);
+  }
+}
@@ -1,225 +1,2699 @@
-use std::collections::HashMap;
-use std::process::exit;
-use std::sync::Arc;
-use std::time::{Instant};
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::io::{IsTerminal, Write};
+use std::process::{exit, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 use clap::builder::PossibleValuesParser;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use console::{Emoji, style};
-use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
-use log::{debug, error, info};
-use octocrab::models::Repository;
+use dialoguer::theme::{ColorfulTheme, SimpleTheme, Theme};
+use delete_unused_repo::api_trace;
+use delete_unused_repo::backup::BackupFormat;
+use delete_unused_repo::history;
+use delete_unused_repo::packages::PackageType;
+use delete_unused_repo::rate_limit::{ApiUsage, RateLimits};
+use delete_unused_repo::report::{self, RepoMetadata, Report};
+use delete_unused_repo::filter_expr::FilterExpr;
+use delete_unused_repo::pipeline::{self, AppError, RetryRecord, Sources, Step};
+use delete_unused_repo::{
+  glob_match, Candidate, Cleaner, Config, DeleteError, DeleteOrder, Outcome, ReleaseKind, Risk,
+  SortKey, Stopper, Target, When,
+};
+use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use log::{debug, error, info, warn};
 
 #[derive(Parser, Debug, Clone)]
 #[clap(name = "delete-unused-repo", version, about, long_about = None)]
 struct Cli {
-  /// GitHub Token
+  /// GitHub Token, asked for interactively if missing
   #[clap(short, long, value_parser)]
-  token: String,
+  token: Option<String>,
+  /// Read the GitHub Token from [PATH], used if --token is missing
+  #[clap(long, value_parser, value_name = "PATH")]
+  token_file: Option<PathBuf>,
+  /// Authenticate as an installation of the GitHub App [ID] instead of with a token, renewing its
+  /// hour long token during the deletions. Needs --app-key and --installation-id, and --owner or
+  /// --all-orgs, as an installation has no repos of its own
+  #[clap(long, value_parser, value_name = "ID", requires_all = &["app-key", "installation-id"], conflicts_with_all = &["token", "token-file"])]
+  app_id: Option<u64>,
+  /// Private key of the GitHub App, the PEM file generated in its settings
+  #[clap(long, value_parser, value_name = "PATH", requires = "app-id")]
+  app_key: Option<PathBuf>,
+  /// Installation of the GitHub App to act as, the number at the end of its settings URL
+  #[clap(long, value_parser, value_name = "ID", requires = "app-id")]
+  installation_id: Option<u64>,
+  /// Send all GitHub traffic through the proxy at [URL], like http://proxy.corp:3128. Without it
+  /// HTTPS_PROXY and HTTP_PROXY are honored
+  #[clap(long, value_parser, value_name = "URL")]
+  proxy: Option<String>,
+  /// Trust the CA certificates in the PEM file [PATH] instead of the system's, for proxies
+  /// intercepting TLS. Used where TLS is done by OpenSSL, like on Linux
+  #[clap(long, value_parser, value_name = "PATH")]
+  ca_cert: Option<PathBuf>,
+  /// Pin the GitHub REST API to the version [DATE], sent with every request so newer API changes
+  /// don't affect listing or deleting
+  #[clap(long, value_parser = delete_unused_repo::parse_api_version, value_name = "DATE", default_value = delete_unused_repo::DEFAULT_API_VERSION)]
+  api_version: String,
+  /// Look up whether each --owner is a user or an org, and the orgs of --all-orgs, again instead
+  /// of using what earlier runs of the last 24 hours cached
+  #[clap(long, value_parser)]
+  refresh: bool,
+  /// Look owners up every run, without reading or writing the owner cache
+  #[clap(long, value_parser, conflicts_with = "refresh")]
+  no_owner_cache: bool,
   /// Only delete forks
   #[clap(short, long, value_parser, default_value_t = true)]
   fork: bool,
   /// Delete certain visibility value
-  #[clap(short, long, value_parser = PossibleValuesParser::from(vec!["public", "internal", "private"]), default_value = "public")]
+  #[clap(short, long, value_parser = PossibleValuesParser::from(vec!["public", "internal", "private", "all"]), default_value = "public")]
   visibility: Vec<String>,
-  /// Owner, maybe yourself or organization you have access
+  /// Fail if GitHub returns a visibility other than public, internal or private, instead of
+  /// keeping or dropping the repo depending on --visibility
+  #[clap(long, value_parser)]
+  strict_visibility: bool,
+  /// List your repos with this relation to you, can be repeated. Org repos of --owner and
+  /// --all-orgs are listed anyway
+  #[clap(long, value_parser = PossibleValuesParser::from(vec!["owner", "collaborator", "organization_member"]), default_value = "owner")]
+  affiliation: Vec<String>,
+  /// Order GitHub lists your repos in, unlike --sort this changes which repos come first while
+  /// paging [default: GitHub's, full_name]
+  #[clap(long, value_parser = PossibleValuesParser::from(vec!["created", "updated", "pushed", "full_name"]))]
+  list_sort: Option<String>,
+  /// Direction of --list-sort [default: GitHub's, asc for full_name, else desc]
+  #[clap(long, value_parser = PossibleValuesParser::from(vec!["asc", "desc"]))]
+  list_direction: Option<String>,
+  /// Have GitHub only send repos of this type, fewer pages than filtering afterwards. Org listings
+  /// take every type, your own only public and private. --fork and --visibility still apply
+  #[clap(long, value_parser = PossibleValuesParser::from(vec!["all", "public", "private", "forks", "sources", "member"]))]
+  list_type: Option<String>,
+  /// Also consider internal and private repos, same as `--visibility public internal private`
+  #[clap(long, value_parser)]
+  include_private: bool,
+  /// Owner, maybe yourself or organization you have access, org repos are listed too, `@me` is you.
+  /// '*' is every owner: you and every org you are a member of, the broadest reach there is, so
+  /// deleting asks once more before listing
   #[clap(short, long)]
   owner: Option<Vec<String>>,
+  /// Never consider repos of [OWNER], can be repeated, e.g. to carve orgs out of --owner '*'
+  #[clap(long, value_parser, value_name = "OWNER")]
+  exclude_owner: Vec<String>,
+  /// Same as `--owner @me`, composes with other --owner values
+  #[clap(long, value_parser)]
+  mine: bool,
+  /// Read more owners from [PATH], one per line, `#` starts a comment
+  #[clap(long, value_parser, value_name = "PATH")]
+  owner_file: Option<PathBuf>,
+  /// Match --owner exactly, by default case is ignored like GitHub does
+  #[clap(long, value_parser)]
+  owner_case_sensitive: bool,
+  /// Also search your repos in every org you are a member of
+  #[clap(long, value_parser, alias = "owner-from-membership")]
+  all_orgs: bool,
+  /// With --all-orgs, only list org repos you are admin of, others can't be deleted. When false,
+  /// those and other repos you aren't admin of are offered marked [not admin]
+  #[clap(
+    long,
+    value_parser,
+    action = clap::ArgAction::Set,
+    default_value_t = true,
+    value_name = "BOOL"
+  )]
+  include_org_repos_only_if_admin: bool,
   /// Delete if stars number <= [STARS]
   #[clap(short, long, value_parser, default_value_t = 0, value_name = "STARS")]
   star: u32,
+  /// Never delete repos pinned to your profile, needs a token with the `read:user` scope
+  #[clap(long, value_parser)]
+  exclude_pinned: bool,
+  /// What to clean up in the matched repos
+  #[clap(long, value_enum, default_value_t = Target::Repos)]
+  target: Target,
+  /// Delete if not pushed since [WHEN], or workflow runs and releases older than it. WHEN is a
+  /// duration ago like `90d`, `6mo` or `2y`, a date like `2022-01-01` or a datetime like
+  /// `2022-01-01T12:00:00Z`, the same for every date option
+  #[clap(
+    long,
+    alias = "since",
+    value_parser = delete_unused_repo::parse_when,
+    value_name = "WHEN",
+    required_if_eq_any(&[
+      ("target", "workflow-runs"),
+      ("target", "releases"),
+      ("target", "packages")
+    ])
+  )]
+  inactive: Option<When>,
+  /// Never touch repos pushed since [WHEN], like --inactive takes it, whatever the other filters say
+  #[clap(long, value_parser = delete_unused_repo::parse_when, value_name = "WHEN")]
+  protect_if_pushed_after: Option<When>,
+  /// Also consider profile repos, named like their owner, and .github repos, which are never
+  /// touched by default
+  #[clap(long, value_parser)]
+  include_special_repos: bool,
+  /// Never touch repos matching the gitignore-style patterns in [PATH], read instead of
+  /// ~/.config/delete-unused-repo/ignore and ./.delete-unused-ignore, whose patterns are read
+  /// by default. Like --protect-if-pushed-after it wins over every other option, --repo included
+  #[clap(long, value_parser, value_name = "PATH")]
+  ignore_file: Option<PathBuf>,
+  /// Don't read the default ignore files
+  #[clap(long, value_parser, conflicts_with = "ignore-file")]
+  no_ignore_file: bool,
+  /// Keep the [N] most recently pushed of the matched repos of each owner, after all filters
+  #[clap(long, value_parser, value_name = "N")]
+  retain_newest: Option<usize>,
+  /// Of the matched forks of the same upstream, delete all but the most recently pushed one.
+  /// Upstreams forked only once and repos that are no forks are left alone (an API call per fork)
+  #[clap(long, value_parser, requires = "deep-activity")]
+  squash_forks: bool,
+  /// Kind of releases to delete with `--target releases`
+  #[clap(long, value_enum, default_value_t = ReleaseKind::All)]
+  releases_only: ReleaseKind,
+  /// Ecosystem of the packages to delete with `--target packages`
+  #[clap(long, value_enum, default_value_t = PackageType::Container)]
+  package_type: PackageType,
+  /// Only unstar repos starred before [WHEN], like --inactive takes it, with `--target stars`
+  #[clap(long, value_parser = delete_unused_repo::parse_when, value_name = "WHEN")]
+  starred_before: Option<When>,
+  /// List [N] repos per request, fewer make smaller and more requests
+  #[clap(long, value_parser = clap::value_parser!(u8).range(1..=100), value_name = "N", default_value_t = 100)]
+  per_page: u8,
+  /// Max number of concurrent API requests, sets both --list-concurrency and
+  /// --delete-concurrency
+  #[clap(long, value_parser, value_name = "N")]
+  concurrency: Option<usize>,
+  /// Max number of concurrent API requests while listing and filtering [default: 16]
+  #[clap(long, value_parser, value_name = "N")]
+  list_concurrency: Option<usize>,
+  /// Max number of concurrent deletions [default: 4]
+  #[clap(long, value_parser, value_name = "N")]
+  delete_concurrency: Option<usize>,
+  /// Count a delete request as failed if it takes longer than [DURATION]
+  #[clap(long, value_parser = humantime::parse_duration, value_name = "DURATION", default_value = "60s")]
+  request_timeout: Duration,
+  /// Stop the run after [DURATION], listing included. Deletions already sent finish, the others
+  /// are skipped, and it exits with 124
+  #[clap(long, value_parser = humantime::parse_duration, value_name = "DURATION")]
+  max_runtime: Option<Duration>,
+  /// Start at most [N] deletions per minute, on top of --delete-concurrency
+  #[clap(long, value_parser = clap::value_parser!(u32).range(1..), value_name = "N")]
+  max_deletions_per_minute: Option<u32>,
+  /// Ask again, or refuse without prompting, if the selected repos total more than [SIZE], e.g. `1gb`
+  #[clap(long, value_parser = delete_unused_repo::parse_size, value_name = "SIZE")]
+  max_total_size: Option<u64>,
+  /// Stop at the first failed deletion, leaving the rest unattempted, and exit with 1
+  #[clap(long, value_parser)]
+  fail_fast: bool,
+  /// Stop once [N] deletions failed, assuming something like a revoked token or an outage fails
+  /// them all, and exit with 3. Unlimited by default
+  #[clap(long, value_parser = clap::value_parser!(u64).range(1..), value_name = "N", conflicts_with = "fail-fast")]
+  max_failures: Option<u64>,
+  /// Try every deletion whatever fails before, the default
+  #[clap(long, value_parser, conflicts_with_all = &["fail-fast", "max-failures"])]
+  continue_on_error: bool,
+  /// Run [CMD] with the shell after every successful deletion, with REPO_FULL_NAME and
+  /// DELETED_ITEM set. A failing one is logged, at most --delete-concurrency run at once
+  #[clap(long, value_parser, value_name = "CMD")]
+  on_delete_cmd: Option<String>,
+  /// Exit with 1 if any --on-delete-cmd failed
+  #[clap(long, value_parser, requires = "on-delete-cmd")]
+  hook_strict: bool,
+  /// Rename each repo to DELETE-<name> (or DELETE-<name>-2...) before deleting it, a repo that can't be renamed is kept
+  #[clap(long, value_parser)]
+  rename_before_delete: bool,
+  /// Where --rename-before-delete appends the original and new names, as JSON lines
+  #[clap(long, value_parser, value_name = "PATH", default_value = "renamed-repos.jsonl")]
+  rename_manifest: PathBuf,
+  /// When GitHub rejects a --rename-before-delete name as taken (422), rename to the next one
+  /// instead of keeping the repo
+  #[clap(long, value_parser, requires = "rename-before-delete")]
+  retry_on_422: bool,
+  /// Write every delete attempt to [PATH] as JSON lines
+  #[clap(long, value_parser, value_name = "PATH")]
+  retries_log: Option<PathBuf>,
+  /// Back up each repo into [DIR] before deleting it, a repo whose backup fails is kept
+  #[clap(long, value_parser, value_name = "DIR")]
+  backup_dir: Option<PathBuf>,
+  /// How to back up with --backup-dir, a mirror keeps all history, a tarball is smaller
+  #[clap(long, value_enum, default_value_t = BackupFormat::Git, requires = "backup-dir")]
+  backup_format: BackupFormat,
+  /// Max number of backups taken at once with --backup-dir, apart from --delete-concurrency
+  #[clap(long, value_parser, value_name = "N", default_value_t = 2, requires = "backup-dir")]
+  clone_concurrency: usize,
+  /// Delete only if never pushed to after creation (within a minute)
+  #[clap(long, value_parser)]
+  pushed_never: bool,
+  /// Delete if stars / age in years < [PER_YEAR], age counts as at least 30 days
+  #[clap(long, value_parser, value_name = "PER_YEAR")]
+  max_star_rate: Option<f64>,
+  /// Only delete repos whose default branch is [NAME], e.g. `master` for ones never migrated.
+  /// Empty repos have no default branch and never match
+  #[clap(long, value_parser, value_name = "NAME")]
+  default_branch: Option<String>,
+  /// Allow filters costing extra API calls per repo
+  #[clap(long, value_parser)]
+  deep_activity: bool,
+  /// Fetch each listed repo on its own, so fields the listing omits like `subscribers_count` are
+  /// there for the filters (an API call per repo)
+  #[clap(long, value_parser)]
+  fetch_details: bool,
+  /// Delete if the latest release is older than --inactive, or there is none (an API call per repo)
+  #[clap(long, value_parser, requires_all = &["deep-activity", "inactive"])]
+  since_last_release: bool,
+  /// Keep repos with issues or pull requests updated after --inactive, even if no code was pushed
+  /// (an API call per repo with issues enabled)
+  #[clap(long, value_parser, requires_all = &["deep-activity", "inactive"])]
+  consider_issue_activity: bool,
+  /// Delete if the repo has at least [N] branches (an API call per repo)
+  #[clap(long, value_parser, value_name = "N", requires = "deep-activity")]
+  min_branches: Option<u64>,
+  /// Delete if the repo has at most [N] branches (an API call per repo)
+  #[clap(long, value_parser, value_name = "N", requires = "deep-activity")]
+  max_branches: Option<u64>,
+  /// Delete if the default branch has at least [N] commits (an API call per repo)
+  #[clap(long, value_parser, value_name = "N", requires = "deep-activity")]
+  min_commits: Option<u64>,
+  /// Delete if the default branch has at most [N] commits, empty repos have 0 (an API call per repo)
+  #[clap(long, value_parser, value_name = "N", requires = "deep-activity")]
+  max_commits: Option<u64>,
+  /// Delete only forks of repos owned by [OWNER] (an API call per fork)
+  #[clap(long, value_name = "OWNER", requires = "deep-activity")]
+  parent_owner: Option<Vec<String>>,
+  /// Delete only forks with no commits their parent lacks (API calls per fork)
+  #[clap(long, value_parser, requires = "deep-activity", alias = "since-fork")]
+  fork_not_ahead: bool,
+  /// Delete only your own repos without other collaborators, never org repos (an API call per repo)
+  #[clap(long, value_parser, requires = "deep-activity")]
+  solo_only: bool,
+  /// Never delete repos with an action.yml at the root, others may use them as Actions (API calls per repo)
+  #[clap(long, value_parser, requires = "deep-activity")]
+  skip_if_used_by_workflows: bool,
+  /// Delete only repos without a README (an API call per repo)
+  #[clap(long, value_parser, requires = "deep-activity")]
+  no_readme: bool,
+  /// Delete only repos with GitHub Actions disabled in their settings, often a sign of an
+  /// abandoned repo. Reading them takes admin access (an API call per repo)
+  #[clap(long, value_parser, requires = "deep-activity")]
+  actions_disabled: bool,
+  /// Never delete repos linked to a GitHub Package (API calls per owner and package type)
+  #[clap(long, value_parser)]
+  skip_if_packages: bool,
+  /// Never delete repos with Actions secrets or deployment environments, which CI is likely
+  /// using (2 API calls per repo, reading secrets takes admin access)
+  #[clap(long, value_parser)]
+  protect_repos_with_secrets: bool,
+  /// Show how many commits each fork is ahead/behind its parent when selecting (API calls per fork)
+  #[clap(long, value_parser, requires = "deep-activity")]
+  compare_to_upstream_commits: bool,
+  /// Color the matched repos by risk, green for worthless forks, red for repos with stars or
+  /// watchers, yellow for the rest. Without colors the risk is written after each
+  #[clap(long, value_parser)]
+  color_by_risk: bool,
+  /// Delete only repos matching [EXPR] too, like `stars < 5 && (fork || archived) && pushed < 2022`.
+  /// Fields: stars, forks, watchers, size, issues, subscribers with --fetch-details (numbers);
+  /// fork, archived, private, template (conditions); name, owner, language, visibility (text, quoted); pushed, created, updated
+  /// (dates, compared with a year or "YYYY-MM-DD"). Operators: == != < <= > >= ! && || ( )
+  #[clap(long, value_parser, value_name = "EXPR")]
+  filter_expr: Option<FilterExpr>,
+  /// Write the matched repos with all metadata to [PATH] as JSON
+  #[clap(long, value_parser, value_name = "PATH")]
+  dump_filtered: Option<PathBuf>,
+  /// Use repos from a --dump-filtered file instead of listing and filtering
+  #[clap(long, value_parser, value_name = "PATH")]
+  from_file: Option<PathBuf>,
+  /// Use the repo [OWNER/NAME] instead of listing and filtering, can be repeated. Given repos
+  /// skip the filters, only the protections apply
+  #[clap(
+    long = "repo",
+    value_parser = delete_unused_repo::parse_full_name,
+    value_name = "OWNER/NAME",
+    conflicts_with = "from-file"
+  )]
+  repos: Vec<String>,
+  /// List and filter as without --repo too, and add the given repos to the matches. Without
+  /// --owner, --owner-file, --mine or --all-orgs the owners of the given repos are listed
+  #[clap(long, value_parser, requires = "repos")]
+  repo_and_filter: bool,
+  /// Count the repos matching the filters on the listing alone and ask whether to go on, before
+  /// the filters costing API calls per repo, like with --deep-activity, so a run cancelled there
+  /// spends none on them
+  #[clap(long, value_parser, alias = "no-enrich-on-cancel")]
+  preflight_count: bool,
+  /// Retry the failed deletions recorded in the --output json report at [PATH], without listing
+  /// or filtering, and record the new attempts there
+  #[clap(long, value_parser, value_name = "PATH", conflicts_with_all = &["from-file", "repos", "reconcile"])]
+  retry_failed: Option<PathBuf>,
+  /// Delete every repo of --owner and --visibility NOT listed in --keep-file, so the account
+  /// converges to it. The other filters don't apply, the protections do. Asks once more, and
+  /// refuses to delete more than --reconcile-max
+  #[clap(long, value_parser, requires = "keep-file", conflicts_with_all = &["from-file", "repos"])]
+  reconcile: bool,
+  /// The repos --reconcile keeps, an OWNER/NAME per line, `#` starts a comment
+  #[clap(long, value_parser, value_name = "PATH", requires = "reconcile")]
+  keep_file: Option<PathBuf>,
+  /// Refuse to --reconcile when it would delete more than [N] repos
+  #[clap(long, value_parser, value_name = "N", default_value_t = 20, requires = "reconcile")]
+  reconcile_max: usize,
+  /// Order of the matched repos, ties are broken by full name
+  #[clap(long, value_enum, default_value_t = SortKey::Name)]
+  sort: SortKey,
+  /// Order to start deleting the selected repos in, by risk: low for forks without stars or
+  /// watchers not pushed for 90 days, high for 10 or more stars or other watchers
+  #[clap(long, value_enum, default_value_t = DeleteOrder::SafestFirst)]
+  delete_order: DeleteOrder,
+  /// Group matched repos by name without owner, selecting a group selects all its repos
+  #[clap(long, value_parser)]
+  dedupe_by_name: bool,
+  /// Mark matched repos missing from a previous --dump-filtered file as new
+  #[clap(long, value_parser, value_name = "PATH")]
+  diff_against: Option<PathBuf>,
+  /// Only report matched repos and the API calls it took, never prompt or delete
+  #[clap(long, value_parser)]
+  report_only: bool,
+  /// Like --report-only, but only report the matched repos the token could delete, as deleting
+  /// runs do
+  #[clap(long, value_parser, conflicts_with = "report-only")]
+  list_only_deletable: bool,
+  /// Only list the repos of --owner and --visibility and print totals per owner, of forks,
+  /// archived ones, those inactive by --inactive, size and stars, in the --output format
+  #[clap(long, value_parser, conflicts_with_all = &["from-file", "repos", "reconcile"])]
+  owner_activity_summary: bool,
+  /// Only print the distinct owners of the matched repos, one per line or a JSON array with
+  /// --output json, never prompt or delete
+  #[clap(long, value_parser, conflicts_with = "owner-activity-summary")]
+  output_owners_only: bool,
+  /// Page through every matched repo before selecting
+  #[clap(long, value_parser)]
+  confirm_seen: bool,
+  /// Select by editing the list in $EDITOR instead of checking items, delete a line to keep its
+  /// repo, like `git rebase -i`
+  #[clap(long, value_parser)]
+  confirm_via_editor: bool,
+  /// After selecting, offer to flip the selection, to check the few to keep rather than the many
+  /// to delete
+  #[clap(long, value_parser)]
+  select_invert: bool,
+  /// Refuse to show the selection prompt for more than [N] items, tighten the filters or raise it
+  #[clap(long, value_parser = clap::value_parser!(u64).range(1..), value_name = "N", default_value_t = 1000)]
+  cap_candidates: u64,
+  /// Check only the items whose name or full name matches the glob [PATTERN] at first, like
+  /// '*-fork', instead of every item
+  #[clap(long, value_parser, value_name = "PATTERN")]
+  select_pattern: Option<String>,
+  /// Select only the first [N] items in --sort order, checked at first when asking, the only ones
+  /// deleted with --non-interactive or --confirm-file
+  #[clap(long, value_parser, value_name = "N", conflicts_with = "select-pattern")]
+  select_first: Option<usize>,
+  /// Like --select-first, but the last [N] items
+  #[clap(long, value_parser, value_name = "N", conflicts_with_all = &["select-pattern", "select-first"])]
+  select_last: Option<usize>,
+  /// Right before deleting, show how many items are about to go and abort unless that number is
+  /// typed back, a last check after everything else asked
+  #[clap(long, value_parser, alias = "confirm-count-mismatch-abort")]
+  confirm_count: bool,
+  /// Count down [SECONDS] after the last confirmation before the first deletion, a last chance
+  /// to abort with Ctrl-C
+  #[clap(long, value_parser, value_name = "SECONDS", default_value_t = 0)]
+  cooldown: u64,
+  /// Skip the typed double confirm when fewer than [N] items are selected, 0 always asks
+  #[clap(long, value_parser, value_name = "N", default_value_t = 0)]
+  no_double_confirm_under: u64,
+  /// Type a phrase made up for this run to confirm, like `delete 3 maple-orbit`, instead of the usual one
+  #[clap(long, value_parser)]
+  confirm_phrase_random: bool,
+  /// Before asking, show the list in $PAGER (less if unset) or the repos as the HTML report in a browser
+  #[clap(long, value_enum, value_name = "WITH")]
+  preview_open: Option<PreviewMode>,
+  /// Delete everything matched without prompting, for scripts without a terminal
+  #[clap(long, value_parser, conflicts_with = "confirm-seen")]
+  non_interactive: bool,
+  /// Skip typing the confirmation if every selected repo is a fork without
+  /// stars or watchers, not pushed for 90 days
+  #[clap(long, value_parser)]
+  auto_skip_safe_confirm: bool,
+  /// Delete everything matched if [PATH] holds the confirmation phrase, and
+  /// optionally the number of matches on the next line
+  #[clap(long, value_parser, value_name = "PATH", conflicts_with = "non-interactive")]
+  confirm_file: Option<PathBuf>,
+  /// Delete everything matched if the DELETE_CONFIRM environment variable holds the confirmation
+  /// phrase, e.g. a protected CI secret, and abort if it doesn't
+  #[clap(long, value_parser, conflicts_with_all = &["non-interactive", "confirm-file", "confirm-seen"])]
+  confirm_phrase_from_env: bool,
+  /// How to show deletion progress [default: bar, or plain if not a terminal]
+  #[clap(long, value_enum)]
+  progress_style: Option<ProgressMode>,
+  /// Redraw the progress at most every [MS] milliseconds
+  #[clap(long, value_parser = clap::value_parser!(u64).range(1..), value_name = "MS", default_value_t = 100)]
+  progress_refresh: u64,
+  /// Also write a timestamped line per deletion to [PATH], the terminal progress goes to stderr
+  #[clap(long, value_parser, value_name = "PATH")]
+  progress_to_file: Option<PathBuf>,
+  /// Also log a summary of the deletion progress with its rate and ETA every [INTERVAL], a number
+  /// of deletions like `100` or a duration like `1m`, for logs where the bar doesn't show
+  #[clap(long, value_parser = parse_summary_interval, value_name = "INTERVAL")]
+  progress_summary_interval: Option<SummaryInterval>,
+  /// Also print the result to stdout in this format
+  #[clap(long, value_enum, default_value_t = OutputFormat::Text, alias = "format")]
+  output: OutputFormat,
+  /// Write the --output result to [PATH] instead of stdout, creating its directory if needed
+  #[clap(long, value_parser, value_name = "PATH")]
+  output_file: Option<PathBuf>,
+  /// Indent the `--output json` object
+  #[clap(long, value_parser)]
+  json_pretty: bool,
+  /// Also list the matched repos with their stars, size, fork, visibility and last push in the
+  /// `--output json` object
+  #[clap(long, value_parser)]
+  json_include_metadata: bool,
+  /// Only check the token, its scopes and repo access, then exit
+  #[clap(long, value_parser, conflicts_with_all = &["non-interactive", "confirm-file"])]
+  validate_only: bool,
+  /// Print who the token belongs to and its scopes, with a single API call, then exit
+  #[clap(long, value_parser, conflicts_with = "validate-only")]
+  list_scopes: bool,
+  /// Hide step headers and per item messages, only log the summary
+  #[clap(long, value_parser)]
+  summary_only: bool,
+  /// Use the ASCII fallbacks instead of emoji, colors are kept
+  #[clap(long, value_parser)]
+  no_emoji: bool,
+  /// Print without colors, prompts included, which then use the simple theme
+  #[clap(long, value_parser)]
+  no_color: bool,
+  /// Look of the interactive prompts
+  #[clap(long, value_enum, value_name = "THEME", default_value_t = PromptTheme::Default)]
+  prompt_theme: PromptTheme,
+  /// Also write a standalone HTML summary to [PATH]
+  #[clap(long, value_parser, value_name = "PATH")]
+  html_report: Option<PathBuf>,
+  /// Write a JSON line per GitHub request to [PATH], with its status, timing and rate limit.
+  /// Only the requests this tool sends itself are traced, not those of octocrab's listings
+  /// of repos, orgs and their further pages. The token and URL secrets are left out
+  #[clap(long, value_parser, value_name = "PATH")]
+  dump_api_calls: Option<PathBuf>,
+  /// Print the JSON Schema of `--output json` and exit
+  #[clap(long, value_parser, hide = true)]
+  print_schema: bool,
+  /// Don't record this run to the local history, see the `history` command
+  #[clap(long, value_parser)]
+  no_history: bool,
+  #[clap(subcommand)]
+  command: Option<Subcommand>,
 }
 
+#[derive(clap::Subcommand, Debug, Clone)]
+enum Subcommand {
+  /// Print the runs recorded to the local history, only kept on this machine, and exit
+  History,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+  /// Only the log on stderr
+  Text,
+  /// A JSON object on a single line, see `--print-schema` and `--json-pretty`
+  Json,
+  /// A JSON line per outcome, or per matched name with `--report-only`
+  Ndjson,
+  /// A markdown table of the matched repos with totals per owner
+  Markdown,
+  /// A CSV table of the matched repos, for spreadsheets
+  Csv,
+}
+
+/// Where and how [`print_report`] writes the result.
 #[derive(Debug, Clone)]
-struct WrappedRepo(Repository);
+struct Output {
+  format: OutputFormat,
+  file: Option<PathBuf>,
+  /// The local history to append the run to, `None` with `--no-history`
+  history: Option<PathBuf>,
+  report_only: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptTheme {
+  /// The look dialoguer gives them unless told otherwise
+  Default,
+  /// Colored markers and highlighted choices
+  Colorful,
+  /// Plain text, for terminals and color schemes the others render poorly on
+  Simple,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum PreviewMode {
+  /// $PAGER, or less
+  Pager,
+  /// The default browser
+  Browser,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ProgressMode {
+  /// A spinner with the last deleted repo
+  Spinner,
+  /// A progress bar with count and ETA
+  Bar,
+  /// A log line per deletion
+  Plain,
+}
+
+/// How often `--progress-summary-interval` logs a summary.
+#[derive(Debug, Clone, Copy)]
+enum SummaryInterval {
+  Deletions(u64),
+  Period(Duration),
+}
+
+fn parse_summary_interval(text: &str) -> Result<SummaryInterval, String> {
+  let interval = match text.parse::<u64>() {
+    Ok(n) => SummaryInterval::Deletions(n),
+    Err(_) => SummaryInterval::Period(humantime::parse_duration(text).map_err(|e| {
+      format!("expected a number of deletions or a duration like 30s, {e}")
+    })?),
+  };
+  match interval {
+    SummaryInterval::Deletions(0) => Err("the interval must be at least 1 deletion".to_string()),
+    SummaryInterval::Period(period) if period.is_zero() => {
+      Err("the interval must be longer than 0s".to_string())
+    }
+    interval => Ok(interval),
+  }
+}
+
+impl From<Cli> for Config {
+  fn from(cli: Cli) -> Self {
+    Config {
+      token: cli.token.unwrap_or_default(),
+      api_version: cli.api_version,
+      owner_cache_dir: match cli.no_owner_cache {
+        true => None,
+        false => delete_unused_repo::owner_cache::default_dir(),
+      },
+      refresh_owner_cache: cli.refresh,
+      fork: cli.fork,
+      visibility: cli.visibility,
+      affiliation: cli.affiliation,
+      list_sort: cli.list_sort,
+      list_direction: cli.list_direction,
+      list_type: cli.list_type,
+      owner: cli.owner,
+      exclude_owner: cli.exclude_owner,
+      owner_case_sensitive: cli.owner_case_sensitive,
+      all_orgs: cli.all_orgs,
+      // Repos the user isn't admin of are never deletable, so there is no
+      // point in listing them for --list-only-deletable
+      org_admin_only: cli.include_org_repos_only_if_admin || cli.list_only_deletable,
+      star: cli.star,
+      exclude_pinned: cli.exclude_pinned,
+      target: cli.target,
+      inactive: cli.inactive,
+      starred_before: cli.starred_before,
+      protect_pushed_within: cli.protect_if_pushed_after,
+      ignore: None,
+      include_special: cli.include_special_repos,
+      releases_only: cli.releases_only,
+      package_type: cli.package_type,
+      per_page: cli.per_page,
+      list_concurrency: cli.list_concurrency.or(cli.concurrency).unwrap_or(16),
+      delete_concurrency: cli.delete_concurrency.or(cli.concurrency).unwrap_or(4),
+      request_timeout: cli.request_timeout,
+      max_deletions_per_minute: cli.max_deletions_per_minute,
+      pushed_never: cli.pushed_never,
+      max_star_rate: cli.max_star_rate,
+      default_branch: cli.default_branch,
+      deep_activity: cli.deep_activity,
+      fetch_details: cli.fetch_details,
+      since_last_release: cli.since_last_release,
+      consider_issue_activity: cli.consider_issue_activity,
+      min_branches: cli.min_branches,
+      max_branches: cli.max_branches,
+      min_commits: cli.min_commits,
+      max_commits: cli.max_commits,
+      parent_owner: cli.parent_owner,
+      fork_not_ahead: cli.fork_not_ahead,
+      solo_only: cli.solo_only,
+      skip_actions: cli.skip_if_used_by_workflows,
+      no_readme: cli.no_readme,
+      actions_disabled: cli.actions_disabled,
+      filter_expr: cli.filter_expr,
+      backup_dir: cli.backup_dir,
+      backup_format: cli.backup_format,
+      clone_concurrency: cli.clone_concurrency,
+      fail_fast: cli.fail_fast,
+      max_failures: cli.max_failures.map(|n| n as usize),
+      on_delete_cmd: cli.on_delete_cmd,
+      skip_packaged: cli.skip_if_packages,
+      protect_with_secrets: cli.protect_repos_with_secrets,
+      retain_newest: cli.retain_newest,
+      retry_on_422: cli.retry_on_422,
+      squash_forks: cli.squash_forks,
+    }
+  }
+}
 
 static LOOKING_GLASS: Emoji<'_, '_> = Emoji("🔍  ", "");
 static CLIP: Emoji<'_, '_> = Emoji("🔗  ", "");
 static FILTER: Emoji<'_, '_> = Emoji("⏳  ", "");
 static TRASH: Emoji<'_, '_> = Emoji("🗑  ", "");
 static SPARKLE: Emoji<'_, '_> = Emoji("✨ ", ":-)");
+static STAR: Emoji<'_, '_> = Emoji("★", "stars ");
 
-#[tokio::main]
-async fn main() {
+static NO_EMOJI: AtomicBool = AtomicBool::new(false);
+static SUMMARY_ONLY: AtomicBool = AtomicBool::new(false);
+static JSON_PRETTY: AtomicBool = AtomicBool::new(false);
+static JSON_METADATA: AtomicBool = AtomicBool::new(false);
+static HOOK_STRICT: AtomicBool = AtomicBool::new(false);
+/// Whether the prompts use [`ColorfulTheme`], see [`theme`].
+static COLORFUL_PROMPTS: AtomicBool = AtomicBool::new(false);
+
+/// Where the run is for `--max-runtime`, one of the `PHASE_` constants.
+static PHASE: AtomicU8 = AtomicU8::new(PHASE_PREPARING);
+const PHASE_PREPARING: u8 = 0;
+const PHASE_DELETING: u8 = 1;
+const PHASE_OUT_OF_TIME: u8 = 2;
+
+/// The theme of `--prompt-theme` for the dialoguer prompts. `--no-color`
+/// forces the simple one, the colorful one would still draw its markers.
+fn theme() -> &'static dyn Theme {
+  static COLORFUL: OnceLock<ColorfulTheme> = OnceLock::new();
+  match COLORFUL_PROMPTS.load(Ordering::Relaxed) {
+    true => COLORFUL.get_or_init(ColorfulTheme::default),
+    false => &SimpleTheme,
+  }
+}
+
+/// `info!` for step headers and per item messages, hidden by `--summary-only`.
+macro_rules! step {
+  ($($arg:tt)*) => {
+    if !SUMMARY_ONLY.load(Ordering::Relaxed) {
+      info!($($arg)*)
+    }
+  };
+}
+
+/// `e`, or its fallback with `--no-emoji`. Without it [`Emoji`] still
+/// falls back by itself on terminals it knows can't show emoji.
+fn emoji(e: Emoji<'static, 'static>) -> String {
+  if NO_EMOJI.load(Ordering::Relaxed) {
+    e.1.to_string()
+  } else {
+    e.to_string()
+  }
+}
+
+/// Whether someone can answer a prompt: prompts read stdin and draw on
+/// stderr, so stdout may still be redirected, e.g. `--output json > out.json`.
+fn attended() -> bool {
+  std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Asks for the token without echoing it, only when someone is at the
+/// terminal so scripts missing `--token` still fail right away.
+fn prompt_token() -> Option<String> {
+  if !attended() {
+    return None;
+  }
+  dialoguer::Password::with_theme(theme())
+    .with_prompt("GitHub Token")
+    .interact()
+    .ok()
+    .filter(|t| !t.is_empty())
+}
+
+/// Shows `items` one screen at a time until the end, so long lists can't
+/// be confirmed without seeing their bottom. Returns `false` on Esc or q.
+fn review_pages(items: &[String]) -> bool {
+  let term = console::Term::stderr();
+  let page_len = (term.size().0 as usize).saturating_sub(2).max(1);
+  if items.len() <= page_len {
+    return true;
+  }
+  for (i, page) in items.chunks(page_len).enumerate() {
+    for item in page {
+      let _ = term.write_line(item);
+    }
+    let seen = i * page_len + page.len();
+    if seen == items.len() {
+      break;
+    }
+    let more = format!("-- {seen}/{} seen, any key for more, [Esc/q] to cancel --", items.len());
+    let _ = term.write_str(&style(more).dim().to_string());
+    let key = term.read_key();
+    let _ = term.clear_line();
+    if matches!(key, Err(_) | Ok(console::Key::Escape) | Ok(console::Key::Char('q'))) {
+      return false;
+    }
+  }
+  true
+}
+
+/// How [`select`] asks.
+#[derive(Debug, Clone, Default)]
+struct Prompts {
+  confirm_seen: bool,
+  non_interactive: bool,
+  auto_skip_safe_confirm: bool,
+  confirm_file: Option<PathBuf>,
+  confirm_env: bool,
+  preview: Option<PreviewMode>,
+  random_phrase: bool,
+  cap: u64,
+  select_pattern: Option<String>,
+  select_first: Option<usize>,
+  select_last: Option<usize>,
+  confirm_under: u64,
+  editor: bool,
+  select_invert: bool,
+  confirm_count: bool,
+  cooldown: u64,
+}
+
+impl Prompts {
+  /// Whether [`select`] asks, instead of selecting everything by itself.
+  fn asks(&self) -> bool {
+    self.confirm_file.is_none() && !self.non_interactive && !self.confirm_env
+  }
+}
+
+/// Adds the visibilities `--include-private` considers to `visibility`.
+fn include_private(visibility: &mut Vec<String>) {
+  for vis in ["internal", "private"] {
+    if !visibility.iter().any(|v| v == vis) {
+      visibility.push(vis.to_string());
+    }
+  }
+}
+
+/// Refuses to show the selection prompt for more than `cap` matched items,
+/// see `--cap-candidates`.
+fn check_cap(matched: usize, cap: u64, noun: &str) -> Result<(), AppError> {
+  if matched as u64 > cap {
+    return Err(AppError::Failed(format!(
+      "{matched} {noun} matched, too many to select from with more than --cap-candidates \
+      {cap}, tighten the filters or raise the cap"
+    )));
+  }
+  Ok(())
+}
+
+/// Whether `selected` items are few enough to skip the double confirm, see
+/// `--no-double-confirm-under`. A threshold of 0 never skips it.
+fn under_confirm_threshold(selected: usize, confirm_under: u64) -> bool {
+  (selected as u64) < confirm_under
+}
+
+/// `--color-by-risk`, colors `item` by `risk`, or writes the risk after it
+/// without colors, e.g. with `--no-color`.
+fn by_risk(item: String, risk: Risk) -> String {
+  if !console::colors_enabled_stderr() {
+    return format!("{item} [{} risk]", risk.label());
+  }
+  match risk {
+    Risk::Low => style(item).green().to_string(),
+    Risk::Medium => style(item).yellow().to_string(),
+    Risk::High => style(item).red().to_string(),
+  }
+}
+
+/// `--preview-open`, shows `items` in a pager or `candidates` as the HTML
+/// report in a browser before [`select`] asks. Packages have no
+/// `candidates`, so they always go to the pager. If neither can be started
+/// this only warns, as the list is still shown for selection.
+fn preview(mode: PreviewMode, items: &[String], candidates: &[Candidate]) {
+  let result = match mode {
+    PreviewMode::Browser if !candidates.is_empty() => open_in_browser(candidates),
+    _ => page(items),
+  };
+  if let Err(e) = result {
+    warn!("Can't show the preview: {e}");
+  }
+}
+
+/// Pipes `items` into `$PAGER` and waits for it to quit.
+fn page(items: &[String]) -> std::io::Result<()> {
+  let pager = std::env::var("PAGER")
+    .ok()
+    .filter(|p| !p.trim().is_empty())
+    .unwrap_or_else(|| "less".to_string());
+  let mut words = pager.split_whitespace();
+  let mut child = Command::new(words.next().unwrap())
+    .args(words)
+    .stdin(Stdio::piped())
+    .spawn()
+    .map_err(|e| std::io::Error::new(e.kind(), format!("failed to run {pager}: {e}")))?;
+  if let Some(mut stdin) = child.stdin.take() {
+    for item in items {
+      // The pager may be quit before reading everything
+      if writeln!(stdin, "{}", console::strip_ansi_codes(item)).is_err() {
+        break;
+      }
+    }
+  }
+  child.wait()?;
+  Ok(())
+}
+
+/// Writes the HTML report of `candidates` to a temporary file and opens it.
+fn open_in_browser(candidates: &[Candidate]) -> std::io::Result<()> {
+  let path = std::env::temp_dir().join("delete-unused-repo-preview.html");
+  std::fs::write(&path, report::html(candidates, &[], &command_line()))?;
+  let mut command = if cfg!(target_os = "macos") {
+    Command::new("open")
+  } else if cfg!(windows) {
+    let mut command = Command::new("cmd");
+    command.args(["/C", "start", ""]);
+    command
+  } else {
+    Command::new("xdg-open")
+  };
+  let status = command.arg(&path).status()?;
+  if !status.success() {
+    let message = format!("failed to open {} ({status})", path.display());
+    return Err(std::io::Error::other(message));
+  }
+  info!("Opened the matched repos in a browser, see {}", path.display());
+  Ok(())
+}
+
+/// Checks a `--confirm-file`: its first line must be `phrase`, and a
+/// second line, if any, the number of `selected` items to delete.
+fn check_confirm_file(path: &Path, phrase: &str, selected: usize) -> Result<(), String> {
+  let content = std::fs::read_to_string(path).map_err(|e| format!("can't read it: {e}"))?;
+  let mut lines = content.lines().map(str::trim).filter(|l| !l.is_empty());
+  match lines.next() {
+    Some(line) if line == phrase => {}
+    Some(line) => return Err(format!("expected '{phrase}', found '{line}'")),
+    None => return Err(format!("it is empty, expected '{phrase}'")),
+  }
+  if let Some(count) = lines.next() {
+    match count.parse::<usize>() {
+      Ok(count) if count == selected => {}
+      Ok(count) => return Err(format!("it confirms {count} items, but {selected} are selected")),
+      Err(_) => return Err(format!("expected a count on the second line, found '{count}'")),
+    }
+  }
+  Ok(())
+}
+
+/// The environment variable of `--confirm-phrase-from-env`.
+const CONFIRM_ENV: &str = "DELETE_CONFIRM";
+
+/// Checks [`CONFIRM_ENV`] holds exactly `phrase`. What it holds instead is
+/// never shown, it may be a secret meant for another run.
+fn check_confirm_env(phrase: &str) -> Result<(), String> {
+  match std::env::var(CONFIRM_ENV) {
+    Ok(value) if value == phrase => Ok(()),
+    Ok(_) => Err(format!("it doesn't hold '{phrase}'")),
+    Err(std::env::VarError::NotPresent) => Err(format!("it isn't set, expected '{phrase}'")),
+    Err(e) => Err(e.to_string()),
+  }
+}
+
+const PHRASE_WORDS: &[&str] = &[
+  "amber", "anchor", "battery", "cactus", "candle", "correct", "falcon", "garden", "harbor",
+  "horse", "island", "lantern", "maple", "meadow", "orbit", "pepper", "river", "saddle", "staple",
+  "timber", "tunnel", "velvet", "walnut", "willow",
+];
+
+/// A confirmation like `delete 37 correct-horse` for `--confirm-phrase-random`,
+/// different each run so it has to be read before typing it.
+fn random_phrase(count: usize) -> String {
+  use std::collections::hash_map::RandomState;
+  use std::hash::{BuildHasher, Hasher};
+  // `RandomState` is seeded randomly per process, enough to vary the words
+  let random = RandomState::new().build_hasher().finish() as usize;
+  let first = PHRASE_WORDS[random % PHRASE_WORDS.len()];
+  let second = PHRASE_WORDS[random / PHRASE_WORDS.len() % PHRASE_WORDS.len()];
+  format!("delete {count} {first}-{second}")
+}
+
+/// Which items start checked, those whose name, with or without owner,
+/// matches `--select-pattern`, or every item.
+fn prechecked(names: &[String], pattern: Option<&str>) -> Vec<bool> {
+  names
+    .iter()
+    .map(|name| {
+      pattern.is_none_or(|pattern| {
+        let short = name.rsplit('/').next().unwrap_or(name);
+        glob_match(pattern, name) || glob_match(pattern, short)
+      })
+    })
+    .collect()
+}
+
+/// Indices of the first or the last items of `len` with `--select-first`
+/// or `--select-last`, `None` without either.
+fn quick_selection(len: usize, prompts: &Prompts) -> Option<Vec<usize>> {
+  match (prompts.select_first, prompts.select_last) {
+    (Some(n), _) => Some((0..n.min(len)).collect()),
+    (None, Some(n)) => Some((len.saturating_sub(n)..len).collect()),
+    (None, None) => None,
+  }
+}
+
+/// The indices below `len` not in `selected`, for `--select-invert`.
+fn invert_selection(selected: &[usize], len: usize) -> Vec<usize> {
+  let selected: HashSet<usize> = selected.iter().copied().collect();
+  (0..len).filter(|i| !selected.contains(i)).collect()
+}
+
+/// Lets the user pick from `items`, all checked by default, or edit them
+/// with `--confirm-via-editor`, and asks to
+/// type a confirmation, returning the indices unless cancelled, or none
+/// without asking if nothing was checked. Fails if
+/// there are more items than `--cap-candidates`, and without a terminal to
+/// ask on fails with a hint instead of waiting for input that never comes,
+/// unless `--non-interactive` picks every item. `names` are what
+/// `--select-pattern` is matched against, one per item.
+/// With `--auto-skip-safe-confirm` the typed confirmation is skipped if
+/// every selected item is `safe`, with `--no-double-confirm-under` if only
+/// a few are selected, and `--confirm-file` selects every item
+/// without asking once the file holds the confirmation, as does
+/// `--confirm-phrase-from-env` once [`CONFIRM_ENV`] does. `--select-invert`
+/// offers to flip the selection before confirming, `--select-first` and
+/// `--select-last` narrow what is checked or selected without asking.
+fn select(
+  items: &[String],
+  names: &[String],
+  safe: &[bool],
+  prompt: &str,
+  noun: &str,
+  prompts: &Prompts,
+) -> Result<Vec<usize>, AppError> {
+  let confirm_str = format!("I want to remove all {noun} above");
+  let quick = quick_selection(items.len(), prompts);
+  let unasked = |why: String| {
+    match &quick {
+      Some(quick) => info!("Selected {} of the {} items, {why}", quick.len(), items.len()),
+      None => info!("Selected all {} items, {why}", items.len()),
+    }
+    quick.clone().unwrap_or_else(|| (0..items.len()).collect())
+  };
+  if let Some(path) = &prompts.confirm_file {
+    let selected = quick.as_ref().map_or(items.len(), Vec::len);
+    check_confirm_file(path, &confirm_str, selected)
+      .map_err(|e| AppError::Failed(format!("Not confirmed by {}: {e}", path.display())))?;
+    return Ok(unasked(format!("confirmed by {}", path.display())));
+  }
+  if prompts.confirm_env {
+    check_confirm_env(&confirm_str)
+      .map_err(|e| AppError::Failed(format!("Not confirmed by ${CONFIRM_ENV}: {e}")))?;
+    return Ok(unasked(format!("confirmed by ${CONFIRM_ENV}")));
+  }
+  if prompts.non_interactive {
+    return Ok(unasked("--non-interactive".to_string()));
+  }
+  check_cap(items.len(), prompts.cap, noun)?;
+  if !attended() {
+    return Err(AppError::Failed(format!(
+      "Can't ask which {noun} to delete without a terminal, \
+      pass --report-only to only list them or --non-interactive to delete all of them"
+    )));
+  }
+  if prompts.confirm_seen && !review_pages(items) {
+    return Err(AppError::Cancelled);
+  }
+  let checked = match &quick {
+    Some(quick) => (0..items.len()).map(|i| quick.contains(&i)).collect(),
+    None => prechecked(names, prompts.select_pattern.as_deref()),
+  };
+  let selected = if prompts.editor {
+    edit_selection(items, names, &checked, noun)?
+  } else {
+    let result = dialoguer::MultiSelect::with_theme(theme())
+      .with_prompt(format!(
+        "{prompt}, \n\
+        [Space] to check item, \n\
+        [Esc/q] to cancel, \n\
+        [Enter] to confirm",
+      ))
+      .items(items)
+      .defaults(&checked)
+      .interact_opt();
+    match result {
+      Ok(Some(selected)) => selected,
+      _ => return Err(AppError::Cancelled),
+    }
+  };
+  let selected = if prompts.select_invert {
+    let invert = dialoguer::Confirm::with_theme(theme())
+      .with_prompt(format!(
+        "Invert the selection, delete the {} unchecked {noun} instead of the {} checked?",
+        items.len() - selected.len(),
+        selected.len()
+      ))
+      .default(false)
+      .interact_opt();
+    match invert {
+      Ok(Some(true)) => invert_selection(&selected, items.len()),
+      Ok(Some(false)) => selected,
+      _ => return Err(AppError::Cancelled),
+    }
+  } else {
+    selected
+  };
+  // Nothing to confirm, the callers stop on an empty selection
+  if selected.is_empty() {
+    info!("No {noun} selected, nothing to do");
+    return Ok(selected);
+  }
+
+  if prompts.auto_skip_safe_confirm && selected.iter().all(|&i| safe.get(i) == Some(&true)) {
+    info!(
+      "Skip the double confirm, all selected repos are forks without stars or watchers, \
+      not pushed for {} days",
+      delete_unused_repo::SAFE_INACTIVE_DAYS
+    );
+    return Ok(selected);
+  }
+  if under_confirm_threshold(selected.len(), prompts.confirm_under) {
+    info!(
+      "Skip the double confirm, only {} selected, under --no-double-confirm-under {}",
+      selected.len(),
+      prompts.confirm_under
+    );
+    return Ok(selected);
+  }
+
+  let confirm_str = match prompts.random_phrase {
+    true => random_phrase(selected.len()),
+    false => confirm_str,
+  };
+  let confirm: std::io::Result<String> = dialoguer::Input::with_theme(theme())
+    .with_prompt(format!(
+      "Double confirm, please type '{}'",
+      style(&confirm_str).bold()
+    ))
+    .interact();
+  if confirm.is_ok() && confirm.unwrap() == confirm_str {
+  } else {
+    return Err(AppError::Cancelled);
+  };
+  Ok(selected)
+}
+
+/// `--confirm-count`, shows the final number of `total` items about to be
+/// deleted and aborts unless it is typed back. Only asks when [`select`]
+/// did.
+fn confirm_count(total: usize, noun: &str, prompts: &Prompts) -> Result<(), AppError> {
+  if !prompts.confirm_count || !prompts.asks() {
+    return Ok(());
+  }
+  let typed: std::io::Result<String> = dialoguer::Input::with_theme(theme())
+    .with_prompt(format!(
+      "About to delete {} {noun}, type the number to go on",
+      style(total).bold()
+    ))
+    .interact();
+  match typed {
+    Ok(typed) if typed.trim() == total.to_string() => Ok(()),
+    Ok(typed) => Err(AppError::Failed(format!(
+      "Typed {}, but {total} {noun} are about to be deleted, nothing deleted",
+      typed.trim()
+    ))),
+    Err(_) => Err(AppError::Cancelled),
+  }
+}
+
+/// `--cooldown`, counts down before the first deletion, cancelling the run
+/// on Ctrl-C. Nothing is deleted yet, so it stops right there.
+async fn cooldown(prompts: &Prompts, total: usize, noun: &str) -> Result<(), AppError> {
+  if prompts.cooldown == 0 {
+    return Ok(());
+  }
+  let countdown = async {
+    for left in (1..=prompts.cooldown).rev() {
+      warn!("Deleting {total} {noun}, starting in {left}... (Ctrl-C to abort)");
+      tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+  };
+  tokio::select! {
+    _ = countdown => Ok(()),
+    _ = tokio::signal::ctrl_c() => Err(AppError::Cancelled),
+  }
+}
+
+/// `--preflight-count`, shows how many repos the filters on the listing
+/// match and asks whether to run the others. Without prompts it only logs
+/// the count.
+fn confirm_preflight(matched: usize, prompts: &Prompts) -> Result<(), AppError> {
+  info!("{matched} repos match the filters on the listing, before the ones costing API calls");
+  if matched == 0 || !prompts.asks() {
+    return Ok(());
+  }
+  let confirmed = dialoguer::Confirm::with_theme(theme())
+    .with_prompt(format!("Go on filtering the {matched} repos?"))
+    .default(true)
+    .interact()
+    .unwrap_or(false);
+  if confirmed {
+    Ok(())
+  } else {
+    Err(AppError::Cancelled)
+  }
+}
+
+/// `--reconcile`, lists what goes and fails if that is more than `max`,
+/// then asks once more, or fails if [`select`] won't ask either.
+fn confirm_reconcile(
+  candidates: &[Candidate],
+  kept: usize,
+  max: usize,
+  prompts: &Prompts,
+) -> Result<(), AppError> {
+  if candidates.is_empty() {
+    return Ok(());
+  }
+  for c in candidates {
+    info!("Not in the keep file: {}", c.full_name());
+  }
+  let summary = format!(
+    "--reconcile would delete {} repos, keeping the {kept} of the keep file",
+    candidates.len()
+  );
+  if candidates.len() > max {
+    return Err(AppError::Failed(format!(
+      "{summary}, more than --reconcile-max {max}, check the keep file and scope or raise it"
+    )));
+  }
+  if !prompts.asks() {
+    return Err(AppError::Failed(format!("{summary}, not deleting without a prompt")));
+  }
+  warn!("{summary}");
+  let confirmed = dialoguer::Confirm::with_theme(theme())
+    .with_prompt("Delete every repo above that isn't in the keep file?")
+    .default(false)
+    .interact()
+    .unwrap_or(false);
+  if confirmed {
+    Ok(())
+  } else {
+    Err(AppError::Cancelled)
+  }
+}
+
+/// `--owner '*'`, asks before listing whether to consider every owner, or
+/// fails if [`select`] won't ask either.
+fn confirm_any_owner(prompts: &Prompts) -> Result<(), AppError> {
+  if !prompts.asks() {
+    return Err(AppError::Failed(
+      "--owner '*' reaches the repos of you and every org you are a member of, \
+      not deleting without a prompt, name the owners or try --report-only first"
+        .to_string(),
+    ));
+  }
+  let confirmed = dialoguer::Confirm::with_theme(theme())
+    .with_prompt("Consider the repos of you and every org you are a member of?")
+    .default(false)
+    .interact()
+    .unwrap_or(false);
+  if confirmed {
+    Ok(())
+  } else {
+    Err(AppError::Cancelled)
+  }
+}
+
+/// `--confirm-via-editor`, opens a line per item in `$EDITOR`, the
+/// `checked` ones uncommented, and returns the items left. Cancelled if
+/// the file isn't saved or no item is left.
+fn edit_selection(
+  items: &[String],
+  names: &[String],
+  checked: &[bool],
+  noun: &str,
+) -> Result<Vec<usize>, AppError> {
+  let mut text = format!(
+    "# These {noun} will be deleted. Delete or comment out a line to keep it,\n\
+    # then save and quit. Text after # is ignored, leaving no line cancels.\n\n"
+  );
+  for ((item, name), &checked) in items.iter().zip(names).zip(checked) {
+    let item = console::strip_ansi_codes(item);
+    let detail = item.strip_prefix(name.as_str()).unwrap_or(&item).trim();
+    let comment = if checked { "" } else { "# " };
+    match detail {
+      "" => text += &format!("{comment}{name}\n"),
+      _ => text += &format!("{comment}{name}  # {detail}\n"),
+    }
+  }
+  let edited = dialoguer::Editor::new()
+    .extension(".txt")
+    .edit(&text)
+    .map_err(|e| AppError::Failed(format!("Failed to run $EDITOR: {e}")))?
+    .ok_or(AppError::Cancelled)?;
+  let selected = parse_edited(&edited, names)
+    .map_err(|e| AppError::Failed(format!("Can't read the edited list, {e}")))?;
+  if selected.is_empty() {
+    return Err(AppError::Cancelled);
+  }
+  Ok(selected)
+}
+
+/// Indices of the `names` left in `text` edited by [`edit_selection`],
+/// ignoring everything after `#` and blank lines. A name that wasn't
+/// offered fails rather than being guessed at.
+fn parse_edited(text: &str, names: &[String]) -> Result<Vec<usize>, String> {
+  let mut selected = vec![];
+  for (n, line) in text.lines().enumerate() {
+    let name = line.split('#').next().unwrap_or_default().trim();
+    if name.is_empty() {
+      continue;
+    }
+    let idx = names
+      .iter()
+      .position(|offered| offered == name)
+      .ok_or_else(|| format!("line {}: {name} wasn't in the list", n + 1))?;
+    if !selected.contains(&idx) {
+      selected.push(idx);
+    }
+  }
+  selected.sort_unstable();
+  Ok(selected)
+}
+
+/// `--max-total-size`, asks once more if the repos to delete total more
+/// than `max` bytes, or fails if [`select`] didn't ask either.
+fn check_total_size(to_delete: &[Candidate], max: u64, prompts: &Prompts) -> Result<(), AppError> {
+  let size = delete_unused_repo::total_size(to_delete);
+  if size <= max {
+    return Ok(());
+  }
+  let over = format!(
+    "The {} selected repos total ~{}, more than --max-total-size {}",
+    to_delete.len(),
+    HumanBytes(size),
+    HumanBytes(max)
+  );
+  if !prompts.asks() {
+    return Err(AppError::Failed(format!("{over}, not deleting without a prompt")));
+  }
+  warn!("{over}");
+  let confirmed = dialoguer::Confirm::with_theme(theme())
+    .with_prompt("Delete them anyway?")
+    .default(false)
+    .interact()
+    .unwrap_or(false);
+  if confirmed {
+    Ok(())
+  } else {
+    Err(AppError::Cancelled)
+  }
+}
+
+/// How deletion progress is shown.
+#[derive(Debug, Clone)]
+struct ProgressOptions {
+  style: Option<ProgressMode>,
+  refresh: Duration,
+  /// `--progress-to-file`
+  file: Option<Arc<Mutex<File>>>,
+  summary_interval: Option<SummaryInterval>,
+}
+
+/// Progress of `total` deletions, a bar on terminals by default. It is
+/// redrawn at most once per `refresh` however fast tasks report, and the
+/// spinner ticks by itself at that rate. [`ProgressBar`] locks its state,
+/// so messages set by concurrent tasks never interleave.
+fn progress_bar(options: ProgressOptions, total: usize) -> (Arc<ProgressBar>, ProgressMode) {
+  let mode = options.style.unwrap_or(if console::Term::stderr().is_term() {
+    ProgressMode::Bar
+  } else {
+    ProgressMode::Plain
+  });
+  let bar = match mode {
+    ProgressMode::Spinner => ProgressBar::new(total as u64).with_style(
+      ProgressStyle::with_template("{prefix:.bold.dim} {spinner} {wide_msg}")
+        .unwrap()
+        .tick_chars(if NO_EMOJI.load(Ordering::Relaxed) {
+          "|/-\\ "
+        } else {
+          "⠁⠂⠄⡀⢀⠠⠐⠈ "
+        }),
+    ),
+    ProgressMode::Bar => ProgressBar::new(total as u64).with_style(
+      ProgressStyle::with_template(
+        "{prefix:.bold.dim} [{bar:30}] {pos}/{len} ({percent}%, ETA {eta}) {wide_msg}",
+      )
+      .unwrap()
+      .progress_chars("=> "),
+    ),
+    ProgressMode::Plain => ProgressBar::hidden(),
+  };
+  let hz = (1000 / options.refresh.as_millis().max(1)).clamp(1, 255) as u8;
+  if mode != ProgressMode::Plain {
+    bar.set_draw_target(ProgressDrawTarget::stderr_with_hz(hz));
+  }
+  if mode == ProgressMode::Spinner {
+    bar.enable_steady_tick(options.refresh);
+  }
+  bar.set_length(total as u64);
+  bar.set_prefix("");
+  (Arc::new(bar), mode)
+}
+
+/// The callback for [`Cleaner::delete`] advancing `bar`, and writing to
+/// the `--progress-to-file` file if any.
+fn on_progress(
+  bar: &Arc<ProgressBar>,
+  mode: ProgressMode,
+  total: usize,
+  file: Option<Arc<Mutex<File>>>,
+) -> impl Fn(String) {
+  let bar = Arc::clone(bar);
+  move |msg| {
+    bar.inc(1);
+    if let Some(file) = &file {
+      let line = format!(
+        "{} [{}/{}] {}\n",
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        bar.position(),
+        total,
+        msg
+      );
+      if let Err(e) = file.lock().unwrap().write_all(line.as_bytes()) {
+        warn!("Failed to write the progress to --progress-to-file: {e}");
+      }
+    }
+    match mode {
+      ProgressMode::Plain => step!("[{}/{}] {}", bar.position(), total, msg),
+      _ if SUMMARY_ONLY.load(Ordering::Relaxed) => {}
+      _ => bar.set_message(msg),
+    }
+  }
+}
+
+/// Runs `deleting` while logging `--progress-summary-interval` summaries of
+/// the `total` deletions `bar` counts.
+async fn with_summaries<F: std::future::Future>(
+  deleting: F,
+  interval: Option<SummaryInterval>,
+  cleaner: &Cleaner,
+  bar: &ProgressBar,
+  total: usize,
+) -> F::Output {
+  let interval = match interval {
+    Some(interval) => interval,
+    None => return deleting.await,
+  };
   let started = Instant::now();
-  if std::env::var("RUST_LOG").is_err() {
-    std::env::set_var("RUST_LOG", "info");
+  let summaries = async {
+    let mut next = 0;
+    loop {
+      match interval {
+        SummaryInterval::Period(period) => tokio::time::sleep(period).await,
+        SummaryInterval::Deletions(n) => {
+          next += n;
+          while bar.position() < next {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+          }
+        }
+      }
+      log_summary(bar.position(), total, cleaner.failures(), started.elapsed(), bar);
+    }
+  };
+  tokio::select! {
+    output = deleting => output,
+    _ = summaries => unreachable!(),
   }
-  pretty_env_logger::init();
-  let args: Cli = Cli::parse();
-  debug!("{:?}", args);
+}
+
+fn log_summary(done: u64, total: usize, failed: usize, elapsed: Duration, bar: &ProgressBar) {
+  let rate = done as f64 / elapsed.as_secs_f64().max(1.0);
+  let eta = match rate > 0.0 {
+    true => {
+      let left = total.saturating_sub(done as usize) as f64 / rate;
+      format!("done in about {}", HumanDuration(Duration::from_secs(left.ceil() as u64)))
+    }
+    false => "no estimate yet".to_string(),
+  };
+  bar.suspend(|| {
+    info!("Progress: {done}/{total} done, {failed} failed, {rate:.1}/s, {eta}");
+  });
+}
 
-  let spinner_style = ProgressStyle::with_template("{prefix:.bold.dim} {spinner} {wide_msg}")
-    .unwrap()
-    .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ");
+/// First Ctrl-C lets running deletions finish, the second one exits at once
+fn stop_on_ctrl_c(stopper: Stopper, bar: &Arc<ProgressBar>) {
+  let interrupted = Arc::clone(bar);
+  tokio::spawn(async move {
+    tokio::signal::ctrl_c().await.unwrap();
+    stopper.stop();
+    interrupted.suspend(|| {
+      warn!("Stopping after running deletions, press Ctrl-C again to exit now")
+    });
+    tokio::signal::ctrl_c().await.unwrap();
+    interrupted.abandon();
+    let _ = console::Term::stderr().show_cursor();
+    exit(130);
+  });
+}
 
-  info!(
-    "{} {}Login to GitHub...",
-    style("[1/4]").bold().dim(),
-    CLIP
+/// Stops the run at `deadline` for `--max-runtime`. Before the deletion
+/// nothing was changed yet, so it exits right away, otherwise it stops
+/// the deletion like the first Ctrl-C.
+fn stop_at(deadline: Instant, max_runtime: Duration, stopper: Stopper) {
+  tokio::spawn(async move {
+    tokio::time::sleep_until(deadline.into()).await;
+    let runtime = humantime::format_duration(max_runtime);
+    if PHASE.swap(PHASE_OUT_OF_TIME, Ordering::SeqCst) == PHASE_PREPARING {
+      let _ = console::Term::stderr().show_cursor();
+      AppError::OutOfTime(format!("Ran out of --max-runtime {runtime} before deleting anything"))
+        .report();
+      exit(124);
+    }
+    stopper.stop();
+    warn!("Ran out of --max-runtime {runtime}, stopping after running deletions");
+  });
+}
+
+/// Enters the deletion, failing if `--max-runtime` ran out just before.
+fn start_deleting() -> Result<(), AppError> {
+  PHASE
+    .compare_exchange(PHASE_PREPARING, PHASE_DELETING, Ordering::SeqCst, Ordering::SeqCst)
+    .map(drop)
+    .map_err(|_| AppError::OutOfTime("Ran out of --max-runtime before deleting anything".into()))
+}
+
+/// Fails with a partial summary if the deletion was stopped, by Ctrl-C,
+/// `--max-runtime`, `--fail-fast` or `--max-failures`, and warns about failed deletions
+/// otherwise. Failed `--on-delete-cmd` runs fail with `--hook-strict`.
+fn check_outcomes(
+  cleaner: &Cleaner,
+  outcomes: &[Outcome],
+  bar: &ProgressBar,
+  noun: &str,
+  total: usize,
+) -> Result<(), AppError> {
+  if cleaner.stopper().is_stopped() {
+    bar.abandon();
+    let deleted: usize = outcomes.iter().map(|o| o.deleted).sum();
+    let skipped: usize = outcomes.iter().map(|o| o.skipped).sum();
+    let failed: usize = outcomes.iter().map(|o| o.errors.len()).sum();
+    let config = cleaner.config();
+    let counts = (deleted, failed, skipped);
+    if let Some(e) = failure_stop(config.fail_fast, config.max_failures, counts, noun) {
+      for outcome in outcomes.iter().filter(|o| o.skipped > 0) {
+        step!("Not attempted: {}", outcome.full_name);
+      }
+      return Err(e);
+    }
+    if PHASE.load(Ordering::SeqCst) == PHASE_OUT_OF_TIME {
+      return Err(AppError::OutOfTime(format!(
+        "Ran out of --max-runtime: {deleted} {noun} deleted, {failed} failed, \
+        {skipped} skipped for it"
+      )));
+    }
+    return Err(AppError::Interrupted(format!(
+      "Interrupted: {deleted} {noun} deleted, {failed} failed, {skipped} skipped"
+    )));
+  }
+
+  let failed: usize = outcomes.iter().map(|o| o.errors.len()).sum();
+  if failed > 0 {
+    let timed_out = outcomes
+      .iter()
+      .flat_map(|o| &o.errors)
+      .filter(|e| matches!(e, DeleteError::Timeout(_)))
+      .count();
+    warn!("Failed to delete {failed} of {total} {noun}, {timed_out} of them timed out");
+  }
+  let hook_failures = cleaner.hook_failures();
+  if hook_failures > 0 && HOOK_STRICT.load(Ordering::Relaxed) {
+    return Err(AppError::Failed(format!(
+      "--on-delete-cmd failed for {hook_failures} deleted {noun}"
+    )));
+  }
+  Ok(())
+}
+
+/// The error of a deletion stopped by `--fail-fast` or `--max-failures`,
+/// given the `(deleted, failed, skipped)` counts, `None` if the failures
+/// didn't stop it.
+fn failure_stop(
+  fail_fast: bool,
+  max_failures: Option<usize>,
+  (deleted, failed, skipped): (usize, usize, usize),
+  noun: &str,
+) -> Option<AppError> {
+  if fail_fast && failed > 0 {
+    return Some(AppError::Failed(format!(
+      "Stopped at the first failure: {deleted} {noun} deleted, {failed} failed, \
+      {skipped} not attempted"
+    )));
+  }
+  let max = max_failures.filter(|&max| failed > 0 && failed >= max)?;
+  Some(AppError::TooManyFailures(format!(
+    "Stopped after {max} failures, see --max-failures: {deleted} {noun} deleted, \
+    {failed} failed, {skipped} not attempted"
+  )))
+}
+
+/// Prints the result to stdout, or `--output-file`, for `--output json`,
+/// `ndjson` or `markdown`, and appends it to the local history. Only the
+/// JSON reports support packages, which have no `candidates`.
+fn print_report(
+  output: &Output,
+  target: Target,
+  candidates: &[Candidate],
+  matched: Vec<String>,
+  outcomes: &[Outcome],
+  interrupted: bool,
+  api_usage: Option<ApiUsage>,
+) {
+  if let Some(path) = &output.history {
+    let entry = history::Entry {
+      at: chrono::Utc::now(),
+      args: history::mask_secrets(std::env::args().skip(1)),
+      target: target.item().to_string(),
+      report_only: output.report_only,
+      matched: matched.len(),
+      deleted: outcomes.iter().map(|o| o.deleted).sum(),
+      failed: outcomes.iter().filter(|o| !o.errors.is_empty()).count(),
+      interrupted,
+    };
+    if let Err(e) = history::append(path, &entry) {
+      warn!("Failed to record the run to {}: {e}", path.display());
+    }
+  }
+  let lines: Vec<String> = match output.format {
+    OutputFormat::Text => return,
+    OutputFormat::Json => {
+      let report = Report {
+        target,
+        matched,
+        outcomes,
+        interrupted,
+        total_stars: delete_unused_repo::total_stars(candidates),
+        total_size: delete_unused_repo::total_size(candidates),
+        api_usage,
+        repos: JSON_METADATA
+          .load(Ordering::Relaxed)
+          .then(|| candidates.iter().map(RepoMetadata::from).collect()),
+      };
+      let json = if JSON_PRETTY.load(Ordering::Relaxed) {
+        serde_json::to_string_pretty(&report)
+      } else {
+        serde_json::to_string(&report)
+      };
+      vec![json.unwrap()]
+    }
+    OutputFormat::Ndjson if outcomes.is_empty() => matched
+      .iter()
+      .map(|name| serde_json::json!({ "full_name": name }).to_string())
+      .collect(),
+    OutputFormat::Ndjson => outcomes
+      .iter()
+      .map(|o| serde_json::to_string(o).unwrap())
+      .collect(),
+    OutputFormat::Markdown => report::markdown(candidates, outcomes)
+      .lines()
+      .map(str::to_string)
+      .collect(),
+    OutputFormat::Csv => report::csv(candidates, outcomes)
+      .lines()
+      .map(str::to_string)
+      .collect(),
+  };
+  write_lines(output, lines);
+}
+
+/// Writes `lines` to stdout, or `--output-file`.
+fn write_lines(output: &Output, lines: Vec<String>) {
+  let mut writer: Box<dyn Write> = match &output.file {
+    Some(path) => match std::fs::File::create(path) {
+      Ok(file) => Box::new(file),
+      Err(e) => {
+        error!("Failed to write the result to {}: {e}", path.display());
+        return;
+      }
+    },
+    None => Box::new(std::io::stdout().lock()),
+  };
+  for line in lines {
+    // Flushed per line so NDJSON consumers see each one at once
+    if let Err(e) = writeln!(writer, "{line}").and_then(|_| writer.flush()) {
+      error!("Failed to write the result: {e}");
+      return;
+    }
+  }
+  if let Some(path) = &output.file {
+    info!("Wrote the result to {}", path.display());
+  }
+}
+
+/// The command line, with the token hidden.
+fn command_line() -> String {
+  let mut args = vec![];
+  let mut hide_next = false;
+  for arg in std::env::args().skip(1) {
+    if hide_next {
+      args.push("***".to_string());
+      hide_next = false;
+    } else if arg == "-t" || arg == "--token" {
+      hide_next = true;
+      args.push(arg);
+    } else if arg.starts_with("--token=") {
+      args.push("--token=***".to_string());
+    } else if arg.starts_with("-t") && arg.len() > 2 {
+      args.push("-t***".to_string());
+    } else {
+      args.push(arg);
+    }
+  }
+  format!("delete-unused-repo {}", args.join(" "))
+}
+
+/// Writes the `--html-report`, if asked for.
+fn write_html_report(path: &Option<PathBuf>, candidates: &[Candidate], outcomes: &[Outcome]) {
+  if let Some(path) = path {
+    let html = report::html(candidates, outcomes, &command_line());
+    match std::fs::write(path, html) {
+      Ok(()) => info!("Wrote the report to {}", path.display()),
+      Err(e) => error!("Failed to write the report to {}: {e}", path.display()),
+    }
+  }
+}
+
+/// The `history` command, a line per recorded run, oldest first.
+fn print_history() -> Result<(), AppError> {
+  let path = history::default_path().ok_or_else(|| {
+    AppError::Failed("No home directory to find the history in".to_string())
+  })?;
+  let entries = history::read(&path).map_err(|e| {
+    AppError::Failed(format!("Failed to read the history {}: {e}", path.display()))
+  })?;
+  if entries.is_empty() {
+    info!("No runs recorded in {} yet", path.display());
+  }
+  for entry in entries {
+    let result = match entry.report_only {
+      true => "report only".to_string(),
+      false => format!("{} deleted, {} failed", entry.deleted, entry.failed),
+    };
+    println!(
+      "{}  {}s  {} matched, {result}{}  {}",
+      entry.at.format("%Y-%m-%d %H:%M:%S UTC"),
+      entry.target,
+      entry.matched,
+      if entry.interrupted { ", interrupted" } else { "" },
+      entry.args.join(" ")
+    );
+  }
+  Ok(())
+}
+
+/// `--list-scopes`, to tell why deletions are refused.
+async fn list_scopes(cleaner: &Cleaner) -> Result<(), AppError> {
+  let token = cleaner.check_token().await.map_err(|e| {
+    AppError::Failed(format!("Token check failed, can't get the user of the token: {e}"))
+  })?;
+  println!("login: {}", token.login);
+  match &token.scopes {
+    Some(scopes) => {
+      println!("scopes: {}", scopes.join(", "));
+      let delete_repo = scopes.iter().any(|s| s == "delete_repo");
+      println!("delete_repo: {}", if delete_repo { "yes" } else { "no" });
+    }
+    None => {
+      println!("scopes: none reported, fine-grained tokens have permissions instead of scopes");
+      println!("delete_repo: unknown, check the token has the Administration write permission");
+    }
+  }
+  Ok(())
+}
+
+/// `--owner-activity-summary`, lists the repos and prints their totals per
+/// owner, human readable for `--output text`.
+async fn print_owner_summaries(
+  cleaner: &Cleaner,
+  output: &Output,
+  strict_visibility: bool,
+) -> Result<(), AppError> {
+  if output.format == OutputFormat::Csv {
+    return Err(AppError::Failed(
+      "--owner-activity-summary supports --output text, json, ndjson and markdown".to_string(),
+    ));
+  }
+  step!(
+    "{} {}Search repos...",
+    style("[2/2]").bold().dim(),
+    emoji(LOOKING_GLASS)
   );
+  let repos = cleaner
+    .list_candidates()
+    .await
+    .map_err(|e| AppError::Failed(format!("Failed to get GitHub repos of you: {e}")))?;
+  pipeline::check_visibility(strict_visibility, &repos)?;
+  let summaries = cleaner.owner_summaries(&repos);
+  let lines = match output.format {
+    OutputFormat::Text => {
+      let width = summaries.iter().map(|s| s.owner.len()).max().unwrap_or(0).max(5);
+      let mut lines = vec![format!(
+        "{:width$}  {:>6}  {:>6}  {:>8}  {:>6}  {:>10}  {:>7}",
+        "Owner", "Repos", "Forks", "Archived", "Stale", "Size", "Stars"
+      )];
+      for s in &summaries {
+        lines.push(format!(
+          "{:width$}  {:>6}  {:>6}  {:>8}  {:>6}  {:>10}  {:>7}",
+          s.owner,
+          s.repos,
+          s.forks,
+          s.archived,
+          s.stale,
+          HumanBytes(s.size * 1024).to_string(),
+          s.stars
+        ));
+      }
+      lines
+    }
+    OutputFormat::Json => {
+      let json = if JSON_PRETTY.load(Ordering::Relaxed) {
+        serde_json::to_string_pretty(&summaries)
+      } else {
+        serde_json::to_string(&summaries)
+      };
+      vec![json.unwrap()]
+    }
+    OutputFormat::Ndjson => summaries
+      .iter()
+      .map(|s| serde_json::to_string(s).unwrap())
+      .collect(),
+    OutputFormat::Markdown => report::owner_summary_markdown(&summaries)
+      .lines()
+      .map(str::to_string)
+      .collect(),
+    OutputFormat::Csv => unreachable!(),
+  };
+  write_lines(output, lines);
+  Ok(())
+}
 
-  let gh = octocrab::Octocrab::builder()
-    .personal_token(args.token)
-    .build();
-  let gh = match gh {
-    Ok(gh) => gh,
-    Err(e) => {
-      error!("Failed to login GitHub via personal token: {e}");
-      exit(1);
+/// `--output-owners-only`, prints the distinct owners of the matched repos.
+fn print_owners(output: &Output, candidates: &[Candidate]) -> Result<(), AppError> {
+  let owners = delete_unused_repo::owners(candidates);
+  let lines = match output.format {
+    OutputFormat::Text => owners,
+    OutputFormat::Json => {
+      let json = if JSON_PRETTY.load(Ordering::Relaxed) {
+        serde_json::to_string_pretty(&owners)
+      } else {
+        serde_json::to_string(&owners)
+      };
+      vec![json.unwrap()]
+    }
+    OutputFormat::Ndjson => owners
+      .iter()
+      .map(|o| serde_json::to_string(o).unwrap())
+      .collect(),
+    OutputFormat::Markdown | OutputFormat::Csv => {
+      return Err(AppError::Failed(
+        "--output-owners-only supports --output text, json and ndjson".to_string(),
+      ));
     }
   };
-  let gh = Arc::new(gh);
+  write_lines(output, lines);
+  Ok(())
+}
 
+/// `--validate-only`, every option was already parsed by clap by now.
+async fn validate(cleaner: &Cleaner) -> Result<(), AppError> {
+  let token = cleaner.check_token().await.map_err(|e| {
+    AppError::Failed(format!("Token check failed, can't get the user of the token: {e}"))
+  })?;
+  info!("Token belongs to {}", token.login);
+  match &token.scopes {
+    Some(scopes) if !scopes.iter().any(|s| s == "delete_repo") => {
+      if cleaner.config().target == Target::Repos {
+        return Err(AppError::Failed(
+          "Scope check failed, the token lacks the delete_repo scope".to_string(),
+        ));
+      }
+      warn!("The token lacks the delete_repo scope, repos can't be deleted");
+    }
+    Some(_) => info!("Token has the delete_repo scope"),
+    None => warn!("Can't check the scopes of a fine-grained token, make sure it may delete repos"),
+  }
+  cleaner
+    .check_listing()
+    .await
+    .map_err(|e| AppError::Failed(format!("Listing check failed, can't list your repos: {e}")))?;
+  info!("Options, token and repo access are fine");
+  Ok(())
+}
+
+/// The API calls made since the `start` of a --report-only run, logged so the
+/// cost of the real run can be told.
+async fn api_usage(cleaner: &Cleaner, start: Option<RateLimits>) -> Option<ApiUsage> {
+  let start = start?;
+  let end = match cleaner.rate_limits().await {
+    Ok(end) => end,
+    Err(e) => {
+      warn!("Failed to get the rate limit, API calls won't be counted: {e}");
+      return None;
+    }
+  };
+  let usage = ApiUsage::between(&start, &end);
   info!(
-    "{} {}Search repos...",
+    "Made {} API calls and {} GraphQL calls, {} of {} left until {}",
+    usage.calls,
+    usage.graphql_calls,
+    usage.remaining,
+    usage.limit,
+    usage.reset_at.format("%H:%M UTC")
+  );
+  Some(usage)
+}
+
+/// `--target packages`, which works on packages instead of repos.
+async fn clean_packages(
+  cleaner: &Cleaner,
+  report_only: bool,
+  budget: Option<RateLimits>,
+  prompts: &Prompts,
+  progress: ProgressOptions,
+  output: &Output,
+) -> Result<(), AppError> {
+  step!(
+    "{} {}Search packages...",
     style("[2/4]").bold().dim(),
-    LOOKING_GLASS
+    emoji(LOOKING_GLASS)
   );
+  let packages = cleaner
+    .list_packages()
+    .await
+    .map_err(|e| AppError::Failed(format!("Failed to get GitHub packages of you: {e}")))?;
+  if packages.is_empty() {
+    info!("No matched packages");
+    return Ok(());
+  }
+  info!("Matched {} packages", packages.len());
+
+  let items: Vec<_> = packages
+    .iter()
+    .map(|p| {
+      let repo = match &p.repository {
+        Some(repo) => format!("of {}", repo.full_name),
+        None => "without repo".to_string(),
+      };
+      format!(
+        "{} ({} versions, {repo}, updated {})",
+        p.full_name(),
+        p.version_count.unwrap_or(0),
+        p.updated_at.format("%Y-%m-%d")
+      )
+    })
+    .collect();
+  let matched: Vec<_> = packages.iter().map(|p| p.full_name()).collect();
+  if report_only {
+    for item in &items {
+      info!("{}", item);
+    }
+    let usage = api_usage(cleaner, budget).await;
+    print_report(output, Target::Packages, &[], matched, &[], false, usage);
+    return Ok(());
+  }
+
+  if let (Some(mode), true) = (prompts.preview, prompts.asks()) {
+    preview(mode, &items, &[]);
+  }
+  let selected = select(
+    &items,
+    &matched,
+    &[],
+    "These packages will be deleted",
+    "packages",
+    prompts,
+  )?;
+  if selected.is_empty() {
+    return Ok(());
+  }
+  let packages: Vec<_> = selected.into_iter().map(|i| packages[i].clone()).collect();
+
+  let total = packages.len();
+  confirm_count(total, "packages", prompts)?;
+  cooldown(prompts, total, "packages").await?;
+  let (file, interval) = (progress.file.clone(), progress.summary_interval);
+  let (p1, mode) = progress_bar(progress, total);
+  start_deleting()?;
+  stop_on_ctrl_c(cleaner.stopper(), &p1);
+  let deleting = cleaner.delete_packages(packages, on_progress(&p1, mode, total, file));
+  let outcomes = with_summaries(deleting, interval, cleaner, &p1, total).await;
+  if let Err(e) = cleaner.flush_attempt_log() {
+    warn!("Failed to write the attempt log: {e}");
+  }
+  let interrupted = cleaner.stopper().is_stopped();
+  print_report(output, Target::Packages, &[], matched, &outcomes, interrupted, None);
+  check_outcomes(cleaner, &outcomes, &p1, "packages", total)?;
+  step!("{} {} Delete packages", style("[4/4]").bold().dim(), emoji(TRASH));
+  Ok(())
+}
 
+#[tokio::main]
+async fn main() {
+  if std::env::var("RUST_LOG").is_err() {
+    std::env::set_var("RUST_LOG", "info");
+  }
+  pretty_env_logger::init();
+  if let Err(e) = run(Cli::parse()).await {
+    e.report();
+    exit(e.exit_code());
+  }
+}
 
-  let get_repos = |page: u8| {
-    let gh = Arc::clone(&gh);
-    async move {
-      let page = match gh
-        .current()
-        .list_repos_for_authenticated_user()
-        .per_page(100)
-        .page(page)
-        .send()
-        .await
-      {
-        Ok(page) => page,
+async fn run(mut args: Cli) -> Result<(), AppError> {
+  let started = Instant::now();
+  NO_EMOJI.store(args.no_emoji, Ordering::Relaxed);
+  SUMMARY_ONLY.store(args.summary_only, Ordering::Relaxed);
+  JSON_PRETTY.store(args.json_pretty, Ordering::Relaxed);
+  JSON_METADATA.store(args.json_include_metadata, Ordering::Relaxed);
+  HOOK_STRICT.store(args.hook_strict, Ordering::Relaxed);
+  if args.no_color {
+    console::set_colors_enabled(false);
+    console::set_colors_enabled_stderr(false);
+  }
+  COLORFUL_PROMPTS.store(
+    args.prompt_theme == PromptTheme::Colorful && !args.no_color,
+    Ordering::Relaxed,
+  );
+  if args.print_schema {
+    println!("{}", serde_json::to_string_pretty(&report::schema()).unwrap());
+    return Ok(());
+  }
+  if let Some(Subcommand::History) = args.command {
+    return print_history();
+  }
+  pipeline::configure_network(args.proxy.as_deref(), args.ca_cert.as_deref())?;
+  if let Some(path) = &args.dump_api_calls {
+    api_trace::init(path).map_err(|e| {
+      AppError::Failed(format!("Failed to create the API trace {}: {e}", path.display()))
+    })?;
+  }
+  if let (None, Some(path)) = (&args.token, &args.token_file) {
+    args.token = Some(pipeline::read_token_file(path)?);
+  }
+  let app = match (args.app_id, &args.app_key, args.installation_id) {
+    (Some(app_id), Some(path), Some(installation_id)) => {
+      Some(pipeline::read_app_key(app_id, path, installation_id)?)
+    }
+    _ => None,
+  };
+  if args.token.is_none() && app.is_none() {
+    let token = prompt_token().ok_or_else(|| {
+      AppError::Failed("No GitHub token, pass one with --token or --token-file".to_string())
+    })?;
+    args.token = Some(token);
+  }
+  let from_file = args.owner_file.as_deref().map(pipeline::read_owner_file).transpose()?;
+  let case_sensitive = args.owner_case_sensitive;
+  args.owner = pipeline::merge_owners(args.owner.take(), from_file, args.mine, case_sensitive);
+  let any_owner = args
+    .owner
+    .as_ref()
+    .is_some_and(|owners| owners.iter().any(|o| o == delete_unused_repo::ANY_OWNER));
+  if any_owner {
+    if args.owner.as_ref().is_some_and(|owners| owners.len() > 1) {
+      warn!("--owner '*' overrides the other --owner values");
+    }
+    args.owner = None;
+    args.all_orgs = true;
+    warn!("--owner '*': repos of you and of every org you are a member of will be considered");
+  }
+  if args.repo_and_filter && args.owner.is_none() && !args.all_orgs {
+    let owners = pipeline::owners_of(&args.repos);
+    info!("--repo-and-filter: listing the owners of --repo, {}", owners.join(", "));
+    args.owner = Some(owners);
+  }
+  debug!("{:?}", args);
+  match (args.list_type.as_deref(), args.fork) {
+    (Some("forks"), false) => warn!("--list-type forks lists only forks, --fork=false keeps none"),
+    (Some("sources"), true) => warn!("--list-type sources lists no forks, --fork keeps only forks"),
+    _ => {}
+  }
+  if args.include_private {
+    include_private(&mut args.visibility);
+    warn!("--include-private: private and internal repos will be considered for deletion too");
+  }
+  if args.visibility.len() > 1 && args.visibility.iter().any(|v| v == "all") {
+    warn!("--visibility all overrides the other visibility values");
+  }
+  let target = args.target;
+  let dump_filtered = args.dump_filtered.clone();
+  let keep = match (&args.keep_file, args.target) {
+    (Some(path), Target::Repos) => Some(pipeline::read_keep_file(path)?),
+    (Some(_), _) => {
+      return Err(AppError::Failed("--reconcile only works with repos".to_string()));
+    }
+    (None, _) => None,
+  };
+  let reconcile_max = args.reconcile_max;
+  let strict_visibility = args.strict_visibility;
+  let mut given_repos = args.repos.clone();
+  let mut seen = HashSet::new();
+  given_repos.retain(|r| seen.insert(r.to_lowercase()));
+  let retry_record = match &args.retry_failed {
+    Some(path) => {
+      if args.output_file.as_ref() == Some(path) {
+        return Err(AppError::Failed(format!(
+          "--retry-failed updates {} itself, pick another --output-file",
+          path.display()
+        )));
+      }
+      let mut record = RetryRecord::read(path, args.target)?;
+      if record.failed.is_empty() {
+        info!("No failed deletions recorded in {}", path.display());
+        return Ok(());
+      }
+      info!("Retrying {} failed deletions from {}", record.failed.len(), path.display());
+      given_repos = std::mem::take(&mut record.failed);
+      Some(record)
+    }
+    None => None,
+  };
+  let owner_activity_summary = args.owner_activity_summary;
+  let diff_against = args.diff_against.clone();
+  let sort = args.sort;
+  let delete_order = args.delete_order;
+  let dedupe_by_name = args.dedupe_by_name;
+  let list_only_deletable = args.list_only_deletable;
+  let owners_only = args.output_owners_only;
+  let report_only = args.report_only || list_only_deletable || owners_only;
+  let progress = ProgressOptions {
+    style: args.progress_style,
+    refresh: Duration::from_millis(args.progress_refresh),
+    summary_interval: args.progress_summary_interval,
+    file: match &args.progress_to_file {
+      Some(path) => match File::create(path) {
+        Ok(file) => Some(Arc::new(Mutex::new(file))),
         Err(e) => {
-          error!("Failed to get GitHub repos of you: {e}");
-          exit(1);
+          return Err(AppError::Failed(format!(
+            "Failed to create --progress-to-file {}: {e}",
+            path.display()
+          )));
         }
-      };
-      page
-    }
+      },
+      None => None,
+    },
+  };
+  let prompts = Prompts {
+    confirm_seen: args.confirm_seen,
+    non_interactive: args.non_interactive,
+    auto_skip_safe_confirm: args.auto_skip_safe_confirm,
+    confirm_file: args.confirm_file.clone(),
+    confirm_env: args.confirm_phrase_from_env,
+    preview: args.preview_open,
+    random_phrase: args.confirm_phrase_random,
+    cap: args.cap_candidates,
+    select_pattern: args.select_pattern.clone(),
+    select_first: args.select_first,
+    select_last: args.select_last,
+    confirm_under: args.no_double_confirm_under,
+    editor: args.confirm_via_editor,
+    select_invert: args.select_invert,
+    confirm_count: args.confirm_count,
+    cooldown: args.cooldown,
+  };
+  let output = Output {
+    format: args.output,
+    file: args.output_file.clone(),
+    history: match args.no_history {
+      true => None,
+      false => history::default_path(),
+    },
+    report_only,
+  };
+  let retries_log = args.retries_log.clone();
+  let rename_manifest = args.rename_before_delete.then(|| args.rename_manifest.clone());
+  let html_report = args.html_report.clone();
+  let validate_only = args.validate_only;
+  let list_scopes_only = args.list_scopes;
+  let compare_upstream = args.compare_to_upstream_commits;
+  let color_by_risk = args.color_by_risk;
+  let max_total_size = args.max_total_size;
+  let max_runtime = args.max_runtime;
+  let ignore = match (&args.ignore_file, args.no_ignore_file) {
+    (Some(path), _) => pipeline::load_ignore(std::slice::from_ref(path), true)?,
+    (None, false) => pipeline::load_ignore(&delete_unused_repo::ignore::default_paths(), false)?,
+    (None, true) => None,
+  };
+  let sources = Sources {
+    from_file: args.from_file.clone(),
+    repos: given_repos,
+    repo_and_filter: args.repo_and_filter,
+    keep,
+    strict_visibility,
+    preflight_count: args.preflight_count,
+    // --report-only reports every match, deleting runs only offer those that
+    // can be deleted
+    undeletable: report_only && !list_only_deletable,
   };
 
-  let mut repos = vec![];
-  {
-    let first = get_repos(1).await;
-    let page_num = first.number_of_pages();
-    repos.extend(first);
-    if page_num >= Some(2) {
-      let (tx, mut rx) = tokio::sync::mpsc::channel(32);
-      for i in 2..=page_num.unwrap() {
-        let i = i as u8;
-        let handle = tokio::spawn(get_repos(i));
-        tx.send(handle).await.unwrap();
-      }
-      drop(tx);
+  step!(
+    "{} {}Login to GitHub...",
+    style("[1/4]").bold().dim(),
+    emoji(CLIP)
+  );
+
+  let mut config: Config = args.into();
+  config.ignore = ignore;
+  let mut cleaner = pipeline::login(config, app).await?;
+  if let Some(max_runtime) = max_runtime {
+    stop_at(started + max_runtime, max_runtime, cleaner.stopper());
+  }
+  if list_scopes_only {
+    return list_scopes(&cleaner).await;
+  }
+  if validate_only {
+    return validate(&cleaner).await;
+  }
 
-      while let Some(get_repo) = rx.recv().await {
-        repos.extend(get_repo.await.unwrap().items);
+  // Before any other call, so they're all counted
+  let budget = if report_only {
+    match cleaner.rate_limits().await {
+      Ok(budget) => Some(budget),
+      Err(e) => {
+        warn!("Failed to get the rate limit, API calls won't be counted: {e}");
+        None
       }
     }
+  } else {
+    None
+  };
+
+  if any_owner && !report_only {
+    confirm_any_owner(&prompts)?;
+  }
+
+  cleaner
+    .resolve_me()
+    .await
+    .map_err(|e| AppError::Failed(format!("Failed to get who @me is: {e}")))?;
+
+  if output.file.is_some() && output.format == OutputFormat::Text {
+    warn!("--output-file has nothing to write with --output text");
+  }
+  pipeline::create_dirs(output.file.as_deref(), cleaner.config().backup_dir.as_deref())?;
+  let rename_manifest = rename_manifest.filter(|_| target == Target::Repos);
+  let cleaner = pipeline::with_logs(cleaner, retries_log.as_deref(), rename_manifest.as_deref())?;
+
+  if owner_activity_summary {
+    return print_owner_summaries(&cleaner, &output, strict_visibility).await;
+  }
+
+  if target == Target::Packages {
+    if matches!(output.format, OutputFormat::Markdown | OutputFormat::Csv) || html_report.is_some()
+    {
+      return Err(AppError::Failed(
+        "--output markdown, csv and --html-report only work with repos, not packages".to_string(),
+      ));
+    }
+    if sources.from_file.is_some()
+      || !sources.repos.is_empty()
+      || dump_filtered.is_some()
+      || diff_against.is_some()
+      || owners_only
+      || JSON_METADATA.load(Ordering::Relaxed)
+    {
+      return Err(AppError::Failed(
+        "--from-file, --repo, --dump-filtered, --diff-against, --output-owners-only and \
+         --json-include-metadata only work with repos"
+          .to_string(),
+      ));
+    }
+    clean_packages(&cleaner, report_only, budget, &prompts, progress, &output).await?;
+    info!("{} Done in {}", emoji(SPARKLE), HumanDuration(started.elapsed()));
+    return Ok(());
+  }
+
+  let step = |step: Step| match step {
+    Step::Load(path) => step!(
+      "{} {}Load repos from {}...",
+      style("[2/4]").bold().dim(),
+      emoji(LOOKING_GLASS),
+      path.display()
+    ),
+    Step::Get(count) => step!(
+      "{} {}Get {} given repos...",
+      style("[2/4]").bold().dim(),
+      emoji(LOOKING_GLASS),
+      count
+    ),
+    Step::Search => step!(
+      "{} {}Search repos...",
+      style("[2/4]").bold().dim(),
+      emoji(LOOKING_GLASS)
+    ),
+    Step::Reconcile => step!(
+      "{} {}Reconcile repos with the keep file...",
+      style("[3/4]").bold().dim(),
+      emoji(FILTER),
+    ),
+    Step::Filter => step!(
+      "{} {}Filter repos...",
+      style("[3/4]").bold().dim(),
+      emoji(FILTER),
+    ),
   };
+  let confirm = |matched| confirm_preflight(matched, &prompts);
+  let mut candidates = pipeline::find_candidates(&cleaner, &sources, step, confirm).await?;
+  if let (Some(keep), false) = (&sources.keep, report_only) {
+    confirm_reconcile(&candidates, keep.len(), reconcile_max, &prompts)?;
+  }
+
+  if candidates.is_empty() {
+    match target {
+      Target::Repos => info!("No matched repos"),
+      Target::WorkflowRuns => info!("No matched workflow runs"),
+      Target::Releases => info!("No matched releases"),
+      Target::Pages => info!("No matched repos with a Pages site"),
+      Target::Labels => info!("No matched unused labels"),
+      Target::Subscriptions => info!("No matched watched repos"),
+      Target::Stars => info!("No matched starred repos"),
+      Target::Packages => unreachable!(),
+    }
+    return Ok(());
+  }
 
+  delete_unused_repo::sort(&mut candidates, sort);
+
+  let stars = delete_unused_repo::total_stars(&candidates);
+  let size = delete_unused_repo::total_size(&candidates);
   info!(
-    "{} {}Filter repos...",
-    style("[3/4]").bold().dim(),
-    FILTER,
+    "Matched {} repos (total {}{}, ~{})",
+    candidates.len(),
+    emoji(STAR),
+    stars,
+    HumanBytes(size)
   );
 
-  let repos: Vec<_> = repos
-    .into_iter()
-    .filter(|r| {
-      if let Some(user) = r.owner.clone().map(|u| u.login) {
-        if let Some(owner) = &args.owner {
-          owner.contains(&user)
-        } else {
-          true
+  if let Some(path) = dump_filtered {
+    delete_unused_repo::dump::write(&path, &candidates).map_err(|e| {
+      AppError::Failed(format!("Failed to write repos to {}: {e}", path.display()))
+    })?;
+    info!("Wrote {} repos to {}", candidates.len(), path.display());
+  }
+
+  // Repos of the previous run, to tell which matches are new
+  let previous = match diff_against {
+    Some(path) => Some(pipeline::read_previous(&path)?),
+    None => None,
+  };
+  let mark = |name: &str| match &previous {
+    Some(previous) if !previous.contains(name) => format!(" {}", style("[new]").green()),
+    _ => String::new(),
+  };
+  if let Some(previous) = &previous {
+    let new = candidates
+      .iter()
+      .filter(|c| !previous.contains(&c.full_name()))
+      .count();
+    info!("{new} of them are new, {} were matched before", candidates.len() - new);
+  }
+
+  if owners_only {
+    return print_owners(&output, &candidates);
+  }
+
+  let matched: Vec<_> = candidates.iter().map(|c| c.full_name()).collect();
+  let risky = |item: String, c: &Candidate| match color_by_risk {
+    true => by_risk(item, delete_unused_repo::risk(&c.repo, chrono::Utc::now())),
+    false => item,
+  };
+  if report_only {
+    for (name, c) in matched.iter().zip(&candidates) {
+      info!("{}{}", risky(name.clone(), c), mark(name));
+    }
+    let usage = api_usage(&cleaner, budget).await;
+    print_report(&output, target, &candidates, matched, &[], false, usage);
+    write_html_report(&html_report, &candidates, &[]);
+    return Ok(());
+  }
+
+  let items: Vec<_> = candidates
+    .iter()
+    .map(|c| {
+      let name = c.full_name();
+      match target {
+        Target::Repos if !c.is_admin() => {
+          let not_admin = style("[not admin]").red();
+          format!("{}{} {not_admin}", risky(name.clone(), c), mark(&name))
+        }
+        Target::Repos
+        | Target::Packages
+        | Target::Pages
+        | Target::Subscriptions
+        | Target::Stars => format!("{}{}", risky(name.clone(), c), mark(&name)),
+        Target::WorkflowRuns => {
+          format!("{} ({} runs){}", risky(name.clone(), c), c.items.len(), mark(&name))
+        }
+        Target::Releases => {
+          format!("{} ({} releases){}", risky(name.clone(), c), c.items.len(), mark(&name))
+        }
+        Target::Labels => {
+          format!("{} ({}){}", risky(name.clone(), c), c.labels.join(", "), mark(&name))
         }
-      } else {
-        true
-      }
-    })
-    .filter(|r| {
-      if let Some(vis) = &r.visibility {
-        args.visibility.contains(vis)
-      } else {
-        true
       }
     })
-    .filter(|r| r.fork == Some(args.fork))
-    .filter(|r| r.stargazers_count <= Some(args.star))
     .collect();
-
-  if repos.is_empty() {
-    info!("No matched repos");
-    exit(0);
-  }
-
-  let iter: Vec<_> = repos
-    .into_iter()
-    .map(|r| (r.full_name.clone().unwrap(), r))
+  let items: Vec<_> = if compare_upstream {
+    let upstream = cleaner.upstream_commits(&candidates).await;
+    items
+      .into_iter()
+      .zip(&candidates)
+      .zip(upstream)
+      .map(|((item, c), upstream)| match (c.repo.fork, upstream) {
+        (Some(true), Some((ahead, behind))) => {
+          format!("{item} {}", style(format!("+{ahead}/-{behind}")).dim())
+        }
+        (Some(true), None) => format!("{item} {}", style("?").dim()),
+        _ => item,
+      })
+      .collect()
+  } else {
+    items
+  };
+  let (noun, prompt) = match target {
+    Target::Repos => ("repos", "These repos will be deleted"),
+    Target::WorkflowRuns => (
+      "workflow runs",
+      "Workflow runs of these repos will be deleted",
+    ),
+    Target::Releases => ("releases", "Releases of these repos will be deleted"),
+    Target::Pages => ("Pages sites", "Pages sites of these repos will be deleted"),
+    Target::Labels => ("labels", "Unused labels of these repos will be deleted"),
+    Target::Subscriptions => (
+      "subscriptions",
+      "You will stop watching these repos, they and your stars of them are kept",
+    ),
+    Target::Stars => (
+      "stars",
+      "You will unstar these repos, they are kept and still watched if you watch them",
+    ),
+    Target::Packages => unreachable!(),
+  };
+  let now = chrono::Utc::now();
+  let safe: Vec<_> = candidates
+    .iter()
+    .map(|c| target == Target::Repos && delete_unused_repo::is_safe(&c.repo, now))
     .collect();
-  let map: HashMap<_, _> = HashMap::from_iter(iter);
-
-  let keys = map.keys().collect::<Vec<_>>();
-  let result = dialoguer::MultiSelect::new()
-    .with_prompt(
-      "These repos will be deleted, \n\
-      [Space] to check item, \n\
-      [Esc/q] to cancel, \n\
-      [Enter] to confirm",
-    )
-    .items(&keys)
-    .defaults(&*vec![true; keys.len()])
-    .interact_opt();
-
-  if result.is_err() || (result.is_ok() && result.as_ref().unwrap().is_none()) {
-    info!("Cancelled");
-    exit(1);
-  }
-
-  let confirm_str = "I want to remove all repos above".to_string();
-  let confirm: std::io::Result<String> = dialoguer::Input::new()
-    .with_prompt(format!("Double confirm, please type '{confirm_str}'"))
-    .interact();
-  if confirm.is_ok() && confirm.unwrap() == confirm_str {
+  // With --dedupe-by-name each item is a group of same-named repos, and
+  // selecting it selects them all
+  let groups = pipeline::group_by_name(&candidates, dedupe_by_name);
+  let names: Vec<String> = if dedupe_by_name {
+    groups
+      .iter()
+      .map(|group| candidates[group[0]].repo.name.clone())
+      .collect()
+  } else {
+    matched.clone()
+  };
+  let (items, safe): (Vec<_>, Vec<_>) = if dedupe_by_name {
+    groups
+      .iter()
+      .map(|group| {
+        let names: Vec<_> = group.iter().map(|&i| items[i].as_str()).collect();
+        let item = format!(
+          "{} ({} repos: {})",
+          candidates[group[0]].repo.name,
+          group.len(),
+          names.join(", ")
+        );
+        (item, group.iter().all(|&i| safe[i]))
+      })
+      .unzip()
   } else {
-    info!("Cancelled");
-    exit(1);
+    (items, safe)
   };
+  if let (Some(mode), true) = (prompts.preview, prompts.asks()) {
+    preview(mode, &items, &candidates);
+  }
+  let selected = select(&items, &names, &safe, prompt, noun, &prompts)?;
+  if selected.is_empty() {
+    return Ok(());
+  }
+  let mut to_delete: Vec<_> = selected
+    .into_iter()
+    .flat_map(|idx| &groups[idx])
+    .map(|&idx| candidates[idx].clone())
+    .collect();
+  delete_unused_repo::order_for_deletion(&mut to_delete, delete_order, chrono::Utc::now());
+
+  if let (Target::Repos, Some(max)) = (target, max_total_size) {
+    check_total_size(&to_delete, max, &prompts)?;
+  }
+
+  let total: usize = to_delete.iter().map(|c| c.count(target)).sum();
+  confirm_count(total, noun, &prompts)?;
+  cooldown(&prompts, total, noun).await?;
+  let (file, interval) = (progress.file.clone(), progress.summary_interval);
+  let (p1, mode) = progress_bar(progress, total);
+
+  start_deleting()?;
+  stop_on_ctrl_c(cleaner.stopper(), &p1);
+
+  let deleting = cleaner.delete(to_delete, on_progress(&p1, mode, total, file));
+  let outcomes = with_summaries(deleting, interval, &cleaner, &p1, total).await;
+  if let Err(e) = cleaner.flush_attempt_log() {
+    warn!("Failed to write the attempt log: {e}");
+  }
+  let interrupted = cleaner.stopper().is_stopped();
+  print_report(&output, target, &candidates, matched, &outcomes, interrupted, None);
+  write_html_report(&html_report, &candidates, &outcomes);
+  if let Some(record) = retry_record {
+    record.update(&outcomes);
+  }
 
+  check_outcomes(&cleaner, &outcomes, &p1, noun, total)?;
+  if SUMMARY_ONLY.load(Ordering::Relaxed) {
+    let deleted: usize = outcomes.iter().map(|o| o.deleted).sum();
+    info!("Deleted {deleted} of {total} {noun}");
+  }
 
-  let repos: Vec<_> = if let Ok(Some(to_del)) = result {
-    to_del.into_iter().map(|idx| map[keys[idx]].clone()).collect()
+  if target != Target::Repos {
+    p1.finish_and_clear();
+    for outcome in outcomes {
+      let total = outcome.deleted + outcome.errors.len();
+      step!(
+        "Deleted {}/{} {} of {}",
+        outcome.deleted, total, noun, outcome.full_name
+      );
+    }
+    step!("{} {} Delete {}", style("[4/4]").bold().dim(), emoji(TRASH), noun);
   } else {
-    info!("Cancelled");
-    exit(0);
-  };
-
-  let p1 = Arc::new(ProgressBar::new(repos.len() as u64));
-  p1.set_style(spinner_style);
-  p1.set_prefix("");
-  drop(map);
-  let (tx, mut rx) = tokio::sync::mpsc::channel(64);
-  for repo in repos {
-    let owner = repo.owner.map(|a| a.login);
-    if owner.is_none() { return; }
-    let owner = owner.unwrap();
-    let repo = repo.name;
-    let gh = Arc::clone(&gh);
-    let p1 = Arc::clone(&p1);
-    let handle = async move {
-      if let Err(err) = gh.repos(&owner, &repo).delete().await {
-        error!("Failed to delete {}/{}: {:?}", owner, repo, err);
+    step!("{} {} Delete repos", style("[4/4]").bold().dim(), emoji(TRASH));
+  }
+  info!("{} Done in {}", emoji(SPARKLE), HumanDuration(started.elapsed()));
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Writes `content` to a file named `name` in a temporary directory.
+  fn temp_file(name: &str, content: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("delete-unused-repo-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, content).unwrap();
+    path
+  }
+
+  #[test]
+  fn confirm_file_phrase_and_count() {
+    let check = |name, content| check_confirm_file(&temp_file(name, content), "delete 2", 2);
+    assert_eq!(check("phrase", "delete 2\n"), Ok(()));
+    assert_eq!(check("count", "\n  delete 2  \n2\n"), Ok(()));
+    assert_eq!(
+      check("wrong-phrase", "delete 3\n"),
+      Err("expected 'delete 2', found 'delete 3'".to_string())
+    );
+    assert_eq!(
+      check("wrong-count", "delete 2\n3\n"),
+      Err("it confirms 3 items, but 2 are selected".to_string())
+    );
+    assert_eq!(
+      check("no-count", "delete 2\ntwo\n"),
+      Err("expected a count on the second line, found 'two'".to_string())
+    );
+    assert_eq!(check("empty", "\n"), Err("it is empty, expected 'delete 2'".to_string()));
+  }
+
+  #[test]
+  fn confirm_file_counts_the_quick_selection() {
+    let items = ["me/a", "me/b", "me/c"].map(String::from);
+    let select_first = |name: &str, content: &str| {
+      let prompts = Prompts {
+        confirm_file: Some(temp_file(name, content)),
+        select_first: Some(2),
+        ..Default::default()
+      };
+      select(&items, &items, &[], "Select", "repos", &prompts)
+    };
+    let phrase = "I want to remove all repos above";
+    let selected = select_first("quick-count", &format!("{phrase}\n2\n"));
+    assert_eq!(selected.unwrap(), [0, 1]);
+    match select_first("quick-matched-count", &format!("{phrase}\n3\n")) {
+      Err(AppError::Failed(message)) => {
+        assert!(message.ends_with("it confirms 3 items, but 2 are selected"), "{message}")
+      }
+      other => panic!("{other:?}"),
+    }
+  }
+
+  #[test]
+  fn max_failures_stop() {
+    assert!(failure_stop(false, Some(3), (4, 2, 5), "repos").is_none());
+    assert!(failure_stop(false, None, (4, 3, 5), "repos").is_none());
+    match failure_stop(false, Some(3), (4, 3, 5), "repos") {
+      Some(AppError::TooManyFailures(message)) => {
+        assert!(message.contains("Stopped after 3 failures"), "{message}");
+        assert!(message.contains("4 repos deleted, 3 failed, 5 not attempted"), "{message}");
       }
-      p1.set_message(format!("Deleted {}/{}", owner, repo));
-      p1.inc(1);
+      other => panic!("{other:?}"),
+    }
+    assert!(matches!(failure_stop(true, Some(3), (4, 1, 5), "repos"), Some(AppError::Failed(_))));
+  }
+
+  #[test]
+  fn summary_interval_of_deletions_or_duration() {
+    assert!(matches!(parse_summary_interval("1"), Ok(SummaryInterval::Deletions(1))));
+    assert!(matches!(parse_summary_interval("50"), Ok(SummaryInterval::Deletions(50))));
+    let period = |text| match parse_summary_interval(text) {
+      Ok(SummaryInterval::Period(period)) => period,
+      other => panic!("{other:?}"),
     };
-    tx.send(tokio::spawn(handle)).await.unwrap();
+    assert_eq!(period("30s"), Duration::from_secs(30));
+    assert_eq!(period("1m 30s"), Duration::from_secs(90));
+    assert_eq!(period("500ms"), Duration::from_millis(500));
+  }
+
+  #[test]
+  fn summary_interval_rejects_zero_and_garbage() {
+    let error = |text| parse_summary_interval(text).unwrap_err();
+    assert_eq!(error("0"), "the interval must be at least 1 deletion");
+    assert_eq!(error("0s"), "the interval must be longer than 0s");
+    assert!(error("-1").starts_with("expected a number of deletions or a duration"));
+    assert!(error("soon").starts_with("expected a number of deletions or a duration"));
+    assert!(error("").starts_with("expected a number of deletions or a duration"));
+  }
+
+  #[test]
+  fn private_repos_only_with_include_private() {
+    let cli = Cli::try_parse_from(["delete-unused-repo"]).unwrap();
+    assert!(!cli.include_private);
+    assert_eq!(cli.visibility, ["public"]);
+    let cli = Cli::try_parse_from(["delete-unused-repo", "--include-private"]).unwrap();
+    assert!(cli.include_private);
+    let mut visibility = cli.visibility;
+    include_private(&mut visibility);
+    assert_eq!(visibility, ["public", "internal", "private"]);
+    let mut visibility = vec!["private".to_string()];
+    include_private(&mut visibility);
+    assert_eq!(visibility, ["private", "internal"]);
+  }
+
+  #[test]
+  fn cap_refuses_more_items() {
+    assert!(check_cap(0, 3, "repos").is_ok());
+    assert!(check_cap(3, 3, "repos").is_ok());
+    match check_cap(4, 3, "repos") {
+      Err(AppError::Failed(message)) => assert_eq!(
+        message,
+        "4 repos matched, too many to select from with more than --cap-candidates 3, \
+        tighten the filters or raise the cap"
+      ),
+      other => panic!("{other:?}"),
+    }
+  }
+
+  #[test]
+  fn double_confirm_threshold_boundary() {
+    assert!(under_confirm_threshold(2, 3));
+    assert!(!under_confirm_threshold(3, 3));
+    assert!(!under_confirm_threshold(4, 3));
+    assert!(under_confirm_threshold(0, 1));
+    assert!(!under_confirm_threshold(1, 1));
+    assert!(!under_confirm_threshold(0, 0));
   }
-  drop(tx);
-  while let Some(handle) = rx.recv().await {
-    handle.await.unwrap();
+
+  #[test]
+  fn confirm_env_must_match() {
+    // The only test touching the variable, so the steps can't race
+    std::env::set_var(CONFIRM_ENV, "delete 3 repos");
+    assert_eq!(check_confirm_env("delete 3 repos"), Ok(()));
+    std::env::set_var(CONFIRM_ENV, "delete 4 repos");
+    let error = check_confirm_env("delete 3 repos").unwrap_err();
+    assert_eq!(error, "it doesn't hold 'delete 3 repos'");
+    assert!(!error.contains("delete 4"), "{error}");
+    std::env::remove_var(CONFIRM_ENV);
+    assert_eq!(
+      check_confirm_env("delete 3 repos"),
+      Err("it isn't set, expected 'delete 3 repos'".to_string())
+    );
+  }
+
+  #[test]
+  fn confirm_file_missing() {
+    let missing = std::env::temp_dir().join("delete-unused-repo-missing-confirm-file");
+    let error = check_confirm_file(&missing, "delete 2", 2).unwrap_err();
+    assert!(error.starts_with("can't read it: "), "{error}");
+  }
+
+  #[test]
+  fn random_phrase_is_count_and_two_words() {
+    for count in [1, 37] {
+      let phrase = random_phrase(count);
+      let words = phrase
+        .strip_prefix(&format!("delete {count} "))
+        .unwrap_or_else(|| panic!("{phrase}"));
+      let (first, second) = words.split_once('-').unwrap();
+      assert!(PHRASE_WORDS.contains(&first), "{phrase}");
+      assert!(PHRASE_WORDS.contains(&second), "{phrase}");
+    }
+  }
+
+  #[test]
+  fn prechecked_by_full_or_short_name() {
+    let names = ["me/test-a".to_string(), "me/keep".to_string(), "org/test-b".to_string()];
+    assert_eq!(prechecked(&names, None), [true, true, true]);
+    assert_eq!(prechecked(&names, Some("test-*")), [true, false, true]);
+    assert_eq!(prechecked(&names, Some("me/*")), [true, true, false]);
+    assert_eq!(prechecked(&names, Some("nothing")), [false, false, false]);
+  }
+
+  #[test]
+  fn parse_edited_keeps_listed_names() {
+    let names = ["me/a".to_string(), "me/b".to_string(), "me/c".to_string()];
+    let edited = "# Remove the repos to keep\n\nme/c  # 2 stars\n  me/a\nme/c\n";
+    assert_eq!(parse_edited(edited, &names), Ok(vec![0, 2]));
+    assert_eq!(parse_edited("# nothing left\n", &names), Ok(vec![]));
+    assert_eq!(
+      parse_edited("me/a\nme/d\n", &names),
+      Err("line 2: me/d wasn't in the list".to_string())
+    );
+  }
+
+  #[test]
+  fn invert_selection_complements() {
+    assert_eq!(invert_selection(&[1, 3], 5), [0, 2, 4]);
+    assert_eq!(invert_selection(&[], 3), [0, 1, 2]);
+    assert!(invert_selection(&[0, 1, 2], 3).is_empty());
+    assert!(invert_selection(&[], 0).is_empty());
+  }
+
+  #[test]
+  fn quick_selection_first_and_last() {
+    let first = |n| Prompts { select_first: Some(n), ..Default::default() };
+    let last = |n| Prompts { select_last: Some(n), ..Default::default() };
+    assert_eq!(quick_selection(5, &first(2)), Some(vec![0, 1]));
+    assert_eq!(quick_selection(5, &last(2)), Some(vec![3, 4]));
+    assert_eq!(quick_selection(2, &first(5)), Some(vec![0, 1]));
+    assert_eq!(quick_selection(2, &last(5)), Some(vec![0, 1]));
+    assert_eq!(quick_selection(0, &last(1)), Some(vec![]));
+    assert_eq!(quick_selection(5, &Prompts::default()), None);
   }
-  info!("{} {} Delete repos", style("[4/4]").bold().dim(), TRASH);
-  info!("{} Done in {}", SPARKLE, HumanDuration(started.elapsed()));
 }
@@ -0,0 +1,158 @@
+//! Owner lookups kept between runs, whether each `--owner` is a user or an
+//! org and the orgs of `--all-orgs`, so iterating on filters with many
+//! owners doesn't look them all up again every run. One file per token, so
+//! no account reads what another one resolved.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Entries older than this are looked up again.
+pub const TTL_HOURS: i64 = 24;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Entry<T> {
+  at: DateTime<Utc>,
+  value: T,
+}
+
+impl<T> Entry<T> {
+  fn new(value: T) -> Self {
+    Self {
+      at: Utc::now(),
+      value,
+    }
+  }
+
+  fn is_fresh(&self) -> bool {
+    Utc::now() - self.at < Duration::hours(TTL_HOURS)
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct OwnerCache {
+  /// What `deep::account_type` answered per owner login, lowercase
+  account_types: HashMap<String, Entry<Option<String>>>,
+  /// The orgs the authenticated user is a member of
+  orgs: Option<Entry<Vec<String>>>,
+}
+
+impl OwnerCache {
+  pub(crate) fn account_type(&self, login: &str) -> Option<Option<String>> {
+    let entry = self.account_types.get(&login.to_lowercase())?;
+    entry.is_fresh().then(|| entry.value.clone())
+  }
+
+  pub(crate) fn set_account_type(&mut self, login: &str, kind: Option<String>) {
+    self
+      .account_types
+      .insert(login.to_lowercase(), Entry::new(kind));
+  }
+
+  pub(crate) fn orgs(&self) -> Option<Vec<String>> {
+    let entry = self.orgs.as_ref()?;
+    entry.is_fresh().then(|| entry.value.clone())
+  }
+
+  pub(crate) fn set_orgs(&mut self, orgs: Vec<String>) {
+    self.orgs = Some(Entry::new(orgs));
+  }
+}
+
+/// `$XDG_CACHE_HOME/delete-unused-repo`, falling back to `~/.cache`, `None`
+/// without a home directory.
+pub fn default_dir() -> Option<PathBuf> {
+  let cache = match std::env::var_os("XDG_CACHE_HOME") {
+    Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+    _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+  };
+  Some(cache.join("delete-unused-repo"))
+}
+
+/// The cache file in `dir` for the account behind `key`, named by a hash
+/// so the token itself isn't written anywhere.
+pub(crate) fn path(dir: &Path, key: &str) -> PathBuf {
+  let mut hasher = DefaultHasher::new();
+  key.hash(&mut hasher);
+  dir.join(format!("owners-{:016x}.json", hasher.finish()))
+}
+
+/// Reads the cache at `path`, empty if there is none or it can't be read,
+/// it is only a cache.
+pub(crate) fn load(path: &Path) -> OwnerCache {
+  fs::read_to_string(path)
+    .ok()
+    .and_then(|content| serde_json::from_str(&content).ok())
+    .unwrap_or_default()
+}
+
+pub(crate) fn save(path: &Path, cache: &OwnerCache) -> io::Result<()> {
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir)?;
+  }
+  fs::write(path, serde_json::to_vec(cache)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::cell::Cell;
+
+  #[test]
+  fn fresh_entries_are_hits() {
+    let fetched = Cell::new(0);
+    let lookup = |cache: &mut OwnerCache, login: &str| match cache.account_type(login) {
+      Some(kind) => kind,
+      None => {
+        fetched.set(fetched.get() + 1);
+        cache.set_account_type(login, Some("Organization".to_string()));
+        Some("Organization".to_string())
+      }
+    };
+    let mut cache = OwnerCache::default();
+    assert_eq!(lookup(&mut cache, "Org").as_deref(), Some("Organization"));
+    assert_eq!(lookup(&mut cache, "org").as_deref(), Some("Organization"));
+    assert_eq!(fetched.get(), 1);
+    lookup(&mut cache, "other");
+    assert_eq!(fetched.get(), 2);
+
+    let stale = Utc::now() - Duration::hours(TTL_HOURS) - Duration::minutes(1);
+    cache.account_types.get_mut("org").unwrap().at = stale;
+    assert_eq!(cache.account_type("org"), None);
+    lookup(&mut cache, "org");
+    assert_eq!(fetched.get(), 3);
+    assert!(cache.account_type("org").is_some());
+  }
+
+  #[test]
+  fn orgs_expire() {
+    let mut cache = OwnerCache::default();
+    assert_eq!(cache.orgs(), None);
+    cache.set_orgs(vec!["org".to_string()]);
+    assert_eq!(cache.orgs(), Some(vec!["org".to_string()]));
+    cache.orgs.as_mut().unwrap().at = Utc::now() - Duration::hours(TTL_HOURS);
+    assert_eq!(cache.orgs(), None);
+  }
+
+  #[test]
+  fn saved_and_loaded_by_token() {
+    let dir = std::env::temp_dir().join(format!("owner-cache-{}", std::process::id()));
+    let file = path(&dir, "ghp_test");
+    assert_ne!(file, path(&dir, "ghp_other"));
+    assert!(!file.to_string_lossy().contains("ghp_test"));
+    assert_eq!(load(&file).account_type("me"), None);
+
+    let mut cache = OwnerCache::default();
+    cache.set_account_type("me", Some("User".to_string()));
+    save(&file, &cache).unwrap();
+    let loaded = load(&file);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(loaded.account_type("ME"), Some(Some("User".to_string())));
+  }
+}
@@ -0,0 +1,145 @@
+//! GitHub Packages of the authenticated user and their orgs.
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use octocrab::Octocrab;
+use serde::Deserialize;
+
+/// Package ecosystems understood by the packages API.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageType {
+  Npm,
+  Maven,
+  Rubygems,
+  Docker,
+  Nuget,
+  Container,
+}
+
+impl PackageType {
+  fn as_str(self) -> &'static str {
+    match self {
+      PackageType::Npm => "npm",
+      PackageType::Maven => "maven",
+      PackageType::Rubygems => "rubygems",
+      PackageType::Docker => "docker",
+      PackageType::Nuget => "nuget",
+      PackageType::Container => "container",
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PackageRepo {
+  pub full_name: String,
+}
+
+/// A package listed by [`crate::Cleaner::list_packages`].
+#[derive(Deserialize, Debug, Clone)]
+pub struct Package {
+  pub id: u64,
+  pub name: String,
+  pub package_type: String,
+  pub version_count: Option<u64>,
+  pub updated_at: DateTime<Utc>,
+  /// The linked repo, `None` once it is deleted
+  pub repository: Option<PackageRepo>,
+  /// The org owning the package, `None` for the authenticated user
+  #[serde(skip)]
+  pub org: Option<String>,
+}
+
+impl Package {
+  /// `type/name`, prefixed with the org for org packages.
+  pub fn full_name(&self) -> String {
+    match &self.org {
+      Some(org) => format!("{org}:{}/{}", self.package_type, self.name),
+      None => format!("{}/{}", self.package_type, self.name),
+    }
+  }
+
+  fn route(&self) -> String {
+    // Container names may contain slashes, which must stay in one segment
+    let name = self.name.replace('/', "%2F");
+    match &self.org {
+      Some(org) => format!("orgs/{org}/packages/{}/{name}", self.package_type),
+      None => format!("user/packages/{}/{name}", self.package_type),
+    }
+  }
+}
+
+/// Lists every `package_type` package of `org`, or of the authenticated user.
+pub(crate) async fn list(
+  gh: &Octocrab,
+  org: Option<&str>,
+  package_type: PackageType,
+) -> octocrab::Result<Vec<Package>> {
+  let route = match org {
+    Some(org) => format!("orgs/{org}/packages"),
+    None => "user/packages".to_string(),
+  };
+  let packages = list_at(gh, &route, package_type).await?;
+  Ok(
+    packages
+      .into_iter()
+      .map(|mut p| {
+        p.org = org.map(str::to_string);
+        p
+      })
+      .collect(),
+  )
+}
+
+/// Full names of the repos linked to a `package_type` package of the user
+/// or org `owner`, including private ones the token can see.
+pub(crate) async fn linked_repos(
+  gh: &Octocrab,
+  owner: &str,
+  is_org: bool,
+  package_type: PackageType,
+) -> octocrab::Result<Vec<String>> {
+  let route = match is_org {
+    true => format!("orgs/{owner}/packages"),
+    false => format!("users/{owner}/packages"),
+  };
+  let packages = list_at(gh, &route, package_type).await?;
+  Ok(
+    packages
+      .into_iter()
+      .filter_map(|p| p.repository.map(|r| r.full_name))
+      .collect(),
+  )
+}
+
+async fn list_at(
+  gh: &Octocrab,
+  route: &str,
+  package_type: PackageType,
+) -> octocrab::Result<Vec<Package>> {
+  let mut packages = vec![];
+  for page in 1u32.. {
+    let items: Vec<Package> = crate::api_trace::get_json(
+      gh,
+      route,
+      Some(&[
+        ("package_type", package_type.as_str()),
+        ("per_page", "100"),
+        ("page", &page.to_string()),
+      ]),
+    )
+    .await?;
+    let len = items.len();
+    packages.extend(items);
+    if len < 100 {
+      break;
+    }
+  }
+  Ok(packages)
+}
+
+pub(crate) async fn delete(
+  gh: &Octocrab,
+  package: &Package,
+) -> (crate::Reply, octocrab::Result<()>) {
+  crate::send_delete(gh, package.route()).await
+}
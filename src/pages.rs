@@ -0,0 +1,11 @@
+use octocrab::Octocrab;
+
+/// Deletes the GitHub Pages site of `owner/repo`, unpublishing it. The
+/// branch or workflow it was built from is kept.
+pub(crate) async fn delete(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+) -> (crate::Reply, octocrab::Result<()>) {
+  crate::send_delete(gh, format!("repos/{owner}/{repo}/pages")).await
+}
@@ -0,0 +1,51 @@
+//! Profile pins, only exposed by the GraphQL API.
+
+use std::collections::HashSet;
+
+use octocrab::Octocrab;
+use serde::Deserialize;
+
+/// A profile holds at most 6 pinned items.
+const QUERY: &str = "query { viewer { pinnedItems(first: 6, types: REPOSITORY) { nodes { ... on Repository { id } } } } }";
+
+#[derive(Deserialize, Debug)]
+struct Response {
+  data: Data,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Data {
+  viewer: Viewer,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Viewer {
+  pinned_items: Nodes,
+}
+
+#[derive(Deserialize, Debug)]
+struct Nodes {
+  nodes: Vec<Node>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Node {
+  id: String,
+}
+
+/// Node ids of the repos pinned to the authenticated user's profile.
+pub(crate) async fn pinned_repos(gh: &Octocrab) -> octocrab::Result<HashSet<String>> {
+  let response: Response = gh.graphql(QUERY).await?;
+  Ok(
+    response
+      .data
+      .viewer
+      .pinned_items
+      .nodes
+      .into_iter()
+      .map(|n| n.id)
+      .collect(),
+  )
+}
@@ -0,0 +1,655 @@
+//! The steps of a run that ask nothing: reading the given files, logging
+//! in and finding the candidates. The binary runs them between its prompts
+//! and maps their [`AppError`]s to exit codes.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use log::{error, info, warn};
+use octocrab::models::Repository;
+use serde_json::Value;
+
+use crate::app_auth::AppCredentials;
+use crate::attempt_log::AttemptLog;
+use crate::ignore::IgnoreFile;
+use crate::rename::RenameManifest;
+use crate::{report, Candidate, Cleaner, Config, Outcome, Target};
+
+/// Why a run ended early, reported and mapped to an exit code by `main`.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+  /// Bad options or files, or a failed GitHub request, exits with 1
+  #[error("{0}")]
+  Failed(String),
+  /// The user didn't confirm the deletion, exits with 1
+  #[error("Cancelled")]
+  Cancelled,
+  /// Ctrl-C during the deletion, with a partial summary, exits with 130
+  #[error("{0}")]
+  Interrupted(String),
+  /// `--max-runtime` ran out, with a partial summary, exits with 124
+  #[error("{0}")]
+  OutOfTime(String),
+  /// `--max-failures` deletions failed, with a partial summary, exits with 3
+  #[error("{0}")]
+  TooManyFailures(String),
+}
+
+impl AppError {
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      AppError::Failed(_) | AppError::Cancelled => 1,
+      AppError::Interrupted(_) => 130,
+      AppError::OutOfTime(_) => 124,
+      AppError::TooManyFailures(_) => 3,
+    }
+  }
+
+  /// Logs the error at the level it deserves, a cancel isn't one.
+  pub fn report(&self) {
+    match self {
+      AppError::Failed(_) | AppError::TooManyFailures(_) => error!("{self}"),
+      AppError::Cancelled => info!("{self}"),
+      AppError::Interrupted(_) | AppError::OutOfTime(_) => warn!("{self}"),
+    }
+  }
+}
+
+/// Entries in `content`, like owner logins, one per line. Blank lines and
+/// everything after a `#` are ignored.
+pub fn parse_lines(content: &str) -> Vec<String> {
+  content
+    .lines()
+    .map(|line| line.split('#').next().unwrap().trim())
+    .filter(|line| !line.is_empty())
+    .map(str::to_string)
+    .collect()
+}
+
+/// Repo names in `content`, one `owner/name` per line like [`parse_lines`]
+/// reads them, failing at the first bad one with its line number.
+pub fn parse_full_names(content: &str) -> Result<Vec<String>, String> {
+  let mut names = vec![];
+  for (number, line) in content.lines().enumerate() {
+    let line = line.split('#').next().unwrap().trim();
+    if line.is_empty() {
+      continue;
+    }
+    let name = crate::parse_full_name(line).map_err(|e| format!("line {}: {e}", number + 1))?;
+    names.push(name);
+  }
+  Ok(names)
+}
+
+/// The token in the `--token-file` at `path`, trimmed.
+pub fn read_token_file(path: &Path) -> Result<String, AppError> {
+  match std::fs::read_to_string(path) {
+    Ok(token) if !token.trim().is_empty() => Ok(token.trim().to_string()),
+    Ok(_) => Err(AppError::Failed(format!(
+      "No GitHub token in {}, the file is empty",
+      path.display()
+    ))),
+    Err(e) => Err(AppError::Failed(format!(
+      "Failed to read the GitHub token from {}: {e}",
+      path.display()
+    ))),
+  }
+}
+
+/// The GitHub App credentials with the private key at `key`.
+pub fn read_app_key(
+  app_id: u64,
+  key: &Path,
+  installation_id: u64,
+) -> Result<AppCredentials, AppError> {
+  let pem = std::fs::read(key).map_err(|e| {
+    AppError::Failed(format!(
+      "Failed to read the GitHub App key {}: {e}",
+      key.display()
+    ))
+  })?;
+  AppCredentials::new(app_id, &pem, installation_id)
+    .map_err(|e| AppError::Failed(format!("Invalid GitHub App key {}: {e}", key.display())))
+}
+
+/// The owners listed in the `--owner-file` at `path`.
+pub fn read_owner_file(path: &Path) -> Result<Vec<String>, AppError> {
+  let content = std::fs::read_to_string(path).map_err(|e| {
+    AppError::Failed(format!(
+      "Failed to read owners from {}: {e}",
+      path.display()
+    ))
+  })?;
+  let owners = parse_lines(&content);
+  info!("Loaded {} owners from {}", owners.len(), path.display());
+  Ok(owners)
+}
+
+/// Drops repeated `owners`, which GitHub matches ignoring case unless
+/// `case_sensitive`, keeping the first.
+pub fn dedupe_owners(owners: &mut Vec<String>, case_sensitive: bool) {
+  let mut seen = HashSet::new();
+  owners.retain(|o| {
+    seen.insert(if case_sensitive {
+      o.clone()
+    } else {
+      o.to_lowercase()
+    })
+  });
+}
+
+/// The `--owner`s followed by those of `--owner-file` and [`crate::ME`]
+/// for `--mine`, each once, see [`dedupe_owners`]. `None`, every owner, if
+/// none of them is given.
+pub fn merge_owners(
+  owner: Option<Vec<String>>,
+  from_file: Option<Vec<String>>,
+  mine: bool,
+  case_sensitive: bool,
+) -> Option<Vec<String>> {
+  let mine = mine.then(|| vec![crate::ME.to_string()]);
+  let mut owners = [owner, from_file, mine]
+    .into_iter()
+    .flatten()
+    .reduce(|mut owners, more| {
+      owners.extend(more);
+      owners
+    })?;
+  dedupe_owners(&mut owners, case_sensitive);
+  Some(owners)
+}
+
+/// The owners of `full_names`, each once, in the order they first appear.
+pub fn owners_of(full_names: &[String]) -> Vec<String> {
+  let mut owners: Vec<String> = vec![];
+  for full_name in full_names {
+    let owner = full_name.split('/').next().unwrap();
+    if !owners.iter().any(|o| o.eq_ignore_ascii_case(owner)) {
+      owners.push(owner.to_string());
+    }
+  }
+  owners
+}
+
+/// The repos to keep in the `--reconcile` file at `path`, which must list
+/// some as an empty one would delete everything in scope.
+pub fn read_keep_file(path: &Path) -> Result<Vec<String>, AppError> {
+  let content = std::fs::read_to_string(path).map_err(|e| {
+    AppError::Failed(format!(
+      "Failed to read the keep file {}: {e}",
+      path.display()
+    ))
+  })?;
+  let keep = parse_full_names(&content)
+    .map_err(|e| AppError::Failed(format!("Bad keep file {}: {e}", path.display())))?;
+  if keep.is_empty() {
+    return Err(AppError::Failed(format!(
+      "The keep file {} lists no repos, --reconcile would delete everything in scope",
+      path.display()
+    )));
+  }
+  Ok(keep)
+}
+
+/// A `--output json` report of an earlier run, whose failed deletions are
+/// retried and then recorded back to it by `--retry-failed`.
+pub struct RetryRecord {
+  path: PathBuf,
+  report: Value,
+  /// The failed deletions to retry
+  pub failed: Vec<String>,
+}
+
+impl RetryRecord {
+  pub fn read(path: &Path, target: Target) -> Result<Self, AppError> {
+    let report: Value = std::fs::read_to_string(path)
+      .map_err(|e| e.to_string())
+      .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+      .map_err(|e| {
+        AppError::Failed(format!("Failed to read the report {}: {e}", path.display()))
+      })?;
+    let failed = report::failed_names(&report, target)
+      .map_err(|e| AppError::Failed(format!("Can't retry from {}: {e}", path.display())))?;
+    Ok(RetryRecord {
+      path: path.to_path_buf(),
+      report,
+      failed,
+    })
+  }
+
+  /// Records the `outcomes` of the retries to the report, only warning if
+  /// it can't, as the deletions are done anyway.
+  pub fn update(mut self, outcomes: &[Outcome]) {
+    report::update_outcomes(&mut self.report, outcomes);
+    let json = serde_json::to_string(&self.report).unwrap();
+    match std::fs::write(&self.path, json + "\n") {
+      Ok(()) => info!("Recorded the retries to {}", self.path.display()),
+      Err(e) => warn!(
+        "Failed to record the retries to {}: {e}",
+        self.path.display()
+      ),
+    }
+  }
+}
+
+/// Checks the proxy, given or from the environment, and the CA bundle, and
+/// hands them to the HTTP clients through the environment they read, as
+/// octocrab builds its own. Must run before the first client is built.
+pub fn configure_network(proxy: Option<&str>, ca_cert: Option<&Path>) -> Result<(), AppError> {
+  let check = |url: &str, from: &str| {
+    // Like reqwest, take a URL without scheme as http
+    let parsed = match url.contains("://") {
+      true => reqwest::Url::parse(url),
+      false => reqwest::Url::parse(&format!("http://{url}")),
+    };
+    match parsed {
+      Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => Ok(()),
+      Ok(url) => Err(AppError::Failed(format!(
+        "Bad proxy URL in {from}: expected http://HOST[:PORT] or https://, not {}://",
+        url.scheme()
+      ))),
+      Err(e) => Err(AppError::Failed(format!(
+        "Bad proxy URL in {from}: {e}, expected http://HOST[:PORT]"
+      ))),
+    }
+  };
+  match proxy {
+    Some(url) => {
+      check(url, "--proxy")?;
+      for var in ["HTTPS_PROXY", "HTTP_PROXY"] {
+        std::env::set_var(var, url);
+      }
+    }
+    None => {
+      // Malformed ones would be ignored silently, connecting directly
+      for var in ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy"] {
+        if let Ok(url) = std::env::var(var) {
+          check(&url, var)?;
+        }
+      }
+    }
+  }
+  if let Some(path) = ca_cert {
+    let pem = std::fs::read(path).map_err(|e| {
+      AppError::Failed(format!(
+        "Failed to read the CA certificates {}: {e}",
+        path.display()
+      ))
+    })?;
+    reqwest::Certificate::from_pem(&pem)
+      .map_err(|e| AppError::Failed(format!("No PEM CA certificate in {}: {e}", path.display())))?;
+    std::env::set_var("SSL_CERT_FILE", path);
+  }
+  Ok(())
+}
+
+/// Reads the ignore files at `paths`, later ones winning, `None` if none
+/// has a pattern. Missing files are skipped unless `required`.
+pub fn load_ignore(paths: &[PathBuf], required: bool) -> Result<Option<IgnoreFile>, AppError> {
+  let mut ignore = IgnoreFile::default();
+  for path in paths {
+    let content = match std::fs::read_to_string(path) {
+      Ok(content) => content,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => continue,
+      Err(e) => {
+        return Err(AppError::Failed(format!(
+          "Failed to read the ignore file {}: {e}",
+          path.display()
+        )));
+      }
+    };
+    let file = IgnoreFile::parse(&content);
+    info!(
+      "Loaded {} ignore patterns from {}",
+      file.len(),
+      path.display()
+    );
+    ignore.extend(file);
+  }
+  Ok((!ignore.is_empty()).then_some(ignore))
+}
+
+/// `--strict-visibility`, fails on the first repo with an unknown visibility.
+pub fn check_visibility(strict: bool, repos: &[Repository]) -> Result<(), AppError> {
+  if !strict {
+    return Ok(());
+  }
+  crate::check_visibility(repos).map_err(|e| {
+    AppError::Failed(format!(
+      "Visibility check failed, GitHub may have changed: {e}"
+    ))
+  })
+}
+
+/// Logs in with the `app` installation if given, with the token of
+/// `config` otherwise.
+pub async fn login(config: Config, app: Option<AppCredentials>) -> Result<Cleaner, AppError> {
+  match app {
+    Some(app) => {
+      let installation = app.installation_id;
+      Cleaner::new_app(config, app).await.map_err(|e| {
+        AppError::Failed(format!(
+          "Failed to login GitHub as installation {installation} of the App: {e}"
+        ))
+      })
+    }
+    None => Cleaner::new(config)
+      .map_err(|e| AppError::Failed(format!("Failed to login GitHub via personal token: {e}"))),
+  }
+}
+
+/// Creates the directories the run writes to, that of `output_file` and
+/// the `backup_dir`, before anything is deleted.
+pub fn create_dirs(output_file: Option<&Path>, backup_dir: Option<&Path>) -> Result<(), AppError> {
+  if let Some(dir) = output_file.and_then(Path::parent) {
+    std::fs::create_dir_all(dir).map_err(|e| {
+      AppError::Failed(format!(
+        "Failed to create the directory of --output-file {}: {e}",
+        dir.display()
+      ))
+    })?;
+  }
+  if let Some(dir) = backup_dir {
+    std::fs::create_dir_all(dir).map_err(|e| {
+      AppError::Failed(format!(
+        "Failed to create the backup directory {}: {e}",
+        dir.display()
+      ))
+    })?;
+  }
+  Ok(())
+}
+
+/// `cleaner` recording its attempts to `retries_log` and its renames to
+/// `rename_manifest`, those given.
+pub fn with_logs(
+  mut cleaner: Cleaner,
+  retries_log: Option<&Path>,
+  rename_manifest: Option<&Path>,
+) -> Result<Cleaner, AppError> {
+  let failed =
+    |path: &Path, e| AppError::Failed(format!("Failed to create {}: {e}", path.display()));
+  if let Some(path) = retries_log {
+    let log = AttemptLog::create(path).map_err(|e| failed(path, e))?;
+    cleaner = cleaner.with_attempt_log(log);
+  }
+  if let Some(path) = rename_manifest {
+    let manifest = RenameManifest::create(path).map_err(|e| failed(path, e))?;
+    cleaner = cleaner.with_rename_manifest(manifest);
+  }
+  Ok(cleaner)
+}
+
+/// Where [`find_candidates`] takes the repos from and what it keeps.
+#[derive(Debug, Clone, Default)]
+pub struct Sources {
+  /// `--from-file`, a dump of an earlier run, instead of listing
+  pub from_file: Option<PathBuf>,
+  /// `--repo`, instead of listing unless `repo_and_filter`
+  pub repos: Vec<String>,
+  /// `--repo-and-filter`, adds `repos` to the listed matches
+  pub repo_and_filter: bool,
+  /// `--reconcile`, the listed repos not in it are the candidates
+  pub keep: Option<Vec<String>>,
+  pub strict_visibility: bool,
+  /// `--preflight-count`, asks after the filters on the listing
+  pub preflight_count: bool,
+  /// Keep the candidates that can't be deleted, to report them
+  pub undeletable: bool,
+}
+
+/// The step [`find_candidates`] starts, for the binary to show.
+#[derive(Debug, Clone, Copy)]
+pub enum Step<'a> {
+  /// Reading the repos of `--from-file`
+  Load(&'a Path),
+  /// Getting this many `--repo`s
+  Get(usize),
+  Search,
+  Reconcile,
+  Filter,
+}
+
+/// Finds the candidates from the `sources`, telling each [`Step`] to
+/// `step`. With `--preflight-count`, `confirm_preflight` gets how many the
+/// filters on the listing keep and may stop the run.
+pub async fn find_candidates(
+  cleaner: &Cleaner,
+  sources: &Sources,
+  mut step: impl FnMut(Step),
+  confirm_preflight: impl FnOnce(usize) -> Result<(), AppError>,
+) -> Result<Vec<Candidate>, AppError> {
+  let strict = sources.strict_visibility;
+  let candidates = if let Some(path) = &sources.from_file {
+    step(Step::Load(path));
+    let repos = crate::dump::read(path).map_err(|e| {
+      AppError::Failed(format!("Failed to read repos from {}: {e}", path.display()))
+    })?;
+    check_visibility(strict, &repos)?;
+    cleaner.load(repos).await
+  } else if !sources.repos.is_empty() && !sources.repo_and_filter {
+    step(Step::Get(sources.repos.len()));
+    let repos = cleaner
+      .get_repos(&sources.repos)
+      .await
+      .map_err(AppError::Failed)?;
+    check_visibility(strict, &repos)?;
+    cleaner.load(repos).await
+  } else {
+    step(Step::Search);
+    let repos = cleaner
+      .list_candidates()
+      .await
+      .map_err(|e| AppError::Failed(format!("Failed to get GitHub repos of you: {e}")))?;
+    check_visibility(strict, &repos)?;
+
+    if let Some(keep) = &sources.keep {
+      step(Step::Reconcile);
+      cleaner.reconcile(repos, keep).await
+    } else {
+      step(Step::Filter);
+      let mut candidates = if sources.preflight_count {
+        let candidates = cleaner.shallow_filter(repos);
+        confirm_preflight(candidates.len())?;
+        cleaner.finish_filter(candidates).await
+      } else {
+        cleaner.filter(repos).await
+      };
+      if sources.repo_and_filter {
+        let given: Vec<String> = sources
+          .repos
+          .iter()
+          .filter(|name| {
+            !candidates
+              .iter()
+              .any(|c| c.full_name().eq_ignore_ascii_case(name))
+          })
+          .cloned()
+          .collect();
+        let repos = cleaner.get_repos(&given).await.map_err(AppError::Failed)?;
+        check_visibility(strict, &repos)?;
+        info!(
+          "Added {} given repos to the {} matched",
+          repos.len(),
+          candidates.len()
+        );
+        candidates.extend(cleaner.load(repos).await);
+      }
+      candidates
+    }
+  };
+  Ok(match sources.undeletable {
+    true => candidates,
+    false => cleaner.deletable(candidates).await,
+  })
+}
+
+/// The full names of the repos in the `--diff-against` dump at `path`.
+pub fn read_previous(path: &Path) -> Result<HashSet<String>, AppError> {
+  let repos = crate::dump::read(path)
+    .map_err(|e| AppError::Failed(format!("Failed to read repos from {}: {e}", path.display())))?;
+  Ok(
+    repos
+      .into_iter()
+      .map(|r| Candidate::from(r).full_name())
+      .collect(),
+  )
+}
+
+/// The indices of `candidates` to offer as one item each, or with
+/// `by_name` those of same-named repos together, in the order their first
+/// repo is listed.
+pub fn group_by_name(candidates: &[Candidate], by_name: bool) -> Vec<Vec<usize>> {
+  if !by_name {
+    return (0..candidates.len()).map(|i| vec![i]).collect();
+  }
+  let mut groups: Vec<(&str, Vec<usize>)> = vec![];
+  for (i, c) in candidates.iter().enumerate() {
+    match groups.iter_mut().find(|(name, _)| *name == c.repo.name) {
+      Some((_, group)) => group.push(i),
+      None => groups.push((&c.repo.name, vec![i])),
+    }
+  }
+  groups.into_iter().map(|(_, group)| group).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::candidate;
+
+  #[test]
+  fn parse_lines_skips_comments() {
+    let content = "# owners\nme\n\n  org-a  # work\n#org-b\norg-c\n";
+    assert_eq!(parse_lines(content), ["me", "org-a", "org-c"]);
+  }
+
+  #[test]
+  fn parse_full_names_reports_the_line() {
+    assert_eq!(
+      parse_full_names("me/a\n# me/b\n\norg/c # kept\n"),
+      Ok(vec!["me/a".into(), "org/c".into()])
+    );
+    let error = parse_full_names("me/a\n\nnot-a-repo\n").unwrap_err();
+    assert!(error.starts_with("line 3: "), "{error}");
+  }
+
+  #[test]
+  fn dedupe_owners_ignores_case() {
+    let mut owners = vec!["Me".to_string(), "org".into(), "me".into(), "ORG".into()];
+    dedupe_owners(&mut owners, false);
+    assert_eq!(owners, ["Me", "org"]);
+    let mut owners = vec!["Me".to_string(), "me".into(), "Me".into()];
+    dedupe_owners(&mut owners, true);
+    assert_eq!(owners, ["Me", "me"]);
+  }
+
+  #[test]
+  fn merge_owners_in_order() {
+    let list = |owners: &[&str]| Some(owners.iter().map(|o| o.to_string()).collect());
+    assert_eq!(merge_owners(None, None, false, false), None);
+    assert_eq!(
+      merge_owners(
+        list(&["org", "Me"]),
+        list(&["me", "other", "org"]),
+        false,
+        false
+      ),
+      list(&["org", "Me", "other"])
+    );
+    assert_eq!(
+      merge_owners(list(&["Me"]), list(&["me"]), false, true),
+      list(&["Me", "me"])
+    );
+    assert_eq!(
+      merge_owners(None, list(&["org"]), true, false),
+      list(&["org", "@me"])
+    );
+    assert_eq!(
+      merge_owners(list(&["@me"]), None, true, false),
+      list(&["@me"])
+    );
+    assert_eq!(merge_owners(None, list(&[]), false, false), list(&[]));
+  }
+
+  #[test]
+  fn owners_of_full_names() {
+    let names = ["me/a", "Org/b", "me/c", "org/d"].map(String::from);
+    assert_eq!(owners_of(&names), ["me", "Org"]);
+    assert!(owners_of(&[]).is_empty());
+  }
+
+  #[test]
+  fn unknown_visibility_fails_only_strict() {
+    let repos = [
+      candidate("me/a", serde_json::json!({ "visibility": "internal" })).repo,
+      candidate("me/b", serde_json::json!({})).repo,
+      candidate("me/c", serde_json::json!({ "visibility": "secret" })).repo,
+    ];
+    assert!(check_visibility(false, &repos).is_ok());
+    assert!(check_visibility(true, &repos[..2]).is_ok());
+    let error = check_visibility(true, &repos).unwrap_err().to_string();
+    assert!(
+      error.contains("me/c has the unknown visibility 'secret'"),
+      "{error}"
+    );
+  }
+
+  #[test]
+  fn retry_record_reads_and_updates_failures() {
+    let path = std::env::temp_dir().join(format!("retry-{}.json", std::process::id()));
+    let outcome = |name: &str, errors: &[&str]| serde_json::json!({ "full_name": name, "deleted": 0, "skipped": 0, "errors": errors });
+    let report = serde_json::json!({
+      "target": "repos",
+      "matched": ["me/a", "me/b", "me/c"],
+      "outcomes": [
+        outcome("me/a", &["timed out after 1m"]),
+        outcome("me/b", &[]),
+        outcome("me/c", &["Not Found"]),
+      ],
+      "interrupted": false,
+    });
+    std::fs::write(&path, report.to_string()).unwrap();
+
+    let error = RetryRecord::read(&path, Target::Releases).err().unwrap();
+    assert!(
+      error.to_string().contains("not a report of \"releases\""),
+      "{error}"
+    );
+    let record = RetryRecord::read(&path, Target::Repos).unwrap();
+    assert_eq!(record.failed, ["me/a", "me/c"]);
+
+    record.update(&[Outcome {
+      full_name: "me/a".to_string(),
+      deleted: 1,
+      skipped: 0,
+      errors: vec![],
+    }]);
+    let record = RetryRecord::read(&path, Target::Repos).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(record.failed, ["me/c"]);
+    assert_eq!(record.report["outcomes"][0]["deleted"], 1);
+    assert_eq!(record.report["outcomes"][1], outcome("me/b", &[]));
+  }
+
+  #[test]
+  fn group_by_name_keeps_listing_order() {
+    let candidates = [
+      candidate("me/a", serde_json::json!({})),
+      candidate("org/b", serde_json::json!({})),
+      candidate("org/a", serde_json::json!({})),
+      candidate("other/a", serde_json::json!({})),
+    ];
+    assert_eq!(group_by_name(&candidates, false), [[0], [1], [2], [3]]);
+    assert_eq!(group_by_name(&candidates, true), [vec![0, 2, 3], vec![1]]);
+  }
+
+  #[test]
+  fn exit_codes() {
+    assert_eq!(AppError::Failed("bad".into()).exit_code(), 1);
+    assert_eq!(AppError::Cancelled.exit_code(), 1);
+    assert_eq!(AppError::Interrupted("stopped".into()).exit_code(), 130);
+    assert_eq!(AppError::OutOfTime("late".into()).exit_code(), 124);
+    assert_eq!(AppError::TooManyFailures("failed".into()).exit_code(), 3);
+  }
+}
@@ -0,0 +1,81 @@
+//! API budget accounting for `--report-only`, so a report-only run shows
+//! what the same real run would cost. Asking `rate_limit` is free, so the
+//! calls made are the difference of two snapshots of it.
+
+use chrono::{DateTime, TimeZone, Utc};
+use octocrab::Octocrab;
+use serde::{Deserialize, Serialize};
+
+/// One resource of `GET /rate_limit`.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Budget {
+  pub limit: u64,
+  pub used: u64,
+  pub remaining: u64,
+  /// Unix time the window resets at
+  pub reset: i64,
+}
+
+impl Budget {
+  /// Calls made since `start`. After the window reset in between only the
+  /// calls of the new window are known, so this is a lower bound then.
+  fn used_since(&self, start: &Budget) -> u64 {
+    if self.reset == start.reset {
+      self.used.saturating_sub(start.used)
+    } else {
+      self.used
+    }
+  }
+}
+
+/// The budgets the cleaner spends, the REST one and the GraphQL one used
+/// by `--exclude-pinned`.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimits {
+  pub core: Budget,
+  pub graphql: Budget,
+}
+
+#[derive(Deserialize)]
+struct Response {
+  resources: Resources,
+}
+
+#[derive(Deserialize)]
+struct Resources {
+  core: Budget,
+  graphql: Budget,
+}
+
+pub(crate) async fn get(gh: &Octocrab) -> octocrab::Result<RateLimits> {
+  let response: Response = crate::api_trace::get_json(gh, "rate_limit", None::<&()>).await?;
+  Ok(RateLimits {
+    core: response.resources.core,
+    graphql: response.resources.graphql,
+  })
+}
+
+/// API calls made between two [`RateLimits`] snapshots and what is left.
+/// Other clients using the same token in the meantime are counted too.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct ApiUsage {
+  /// REST calls
+  pub calls: u64,
+  pub graphql_calls: u64,
+  /// REST calls left in the current window
+  pub remaining: u64,
+  pub limit: u64,
+  pub reset_at: DateTime<Utc>,
+}
+
+impl ApiUsage {
+  pub fn between(start: &RateLimits, end: &RateLimits) -> Self {
+    Self {
+      calls: end.core.used_since(&start.core),
+      graphql_calls: end.graphql.used_since(&start.graphql),
+      remaining: end.core.remaining,
+      limit: end.core.limit,
+      reset_at: Utc.timestamp(end.core.reset, 0),
+    }
+  }
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use octocrab::Octocrab;
+use serde::Deserialize;
+
+use crate::ReleaseKind;
+
+#[derive(Deserialize, Debug)]
+struct Release {
+  id: u64,
+  draft: bool,
+  prerelease: bool,
+  created_at: Option<DateTime<Utc>>,
+}
+
+impl ReleaseKind {
+  fn matches(self, release: &Release) -> bool {
+    match self {
+      ReleaseKind::All => true,
+      ReleaseKind::Draft => release.draft,
+      ReleaseKind::Prerelease => release.prerelease,
+    }
+  }
+}
+
+/// Lists ids of every `kind` release of `owner/repo` created before `before`.
+pub(crate) async fn list_old(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+  before: DateTime<Utc>,
+  kind: ReleaseKind,
+) -> octocrab::Result<Vec<u64>> {
+  let mut ids = vec![];
+  for page in 1u32.. {
+    let releases: Vec<Release> = crate::api_trace::get_json(
+      gh,
+      format!("repos/{owner}/{repo}/releases"),
+      Some(&[("per_page", "100"), ("page", &page.to_string())]),
+    )
+    .await?;
+    let len = releases.len();
+    ids.extend(
+      releases
+        .into_iter()
+        .filter(|r| r.created_at.is_some_and(|created_at| created_at < before))
+        .filter(|r| kind.matches(r))
+        .map(|r| r.id),
+    );
+    if len < 100 {
+      break;
+    }
+  }
+  Ok(ids)
+}
+
+pub(crate) async fn delete(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+  id: u64,
+) -> (crate::Reply, octocrab::Result<()>) {
+  crate::send_delete(gh, format!("repos/{owner}/{repo}/releases/{id}")).await
+}
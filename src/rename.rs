@@ -0,0 +1,174 @@
+//! Renaming repos right before deleting them, see
+//! [`crate::Cleaner::with_rename_manifest`]. The original name is free at
+//! once, and a repo restored from GitHub's settings comes back under its
+//! `DELETE-` name, which the manifest maps back to the original.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use log::warn;
+use octocrab::Octocrab;
+use serde::Serialize;
+use serde_json::json;
+
+/// Prefix of the new names.
+pub const PREFIX: &str = "DELETE-";
+
+/// Gives up finding a free name after this many taken ones.
+const MAX_TRIES: u32 = 100;
+
+/// The `n`th name tried for `name`, counting from 0: `DELETE-name`, then
+/// `DELETE-name-2`, `DELETE-name-3` and so on.
+pub fn new_name(name: &str, n: u32) -> String {
+  match n {
+    0 => format!("{PREFIX}{name}"),
+    n => format!("{PREFIX}{name}-{}", n + 1),
+  }
+}
+
+/// Renames `owner/repo` to the first [`new_name`] not taken by another repo
+/// of `owner`, returning it. With `retry_taken`, a `422` saying the name is
+/// taken after all, by a repo the token can't see or one created meanwhile,
+/// moves on to the next name instead of failing.
+pub(crate) async fn rename(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+  retry_taken: bool,
+) -> octocrab::Result<String> {
+  let mut n = 0;
+  while n + 1 < MAX_TRIES {
+    let taken: Option<serde_json::Value> =
+      crate::deep::get_optional(gh, format!("repos/{owner}/{}", new_name(repo, n))).await?;
+    if taken.is_none() {
+      break;
+    }
+    n += 1;
+  }
+  loop {
+    let name = new_name(repo, n);
+    let route = format!("repos/{owner}/{repo}");
+    let response = crate::api_trace::patch(gh, route, &json!({ "name": name })).await?;
+    let status = response.status();
+    match octocrab::map_github_error(response).await {
+      Ok(_) => return Ok(name),
+      Err(octocrab::Error::GitHub { source, .. })
+        if retry_taken
+          && status == reqwest::StatusCode::UNPROCESSABLE_ENTITY
+          && name_taken(&source)
+          && n + 1 < MAX_TRIES =>
+      {
+        warn!("{owner}/{name} is taken after all, renaming {owner}/{repo} to the next name");
+        n += 1;
+      }
+      Err(e) => return Err(e),
+    }
+  }
+}
+
+/// Whether a `422` rejected a new name because a repo of the owner has it.
+fn name_taken(error: &octocrab::GitHubError) -> bool {
+  let taken = |message: &str| message.contains("already exists");
+  taken(&error.message)
+    || error
+      .errors
+      .iter()
+      .flatten()
+      .filter_map(|e| e.get("message").and_then(serde_json::Value::as_str))
+      .any(taken)
+}
+
+/// A single rename, as a line of the manifest.
+#[derive(Serialize, Debug)]
+pub struct Renamed<'a> {
+  pub owner: &'a str,
+  pub from: &'a str,
+  pub to: &'a str,
+}
+
+/// A JSON lines file with a [`Renamed`] per line, written through at once
+/// so it survives an interrupted run.
+pub struct RenameManifest(Mutex<File>);
+
+impl RenameManifest {
+  /// Opens the manifest at `path` for appending, creating it if needed, so
+  /// a later run never loses the renames of an earlier one.
+  pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(Self(Mutex::new(file)))
+  }
+
+  pub(crate) fn record(&self, renamed: &Renamed) {
+    let mut file = self.0.lock().unwrap();
+    let mut line = serde_json::to_vec(renamed).unwrap();
+    line.push(b'\n');
+    if let Err(e) = file.write_all(&line) {
+      warn!(
+        "Failed to write the rename of {}/{} to {}: {e}",
+        renamed.owner, renamed.from, renamed.to
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn new_names_count_up_from_2() {
+    assert_eq!(new_name("repo", 0), "DELETE-repo");
+    assert_eq!(new_name("repo", 1), "DELETE-repo-2");
+    assert_eq!(new_name("repo", 2), "DELETE-repo-3");
+  }
+
+  #[test]
+  fn new_names_collide_across_repos() {
+    // A repo already renamed once gets names of its own
+    let names: Vec<_> = (0..3).map(|n| new_name("repo", n)).collect();
+    let renamed: Vec<_> = (0..3).map(|n| new_name("DELETE-repo", n)).collect();
+    assert!(names.iter().all(|name| !renamed.contains(name)));
+    // But the first name of `repo-2` is the second of `repo`, which is why
+    // `rename` looks up every name before taking it
+    assert_eq!(new_name("repo-2", 0), new_name("repo", 1));
+  }
+
+  #[test]
+  fn name_taken_in_message_or_errors() {
+    let error = |value| serde_json::from_value::<octocrab::GitHubError>(value).unwrap();
+    assert!(name_taken(&error(json!({
+      "message": "Repository creation failed.",
+      "errors": [{ "resource": "Repository", "message": "name already exists on this account" }],
+    }))));
+    assert!(name_taken(&error(json!({
+      "message": "name already exists on this account",
+    }))));
+    assert!(!name_taken(&error(json!({
+      "message": "Validation Failed",
+      "errors": [{ "resource": "Repository", "code": "invalid", "field": "name" }],
+    }))));
+  }
+
+  #[test]
+  fn manifest_appends() {
+    let path = std::env::temp_dir().join(format!("renamed-repos-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    for to in ["DELETE-a", "DELETE-a-2"] {
+      let manifest = RenameManifest::create(&path).unwrap();
+      manifest.record(&Renamed {
+        owner: "o",
+        from: "a",
+        to,
+      });
+    }
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+      content,
+      "{\"owner\":\"o\",\"from\":\"a\",\"to\":\"DELETE-a\"}\n\
+       {\"owner\":\"o\",\"from\":\"a\",\"to\":\"DELETE-a-2\"}\n"
+    );
+  }
+}
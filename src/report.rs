@@ -0,0 +1,534 @@
+//! The result object printed by `--output json`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+
+use crate::rate_limit::ApiUsage;
+use crate::{Candidate, DeleteError, Outcome, Target};
+
+/// Everything a run matched and deleted.
+#[derive(Serialize, Debug)]
+pub struct Report<'a> {
+  pub target: Target,
+  /// Full names of the matched repos, or of the packages
+  pub matched: Vec<String>,
+  /// Empty with `--report-only`
+  pub outcomes: &'a [Outcome],
+  /// Whether the deletion was stopped by Ctrl-C
+  pub interrupted: bool,
+  /// Stars of the matched repos, see [`crate::total_stars`]
+  pub total_stars: u64,
+  /// Size of the matched repos in bytes, see [`crate::total_size`]
+  pub total_size: u64,
+  /// API calls made, only with `--report-only`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub api_usage: Option<ApiUsage>,
+  /// The matched repos with some of their details, only with
+  /// `--json-include-metadata`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub repos: Option<Vec<RepoMetadata>>,
+}
+
+/// The details of a matched repo a [`Report`] includes, so consumers don't
+/// have to look them up again.
+#[derive(Serialize, Debug)]
+pub struct RepoMetadata {
+  pub full_name: String,
+  pub stars: u32,
+  /// In KB, as GitHub counts it
+  pub size: u32,
+  pub fork: bool,
+  pub visibility: String,
+  pub pushed_at: Option<DateTime<Utc>>,
+}
+
+impl From<&Candidate> for RepoMetadata {
+  fn from(candidate: &Candidate) -> Self {
+    let repo = &candidate.repo;
+    // Older GitHub Enterprise servers only return `private`
+    let visibility = match (&repo.visibility, repo.private) {
+      (Some(visibility), _) => visibility.clone(),
+      (None, Some(true)) => "private".to_string(),
+      (None, _) => "public".to_string(),
+    };
+    Self {
+      full_name: candidate.full_name(),
+      stars: repo.stargazers_count.unwrap_or(0),
+      size: repo.size.unwrap_or(0),
+      fork: repo.fork == Some(true),
+      visibility,
+      pushed_at: repo.pushed_at,
+    }
+  }
+}
+
+pub(crate) fn serialize_errors<S: Serializer>(
+  errors: &[DeleteError],
+  serializer: S,
+) -> Result<S::Ok, S::Error> {
+  serializer.collect_seq(errors.iter().map(|e| e.to_string()))
+}
+
+/// Full names of the outcomes of a [`Report`] read back as JSON that have
+/// errors, for retrying them. Fails if it is no report of `target`.
+pub fn failed_names(report: &Value, target: Target) -> Result<Vec<String>, String> {
+  let expected = serde_json::to_value(target).unwrap();
+  if report.get("target") != Some(&expected) {
+    return Err(format!(
+      "not a report of {expected}, run with the same --target"
+    ));
+  }
+  let outcomes = report
+    .get("outcomes")
+    .and_then(Value::as_array)
+    .ok_or("no outcomes, not a --output json report")?;
+  Ok(
+    outcomes
+      .iter()
+      .filter(|o| o["errors"].as_array().is_some_and(|e| !e.is_empty()))
+      .filter_map(|o| o["full_name"].as_str().map(str::to_string))
+      .collect(),
+  )
+}
+
+/// Replaces the outcomes of a [`Report`] read back as JSON by those in
+/// `outcomes` with the same name, so it records the latest attempt.
+pub fn update_outcomes(report: &mut Value, outcomes: &[Outcome]) {
+  let Some(recorded) = report.get_mut("outcomes").and_then(Value::as_array_mut) else {
+    return;
+  };
+  for outcome in outcomes {
+    let entry = recorded
+      .iter_mut()
+      .find(|o| o["full_name"].as_str() == Some(&outcome.full_name));
+    if let Some(entry) = entry {
+      *entry = serde_json::to_value(outcome).unwrap();
+    }
+  }
+}
+
+/// JSON Schema of [`Report`], kept next to it so both change together.
+pub fn schema() -> Value {
+  json!({
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "title": "Report",
+    "type": "object",
+    "required": [
+      "target",
+      "matched",
+      "outcomes",
+      "interrupted",
+      "total_stars",
+      "total_size",
+    ],
+    "additionalProperties": false,
+    "properties": {
+      "target": {
+        "enum": [
+          "repos",
+          "workflow-runs",
+          "releases",
+          "packages",
+          "pages",
+          "labels",
+          "subscriptions",
+          "stars",
+        ],
+      },
+      "matched": {
+        "type": "array",
+        "items": { "type": "string" },
+      },
+      "outcomes": {
+        "type": "array",
+        "items": {
+          "type": "object",
+          "required": ["full_name", "deleted", "skipped", "errors"],
+          "additionalProperties": false,
+          "properties": {
+            "full_name": { "type": "string" },
+            "deleted": { "type": "integer", "minimum": 0 },
+            "skipped": { "type": "integer", "minimum": 0 },
+            "errors": {
+              "type": "array",
+              "items": { "type": "string" },
+            },
+          },
+        },
+      },
+      "interrupted": { "type": "boolean" },
+      "total_stars": { "type": "integer", "minimum": 0 },
+      "total_size": { "type": "integer", "minimum": 0 },
+      "api_usage": {
+        "type": "object",
+        "required": ["calls", "graphql_calls", "remaining", "limit", "reset_at"],
+        "additionalProperties": false,
+        "properties": {
+          "calls": { "type": "integer", "minimum": 0 },
+          "graphql_calls": { "type": "integer", "minimum": 0 },
+          "remaining": { "type": "integer", "minimum": 0 },
+          "limit": { "type": "integer", "minimum": 0 },
+          "reset_at": { "type": "string", "format": "date-time" },
+        },
+      },
+      "repos": {
+        "type": "array",
+        "items": {
+          "type": "object",
+          "required": ["full_name", "stars", "size", "fork", "visibility", "pushed_at"],
+          "additionalProperties": false,
+          "properties": {
+            "full_name": { "type": "string" },
+            "stars": { "type": "integer", "minimum": 0 },
+            "size": { "type": "integer", "minimum": 0 },
+            "fork": { "type": "boolean" },
+            "visibility": { "type": "string" },
+            "pushed_at": { "type": ["string", "null"], "format": "date-time" },
+          },
+        },
+      },
+    },
+  })
+}
+
+/// What happened to `name`, for the human readable reports.
+fn status(outcomes: &HashMap<&str, &Outcome>, name: &str) -> String {
+  match outcomes.get(name) {
+    None if outcomes.is_empty() => "matched".to_string(),
+    None => "not selected".to_string(),
+    Some(o) if !o.errors.is_empty() => format!("failed: {}", o.errors[0]),
+    Some(o) if o.skipped > 0 => "skipped".to_string(),
+    Some(_) => "deleted".to_string(),
+  }
+}
+
+/// Escapes `|` so `text` stays in one table cell.
+fn cell(text: &str) -> String {
+  text.replace('|', "\\|")
+}
+
+/// A GitHub flavored markdown table of `candidates`, linked to their pages,
+/// with what happened to each according to `outcomes`, followed by totals
+/// per owner.
+pub fn markdown(candidates: &[Candidate], outcomes: &[Outcome]) -> String {
+  let outcomes: HashMap<_, _> = outcomes.iter().map(|o| (o.full_name.as_str(), o)).collect();
+  let mut owners: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+  let mut out = String::from("| Repo | Stars | Last push | Status |\n|---|---:|---|---|\n");
+  for candidate in candidates {
+    let name = candidate.full_name();
+    let stars = candidate.repo.stargazers_count.unwrap_or(0);
+    let repo = match &candidate.repo.html_url {
+      Some(url) => format!("[{}]({url})", cell(&name)),
+      None => cell(&name),
+    };
+    let pushed = candidate
+      .repo
+      .pushed_at
+      .map_or_else(|| "never".to_string(), |p| p.format("%Y-%m-%d").to_string());
+    let status = cell(&status(&outcomes, &name));
+    out += &format!("| {repo} | {stars} | {pushed} | {status} |\n");
+
+    let owner = name.split('/').next().unwrap_or_default().to_string();
+    let subtotal = owners.entry(owner).or_default();
+    subtotal.0 += 1;
+    subtotal.1 += u64::from(stars);
+  }
+
+  out += "\n| Owner | Repos | Stars |\n|---|---:|---:|\n";
+  for (owner, (repos, stars)) in &owners {
+    out += &format!("| {} | {repos} | {stars} |\n", cell(owner));
+  }
+  let stars: u64 = owners.values().map(|(_, stars)| stars).sum();
+  out += &format!("| **Total** | **{}** | **{stars}** |\n", candidates.len());
+  out
+}
+
+/// Repos of a single owner, see [`crate::Cleaner::owner_summaries`].
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct OwnerSummary {
+  pub owner: String,
+  pub repos: usize,
+  pub forks: usize,
+  pub archived: usize,
+  /// Not pushed since `--inactive`, 0 without it
+  pub stale: usize,
+  /// In KB, like GitHub reports it
+  pub size: u64,
+  pub stars: u64,
+}
+
+/// A GitHub flavored markdown table of `summaries`, with totals.
+pub fn owner_summary_markdown(summaries: &[OwnerSummary]) -> String {
+  let mut out = String::from(
+    "| Owner | Repos | Forks | Archived | Stale | Size (KB) | Stars |\n\
+    |---|---:|---:|---:|---:|---:|---:|\n",
+  );
+  let mut total = OwnerSummary::default();
+  for s in summaries {
+    out += &format!(
+      "| {} | {} | {} | {} | {} | {} | {} |\n",
+      cell(&s.owner),
+      s.repos,
+      s.forks,
+      s.archived,
+      s.stale,
+      s.size,
+      s.stars
+    );
+    total.repos += s.repos;
+    total.forks += s.forks;
+    total.archived += s.archived;
+    total.stale += s.stale;
+    total.size += s.size;
+    total.stars += s.stars;
+  }
+  out += &format!(
+    "| **Total** | **{}** | **{}** | **{}** | **{}** | **{}** | **{}** |\n",
+    total.repos, total.forks, total.archived, total.stale, total.size, total.stars
+  );
+  out
+}
+
+/// Quotes `field` for CSV if it has a comma, quote or line break, doubling
+/// its quotes.
+fn csv_field(field: &str) -> String {
+  if field.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", field.replace('"', "\"\""))
+  } else {
+    field.to_string()
+  }
+}
+
+/// A CSV table of `candidates` with a header, one row per repo with what
+/// happened to it according to `outcomes`.
+pub fn csv(candidates: &[Candidate], outcomes: &[Outcome]) -> String {
+  let outcomes: HashMap<_, _> = outcomes.iter().map(|o| (o.full_name.as_str(), o)).collect();
+  let mut out =
+    String::from("full_name,visibility,fork,archived,stars,size,pushed_at,language,status\n");
+  for candidate in candidates {
+    let repo = &candidate.repo;
+    let name = candidate.full_name();
+    let language = repo
+      .language
+      .as_ref()
+      .and_then(|l| l.as_str())
+      .unwrap_or_default();
+    let row = [
+      name.clone(),
+      repo.visibility.clone().unwrap_or_default(),
+      repo.fork.unwrap_or(false).to_string(),
+      repo.archived.unwrap_or(false).to_string(),
+      repo.stargazers_count.unwrap_or(0).to_string(),
+      repo.size.unwrap_or(0).to_string(),
+      repo.pushed_at.map(|p| p.to_rfc3339()).unwrap_or_default(),
+      language.to_string(),
+      status(&outcomes, &name),
+    ];
+    let row: Vec<_> = row.iter().map(|field| csv_field(field)).collect();
+    out += &row.join(",");
+    out += "\n";
+  }
+  out
+}
+
+/// Escapes `text` for HTML element content and attribute values.
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;margin:2em}\
+table{border-collapse:collapse;margin-bottom:2em}\
+th,td{border:1px solid #ccc;padding:.3em .6em;text-align:left}\
+th{background:#f3f3f3;cursor:pointer}code{background:#f3f3f3;padding:.1em .3em}";
+
+/// Sorts the table by the clicked column, numbers numerically.
+const HTML_SCRIPT: &str = "document.querySelectorAll('th').forEach((th,i)=>th.onclick=()=>{\
+const body=th.closest('table').tBodies[0];const rows=[...body.rows];\
+const asc=th.dataset.asc!=='1';th.dataset.asc=asc?'1':'0';\
+const key=r=>{const t=r.cells[i].innerText;return isNaN(t)||t===''?t:Number(t)};\
+rows.sort((a,b)=>(key(a)>key(b)?1:key(a)<key(b)?-1:0)*(asc?1:-1));\
+rows.forEach(r=>body.appendChild(r))})";
+
+/// A standalone HTML page with a sortable table of `candidates` and what
+/// happened to them, totals, and the `criteria` the run was started with.
+pub fn html(candidates: &[Candidate], outcomes: &[Outcome], criteria: &str) -> String {
+  let outcomes: HashMap<_, _> = outcomes.iter().map(|o| (o.full_name.as_str(), o)).collect();
+  let mut rows = String::new();
+  let mut stars_total = 0u64;
+  for candidate in candidates {
+    let name = escape(&candidate.full_name());
+    let stars = candidate.repo.stargazers_count.unwrap_or(0);
+    stars_total += u64::from(stars);
+    let repo = match &candidate.repo.html_url {
+      Some(url) => format!("<a href=\"{}\">{name}</a>", escape(url.as_str())),
+      None => name,
+    };
+    let pushed = candidate
+      .repo
+      .pushed_at
+      .map_or_else(|| "never".to_string(), |p| p.format("%Y-%m-%d").to_string());
+    let status = escape(&status(&outcomes, &candidate.full_name()));
+    rows +=
+      &format!("<tr><td>{repo}</td><td>{stars}</td><td>{pushed}</td><td>{status}</td></tr>\n");
+  }
+  let deleted = outcomes.values().filter(|o| o.deleted > 0).count();
+  let failed = outcomes.values().filter(|o| !o.errors.is_empty()).count();
+  format!(
+    "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>delete-unused-repo report</title>\
+    <style>{HTML_STYLE}</style></head><body>\n\
+    <h1>delete-unused-repo report</h1>\n\
+    <p>Run with <code>{}</code></p>\n\
+    <p>{} repos matched, ★{stars_total} in total, {deleted} deleted, {failed} failed</p>\n\
+    <table><thead><tr><th>Repo</th><th>Stars</th><th>Last push</th><th>Status</th></tr></thead>\
+    <tbody>\n{rows}</tbody></table>\n\
+    <script>{HTML_SCRIPT}</script></body></html>\n",
+    escape(criteria),
+    candidates.len(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::candidate;
+  use crate::DeleteError;
+
+  #[test]
+  fn csv_fields_are_quoted_when_needed() {
+    assert_eq!(csv_field("plain"), "plain");
+    assert_eq!(csv_field(""), "");
+    assert_eq!(csv_field("a,b"), "\"a,b\"");
+    assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+  }
+
+  #[test]
+  fn csv_rows_with_status() {
+    let candidates = [
+      candidate(
+        "me/a",
+        serde_json::json!({
+          "visibility": "public",
+          "fork": true,
+          "stargazers_count": 2,
+          "size": 10,
+          "pushed_at": "2022-01-01T00:00:00Z",
+          "language": "C, C++",
+        }),
+      ),
+      candidate("me/b", serde_json::json!({})),
+      candidate("me/c", serde_json::json!({})),
+    ];
+    let outcomes = [
+      Outcome {
+        full_name: "me/a".to_string(),
+        deleted: 0,
+        skipped: 0,
+        errors: vec![DeleteError::Backup("git said \"no\", twice".to_string())],
+      },
+      Outcome {
+        full_name: "me/b".to_string(),
+        deleted: 1,
+        skipped: 0,
+        errors: vec![],
+      },
+    ];
+    assert_eq!(
+      csv(&candidates, &outcomes),
+      "full_name,visibility,fork,archived,stars,size,pushed_at,language,status\n\
+       me/a,public,true,false,2,10,2022-01-01T00:00:00+00:00,\"C, C++\",\
+       \"failed: backup failed, not deleted: git said \"\"no\"\", twice\"\n\
+       me/b,,false,false,0,0,,,deleted\n\
+       me/c,,false,false,0,0,,,not selected\n"
+    );
+    assert!(csv(&candidates, &[]).ends_with("me/c,,false,false,0,0,,,matched\n"));
+  }
+
+  /// The JSON report of matching `candidates`, none deleted yet.
+  fn json_report(candidates: &[Candidate], repos: Option<Vec<RepoMetadata>>) -> Value {
+    let report = Report {
+      target: Target::Repos,
+      matched: candidates.iter().map(Candidate::full_name).collect(),
+      outcomes: &[],
+      interrupted: false,
+      total_stars: crate::total_stars(candidates),
+      total_size: crate::total_size(candidates),
+      api_usage: None,
+      repos,
+    };
+    serde_json::to_value(&report).unwrap()
+  }
+
+  #[test]
+  fn json_report_totals() {
+    let candidates = [
+      candidate(
+        "me/a",
+        serde_json::json!({ "stargazers_count": 2, "size": 10 }),
+      ),
+      candidate("me/b", serde_json::json!({ "stargazers_count": 3 })),
+      candidate("me/c", serde_json::json!({ "size": 1 })),
+    ];
+    let report = json_report(&candidates, None);
+    assert_eq!(report["total_stars"], 5);
+    assert_eq!(report["total_size"], 11 * 1024);
+    let empty = json_report(&[], None);
+    assert_eq!(
+      (&empty["total_stars"], &empty["total_size"]),
+      (&json!(0), &json!(0))
+    );
+    let required = &schema()["required"];
+    for field in ["total_stars", "total_size"] {
+      assert!(
+        required.as_array().unwrap().contains(&json!(field)),
+        "{field}"
+      );
+    }
+  }
+
+  #[test]
+  fn json_report_metadata_only_when_asked() {
+    let candidates = [
+      candidate(
+        "me/a",
+        serde_json::json!({ "visibility": "internal", "private": true, "fork": true,
+          "stargazers_count": 2, "size": 10, "pushed_at": "2022-01-01T00:00:00Z" }),
+      ),
+      candidate("me/b", serde_json::json!({ "private": true })),
+      candidate("me/c", serde_json::json!({ "private": false })),
+      candidate("me/d", serde_json::json!({})),
+    ];
+    let report = json_report(&candidates, None);
+    assert!(report.get("repos").is_none(), "{report}");
+    assert!(report.get("api_usage").is_none(), "{report}");
+
+    let repos = candidates.iter().map(RepoMetadata::from).collect();
+    let report = json_report(&candidates, Some(repos));
+    assert_eq!(
+      report["repos"][0],
+      json!({
+        "full_name": "me/a",
+        "stars": 2,
+        "size": 10,
+        "fork": true,
+        "visibility": "internal",
+        "pushed_at": "2022-01-01T00:00:00Z",
+      })
+    );
+    let visibilities: Vec<_> = report["repos"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|r| r["visibility"].as_str().unwrap())
+      .collect();
+    assert_eq!(visibilities, ["internal", "private", "public", "public"]);
+  }
+}
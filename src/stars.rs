@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use log::info;
+use octocrab::models::Repository;
+use octocrab::Octocrab;
+use serde::Deserialize;
+
+/// Media type of the starred listing wrapping each repo with `starred_at`,
+/// which the default one leaves out.
+const STAR_JSON: &str = "application/vnd.github.star+json";
+
+/// A repo of the starred listing with [`STAR_JSON`].
+#[derive(Deserialize, Debug)]
+struct Starred {
+  starred_at: DateTime<Utc>,
+  repo: Repository,
+}
+
+/// Lists the repos the authenticated user starred, whoever owns them, only
+/// those starred before `starred_before` if given.
+pub(crate) async fn list(
+  gh: &Octocrab,
+  per_page: u8,
+  starred_before: Option<DateTime<Utc>>,
+) -> octocrab::Result<Vec<Repository>> {
+  let mut starred = vec![];
+  let page_size = per_page.to_string();
+  for page in 1u32.. {
+    let request = gh
+      .request_builder(gh.absolute_url("user/starred")?, reqwest::Method::GET)
+      .header(reqwest::header::ACCEPT, STAR_JSON)
+      .query(&[
+        ("per_page", page_size.as_str()),
+        ("page", &page.to_string()),
+      ]);
+    let response = octocrab::map_github_error(crate::api_trace::send(gh, request).await?).await?;
+    let stars: Vec<Starred> = octocrab::FromResponse::from_response(response).await?;
+    let len = stars.len();
+    starred.extend(stars);
+    if len < usize::from(per_page) {
+      break;
+    }
+  }
+  Ok(starred_before_cutoff(starred, starred_before))
+}
+
+/// The repos of `starred` starred before `cutoff`, all of them without one.
+fn starred_before_cutoff(starred: Vec<Starred>, cutoff: Option<DateTime<Utc>>) -> Vec<Repository> {
+  starred
+    .into_iter()
+    .filter(|s| match cutoff {
+      Some(cutoff) if s.starred_at >= cutoff => {
+        let name = s.repo.full_name.as_deref().unwrap_or(&s.repo.name);
+        info!("Skip {name}, starred at {}", s.starred_at);
+        false
+      }
+      _ => true,
+    })
+    .map(|s| s.repo)
+    .collect()
+}
+
+/// Unstars `owner/repo`. Watching it is kept.
+pub(crate) async fn delete(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+) -> (crate::Reply, octocrab::Result<()>) {
+  crate::send_delete(gh, format!("user/starred/{owner}/{repo}")).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A page of `user/starred` with [`STAR_JSON`], as GitHub returns it.
+  const PAGE: &str = r#"[
+    {
+      "starred_at": "2020-05-01T10:00:00Z",
+      "repo": {
+        "id": 1,
+        "name": "old",
+        "full_name": "someone/old",
+        "url": "https://api.github.com/repos/someone/old"
+      }
+    },
+    {
+      "starred_at": "2022-05-01T10:00:00Z",
+      "repo": {
+        "id": 2,
+        "name": "new",
+        "full_name": "someone/new",
+        "url": "https://api.github.com/repos/someone/new"
+      }
+    }
+  ]"#;
+
+  fn at(text: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(text)
+      .unwrap()
+      .with_timezone(&Utc)
+  }
+
+  #[test]
+  fn parses_starred_at() {
+    let starred: Vec<Starred> = serde_json::from_str(PAGE).unwrap();
+    assert_eq!(starred[0].starred_at, at("2020-05-01T10:00:00Z"));
+    assert_eq!(starred[0].repo.name, "old");
+    assert_eq!(starred[1].starred_at, at("2022-05-01T10:00:00Z"));
+  }
+
+  #[test]
+  fn keeps_those_starred_before() {
+    let names = |cutoff| -> Vec<_> {
+      let starred: Vec<Starred> = serde_json::from_str(PAGE).unwrap();
+      starred_before_cutoff(starred, cutoff)
+        .into_iter()
+        .map(|r| r.name)
+        .collect()
+    };
+    assert_eq!(names(None), ["old", "new"]);
+    assert_eq!(names(Some(at("2021-01-01T00:00:00Z"))), ["old"]);
+    assert_eq!(names(Some(at("2022-05-01T10:00:00Z"))), ["old"]);
+    assert!(names(Some(at("2019-01-01T00:00:00Z"))).is_empty());
+  }
+}
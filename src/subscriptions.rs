@@ -0,0 +1,34 @@
+use octocrab::models::Repository;
+use octocrab::Octocrab;
+
+/// Lists the repos the authenticated user watches, whoever owns them.
+pub(crate) async fn list(gh: &Octocrab, per_page: u8) -> octocrab::Result<Vec<Repository>> {
+  let mut watched = vec![];
+  let page_size = per_page.to_string();
+  for page in 1u32.. {
+    let repos: Vec<Repository> = crate::api_trace::get_json(
+      gh,
+      "user/subscriptions",
+      Some(&[
+        ("per_page", page_size.as_str()),
+        ("page", &page.to_string()),
+      ]),
+    )
+    .await?;
+    let len = repos.len();
+    watched.extend(repos);
+    if len < usize::from(per_page) {
+      break;
+    }
+  }
+  Ok(watched)
+}
+
+/// Stops watching `owner/repo`, so its notifications stop. Stars are kept.
+pub(crate) async fn delete(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+) -> (crate::Reply, octocrab::Result<()>) {
+  crate::send_delete(gh, format!("repos/{owner}/{repo}/subscription")).await
+}
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use octocrab::Octocrab;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+struct WorkflowRuns {
+  workflow_runs: Vec<WorkflowRun>,
+}
+
+#[derive(Deserialize, Debug)]
+struct WorkflowRun {
+  id: u64,
+}
+
+/// Lists ids of every workflow run of `owner/repo` created before `before`.
+pub(crate) async fn list_old(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+  before: DateTime<Utc>,
+) -> octocrab::Result<Vec<u64>> {
+  let created = format!("<{}", before.format("%Y-%m-%dT%H:%M:%SZ"));
+  let mut ids = vec![];
+  for page in 1u32.. {
+    let runs: WorkflowRuns = crate::api_trace::get_json(
+      gh,
+      format!("repos/{owner}/{repo}/actions/runs"),
+      Some(&[
+        ("per_page", "100"),
+        ("page", &page.to_string()),
+        ("created", &created),
+      ]),
+    )
+    .await?;
+    let len = runs.workflow_runs.len();
+    ids.extend(runs.workflow_runs.into_iter().map(|r| r.id));
+    if len < 100 {
+      break;
+    }
+  }
+  Ok(ids)
+}
+
+pub(crate) async fn delete(
+  gh: &Octocrab,
+  owner: &str,
+  repo: &str,
+  id: u64,
+) -> (crate::Reply, octocrab::Result<()>) {
+  crate::send_delete(gh, format!("repos/{owner}/{repo}/actions/runs/{id}")).await
+}